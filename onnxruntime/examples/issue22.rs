@@ -4,7 +4,7 @@
 //! https://drive.google.com/file/d/1FmL-Wpm06V-8wgRqvV3Skey_X98Ue4D_/view?usp=sharing
 
 use ndarray::Array2;
-use onnxruntime::{environment::Environment, tensor::OrtOwnedTensor, GraphOptimizationLevel};
+use onnxruntime::{environment::Environment, tensor::DynOrtTensor, GraphOptimizationLevel};
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
 
@@ -20,7 +20,7 @@ fn main() {
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
     let env = Environment::builder().with_name("env").build().unwrap();
-    let mut session = env
+    let session = env
         .new_session_builder()
         .unwrap()
         .with_optimization_level(GraphOptimizationLevel::Basic)
@@ -34,7 +34,6 @@ fn main() {
     let input_ids = Array2::<i64>::from_shape_vec((1, 3), vec![1, 2, 3]).unwrap();
     let attention_mask = Array2::<i64>::from_shape_vec((1, 3), vec![1, 1, 1]).unwrap();
 
-    let outputs: Vec<OrtOwnedTensor<f32, _>> =
-        session.run(vec![input_ids, attention_mask]).unwrap();
-    print!("outputs: {:#?}", outputs);
+    let outputs: Vec<DynOrtTensor<_>> = session.run(vec![input_ids, attention_mask]).unwrap();
+    print!("outputs: {:#?}", outputs[0].try_extract::<f32>().unwrap());
 }
@@ -19,19 +19,43 @@ fn main() -> Result<(), Box<dyn Error>> {
         .with_optimization_level(onnxruntime::GraphOptimizationLevel::Basic)?
         .with_model_from_file(path)?;
 
+    let metadata = session.metadata()?;
+    println!("Metadata:");
+    println!(
+        "  producer = {}\n  graph name = {}\n  domain = {}\n  description = {}\n  version = {}",
+        metadata.producer_name,
+        metadata.graph_name,
+        metadata.domain,
+        metadata.description,
+        metadata.version
+    );
+    let mut custom: Vec<_> = metadata.custom.iter().collect();
+    custom.sort();
+    for (key, value) in custom {
+        println!("  {} = {}", key, value);
+    }
+
     println!("Inputs:");
     for (index, input) in session.inputs.iter().enumerate() {
         println!(
-            "  {}:\n    name = {}\n    type = {:?}\n    dimensions = {:?}",
-            index, input.name, input.input_type, input.dimensions
+            "  {}:\n    name = {}\n    type = {:?}",
+            index, input.name, input.value_type
         )
     }
 
     println!("Outputs:");
     for (index, output) in session.outputs.iter().enumerate() {
+        println!(
+            "  {}:\n    name = {}\n    type = {:?}",
+            index, output.name, output.value_type
+        );
+    }
+
+    println!("Overridable initializers:");
+    for (index, initializer) in session.overridable_initializers.iter().enumerate() {
         println!(
             "  {}:\n    name = {}\n    type = {:?}\n    dimensions = {:?}",
-            index, output.name, output.output_type, output.dimensions
+            index, initializer.name, initializer.initializer_type, initializer.dimensions
         );
     }
 
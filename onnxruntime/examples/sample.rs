@@ -1,8 +1,10 @@
 #![forbid(unsafe_code)]
 
 use onnxruntime::{
-    environment::Environment, ndarray::Array, tensor::OrtOwnedTensor, GraphOptimizationLevel,
-    LoggingLevel,
+    environment::Environment,
+    ndarray::Array,
+    tensor::{DynOrtTensor, OrtOwnedTensor},
+    GraphOptimizationLevel, LoggingLevel,
 };
 use tracing::Level;
 use tracing_subscriber::FmtSubscriber;
@@ -31,7 +33,7 @@ fn run() -> Result<(), Error> {
         .with_log_level(LoggingLevel::Info)
         .build()?;
 
-    let mut session = environment
+    let session = environment
         .new_session_builder()?
         .with_optimization_level(GraphOptimizationLevel::Basic)?
         .with_number_threads(1)?
@@ -54,18 +56,19 @@ fn run() -> Result<(), Error> {
     let n: u32 = session.inputs[0]
         .dimensions
         .iter()
-        .map(|d| d.unwrap())
+        .map(|d| d.size().unwrap())
         .product();
     let array = Array::linspace(0.0_f32, 1.0, n as usize)
         .into_shape(input0_shape)
         .unwrap();
     let input_tensor_values = vec![array];
 
-    let outputs: Vec<OrtOwnedTensor<f32, _>> = session.run(input_tensor_values)?;
+    let outputs: Vec<DynOrtTensor<_>> = session.run(input_tensor_values)?;
+    let output: OrtOwnedTensor<f32, _> = outputs[0].try_extract()?;
 
-    assert_eq!(outputs[0].shape(), output0_shape.as_slice());
+    assert_eq!(output.shape(), output0_shape.as_slice());
     for i in 0..5 {
-        println!("Score for class [{}] =  {}", i, outputs[0][[0, i, 0, 0]]);
+        println!("Score for class [{}] =  {}", i, output[[0, i, 0, 0]]);
     }
 
     Ok(())
@@ -0,0 +1,453 @@
+//! Module containing the asynchronous inference API
+//!
+//! Enabled with the `async` cargo feature.
+//!
+//! Inference calls are blocking. Performing them inside an async task stalls the executor's
+//! thread for the duration of the call; [`Session::run_async()`](../session/struct.Session.html#method.run_async)
+//! instead moves the call onto a dedicated thread pool and returns a [`RunFuture`](struct.RunFuture.html)
+//! resolving to the outputs. The future does not depend on a particular executor.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::{error::Error, sync::Arc};
+//! # use onnxruntime::{environment::Environment, tensor::OrtOwnedTensor, LoggingLevel};
+//! # async fn infer() -> Result<(), Box<dyn Error>> {
+//! # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+//! let session = Arc::new(
+//!     environment
+//!         .new_owned_session_builder()?
+//!         .with_model_from_file("squeezenet.onnx")?,
+//! );
+//!
+//! let array = ndarray::Array::linspace(0.0_f32, 1.0, 100);
+//! let outputs = session.run_async(vec![array]).await?;
+//! let output: OrtOwnedTensor<f32, _> = outputs[0].try_extract()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The pool runs at most [`max_inference_threads()`](fn.max_inference_threads.html) calls at the
+//! same time, by default the number of CPUs; further calls wait in a queue. The limit is changed
+//! with [`set_max_inference_threads()`](fn.set_max_inference_threads.html).
+
+use std::{
+    collections::VecDeque,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+    time::Duration,
+};
+
+use lazy_static::lazy_static;
+use tracing::{debug, error};
+
+use crate::{
+    error::{OrtError, Result},
+    run_options::{CancelHandle, RunOptions},
+    session::Session,
+    tensor::{DynOrtTensor, InputTensor},
+};
+
+/// Outputs of an inference call
+type RunOutput = Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>;
+
+impl Session<'static> {
+    /// Run the input data through the ONNX graph on the inference thread pool, returning a
+    /// future resolving to the outputs.
+    ///
+    /// The session must be shared through an `Arc`, since the inference call may outlive the
+    /// caller's borrow; use [`Environment::new_owned_session_builder()`](../environment/struct.Environment.html#method.new_owned_session_builder)
+    /// to create a `'static` session. Inputs are converted on the calling thread and moved to the
+    /// pool, so they can't borrow data. See [`run()`](#method.run) for the inputs and outputs.
+    ///
+    /// Dropping the future before it resolves cancels the inference call.
+    pub fn run_async<I>(self: &Arc<Self>, input_arrays: Vec<I>) -> RunFuture
+    where
+        I: Into<InputTensor<'static>>,
+    {
+        match RunOptions::new() {
+            Ok(run_options) => self.run_async_with_options(run_options, input_arrays),
+            Err(e) => RunFuture::ready(Err(e)),
+        }
+    }
+
+    /// Run the input data through the ONNX graph on the inference thread pool, configuring the
+    /// inference call with [`RunOptions`](../run_options/struct.RunOptions.html).
+    ///
+    /// See [`run_async()`](#method.run_async). Dropping the future before it resolves sets the
+    /// terminate flag of `run_options`, like [`CancelHandle::cancel()`](../run_options/struct.CancelHandle.html#method.cancel)
+    /// does.
+    pub fn run_async_with_options<I>(
+        self: &Arc<Self>,
+        run_options: RunOptions,
+        input_arrays: Vec<I>,
+    ) -> RunFuture
+    where
+        I: Into<InputTensor<'static>>,
+    {
+        let input_arrays: Vec<InputTensor<'static>> =
+            input_arrays.into_iter().map(Into::into).collect();
+        let session = Arc::clone(self);
+        let cancel_handle = run_options.cancel_handle();
+
+        RunFuture::spawn(cancel_handle, move || {
+            session.run_positional(Some(&run_options), input_arrays)
+        })
+    }
+}
+
+/// Future resolving to the outputs of an inference call performed on the inference thread pool
+///
+/// Returned by [`Session::run_async()`](../session/struct.Session.html#method.run_async).
+/// Dropping the future before it resolves requests the termination of the inference call.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled; dropping it cancels the inference call"]
+pub struct RunFuture {
+    shared: Arc<Mutex<RunState>>,
+    cancel_handle: Option<CancelHandle>,
+}
+
+/// State shared between a `RunFuture` and its inference call
+#[derive(Debug, Default)]
+struct RunState {
+    output: Option<RunOutput>,
+    waker: Option<Waker>,
+    /// The inference call returned (or panicked)
+    finished: bool,
+    /// The output was returned by `poll()`
+    consumed: bool,
+}
+
+impl RunFuture {
+    /// Create a future already resolved to `output`
+    fn ready(output: RunOutput) -> RunFuture {
+        let state = RunState {
+            output: Some(output),
+            finished: true,
+            ..RunState::default()
+        };
+        RunFuture {
+            shared: Arc::new(Mutex::new(state)),
+            cancel_handle: None,
+        }
+    }
+
+    /// Perform `run` on the inference thread pool
+    fn spawn<F>(cancel_handle: CancelHandle, run: F) -> RunFuture
+    where
+        F: FnOnce() -> RunOutput + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(RunState::default()));
+        let completion = Completion {
+            shared: Arc::clone(&shared),
+        };
+        let spawned = INFERENCE_POOL.spawn(Box::new(move || {
+            let output = run();
+            completion.complete(output);
+        }));
+        if let Err(e) = spawned {
+            return RunFuture::ready(Err(e));
+        }
+
+        RunFuture {
+            shared,
+            cancel_handle: Some(cancel_handle),
+        }
+    }
+}
+
+impl Future for RunFuture {
+    type Output = RunOutput;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.shared.lock().unwrap();
+        assert!(!state.consumed, "RunFuture polled after completion");
+        match state.output.take() {
+            Some(output) => {
+                state.consumed = true;
+                Poll::Ready(output)
+            }
+            None if state.finished => panic!("Inference call panicked"),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for RunFuture {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        let finished = self.shared.lock().unwrap().finished;
+        if let (false, Some(cancel_handle)) = (finished, &self.cancel_handle) {
+            debug!("Cancelling the inference call of a dropped RunFuture.");
+            if let Err(e) = cancel_handle.cancel() {
+                error!("Failed to cancel the inference call: {}", e);
+            }
+        }
+    }
+}
+
+/// Completion side of a `RunFuture`, waking it when the inference call returns
+///
+/// Dropped without completing if the inference call panicked, which still wakes the future.
+struct Completion {
+    shared: Arc<Mutex<RunState>>,
+}
+
+impl Completion {
+    fn complete(self, output: RunOutput) {
+        self.shared.lock().unwrap().output = Some(output);
+    }
+}
+
+impl Drop for Completion {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.shared.lock().unwrap();
+            state.finished = true;
+            state.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// Time after which a thread of the inference pool without work exits
+const IDLE_THREAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+lazy_static! {
+    static ref INFERENCE_POOL: Arc<ThreadPool> = Arc::new(ThreadPool::new(
+        thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+    ));
+}
+
+/// Maximum number of inference calls performed at the same time by the inference thread pool
+pub fn max_inference_threads() -> usize {
+    INFERENCE_POOL.queue.lock().unwrap().max_threads
+}
+
+/// Set the maximum number of inference calls performed at the same time by the inference
+/// thread pool, by default the number of CPUs
+///
+/// Running threads are not stopped when the limit is lowered; they exit once idle.
+///
+/// # Panics
+///
+/// Panics if `max_threads` is 0.
+pub fn set_max_inference_threads(max_threads: usize) {
+    assert!(max_threads > 0, "The inference thread pool needs a thread");
+    INFERENCE_POOL.queue.lock().unwrap().max_threads = max_threads;
+}
+
+/// Pool of threads dedicated to blocking inference calls
+///
+/// A thread is started whenever a job can't be picked up by an idle thread, up to `max_threads`
+/// threads; further jobs wait in the queue. Threads exit after being idle for
+/// `IDLE_THREAD_TIMEOUT`.
+struct ThreadPool {
+    queue: Mutex<JobQueue>,
+    condvar: Condvar,
+}
+
+struct JobQueue {
+    jobs: VecDeque<Job>,
+    idle_threads: usize,
+    /// Threads started and not exited yet
+    threads: usize,
+    max_threads: usize,
+}
+
+impl ThreadPool {
+    fn new(max_threads: usize) -> ThreadPool {
+        ThreadPool {
+            queue: Mutex::new(JobQueue {
+                jobs: VecDeque::new(),
+                idle_threads: 0,
+                threads: 0,
+                max_threads,
+            }),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Queue a job, starting a thread to perform it if needed
+    ///
+    /// Fails if no thread can perform the job, the job being dropped.
+    fn spawn(self: &Arc<Self>, job: Job) -> Result<()> {
+        let mut queue = self.queue.lock().unwrap();
+        queue.jobs.push_back(job);
+        if queue.jobs.len() <= queue.idle_threads {
+            self.condvar.notify_one();
+            return Ok(());
+        }
+        if queue.threads >= queue.max_threads {
+            debug!("Queueing the inference call until a thread is available.");
+            return Ok(());
+        }
+
+        let pool = Arc::clone(self);
+        let spawned = thread::Builder::new()
+            .name("onnxruntime-inference".to_string())
+            .spawn(move || pool.work());
+        match spawned {
+            Ok(_) => queue.threads += 1,
+            // The running threads will perform the job once available
+            Err(e) if queue.threads > 0 => {
+                error!(
+                    "Failed to spawn an inference thread, queueing the call: {}",
+                    e
+                );
+            }
+            Err(e) => {
+                queue.jobs.pop_back();
+                return Err(OrtError::SpawnThread(e));
+            }
+        }
+        Ok(())
+    }
+
+    fn work(&self) {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if let Some(job) = queue.jobs.pop_front() {
+                std::mem::drop(queue);
+                if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                    error!("Inference call panicked.");
+                }
+                queue = self.queue.lock().unwrap();
+                continue;
+            }
+
+            queue.idle_threads += 1;
+            let (guard, wait) = self
+                .condvar
+                .wait_timeout(queue, IDLE_THREAD_TIMEOUT)
+                .unwrap();
+            queue = guard;
+            queue.idle_threads -= 1;
+            if wait.timed_out() && queue.jobs.is_empty() {
+                queue.threads -= 1;
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrtError;
+    use std::{sync::mpsc, task::Wake};
+    use test_env_log::test;
+
+    struct ThreadWaker(thread::Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn run_future_resolves() {
+        let run_options = RunOptions::new().unwrap();
+        let futures: Vec<_> = (0..4)
+            .map(|_| {
+                RunFuture::spawn(run_options.cancel_handle(), || {
+                    thread::sleep(Duration::from_millis(50));
+                    Ok(Vec::new())
+                })
+            })
+            .collect();
+
+        for future in futures {
+            assert!(block_on(future).unwrap().is_empty());
+        }
+        assert!(!run_options.cancel_handle().is_cancelled());
+    }
+
+    #[test]
+    fn thread_pool_is_limited() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let pool = Arc::new(ThreadPool::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let (done_tx, done_rx) = mpsc::channel();
+        for _ in 0..6 {
+            let running = Arc::clone(&running);
+            let max_running = Arc::clone(&max_running);
+            let done_tx = done_tx.clone();
+            pool.spawn(Box::new(move || {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(20));
+                running.fetch_sub(1, Ordering::SeqCst);
+                done_tx.send(()).unwrap();
+            }))
+            .unwrap();
+        }
+
+        // Jobs beyond the limit are queued, not rejected
+        for _ in 0..6 {
+            done_rx.recv().unwrap();
+        }
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+        assert_eq!(pool.queue.lock().unwrap().threads, 2);
+    }
+
+    #[test]
+    fn run_future_ready() {
+        let future = RunFuture::ready(Err(OrtError::RunCancelled));
+        assert!(matches!(block_on(future), Err(OrtError::RunCancelled)));
+    }
+
+    #[test]
+    fn dropping_run_future_cancels() {
+        let run_options = RunOptions::new().unwrap();
+        let cancel_handle = run_options.cancel_handle();
+        let (started_tx, started_rx) = mpsc::channel();
+        let future = RunFuture::spawn(run_options.cancel_handle(), move || {
+            started_tx.send(()).unwrap();
+            while !cancel_handle.is_cancelled() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            Err(OrtError::RunCancelled)
+        });
+
+        started_rx.recv().unwrap();
+        std::mem::drop(future);
+        assert!(run_options.cancel_handle().is_cancelled());
+    }
+
+    #[test]
+    #[should_panic(expected = "Inference call panicked")]
+    fn run_future_panics_with_inference_call() {
+        let run_options = RunOptions::new().unwrap();
+        let future = RunFuture::spawn(run_options.cancel_handle(), || panic!("test panic"));
+        let _ = block_on(future);
+    }
+}
@@ -0,0 +1,380 @@
+//! Module containing a dynamic batcher, grouping the inference requests of many callers
+
+use std::{
+    collections::VecDeque,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, error};
+
+use crate::{
+    error::{NonMatchingDimensionsError, OrtError, Result},
+    session::Session,
+    tensor::{DynOrtTensor, InputTensor},
+};
+
+/// Outputs of an inference request
+type RequestOutput = Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>;
+
+/// Dynamic batcher, grouping the inference requests of many callers into a single inference call
+///
+/// Running a model once on a batch of samples is much faster than running it once per sample.
+/// A `Batcher` collects the requests submitted by concurrent callers with [`run()`](#method.run),
+/// concatenates their inputs along the first (batch) dimension, runs the session once and splits
+/// the outputs back to each caller.
+///
+/// A batch is run as soon as it contains [`max_batch_size`](struct.BatcherBuilder.html#method.with_max_batch_size)
+/// samples, or once its oldest request waited for [`max_latency`](struct.BatcherBuilder.html#method.with_max_latency).
+/// Only requests whose inputs have the same shape (except for the batch dimension) are grouped.
+///
+/// All the model's inputs and outputs must have a dynamic first dimension, which
+/// [`Input::dimensions`](../session/struct.Input.html#structfield.dimensions) reports as
+/// [`Dimension::Symbolic`](../session/enum.Dimension.html#variant.Symbolic) or
+/// [`Dimension::Unknown`](../session/enum.Dimension.html#variant.Unknown).
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::{error::Error, sync::Arc, time::Duration};
+/// # use onnxruntime::{batcher::Batcher, environment::Environment, LoggingLevel};
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+/// let session = environment
+///     .new_owned_session_builder()?
+///     .with_model_from_file("squeezenet.onnx")?;
+/// let batcher = Arc::new(
+///     Batcher::builder(Arc::new(session))
+///         .with_max_batch_size(16)
+///         .with_max_latency(Duration::from_millis(2))
+///         .build()?,
+/// );
+///
+/// // From each request handler, with a single sample:
+/// let array = ndarray::Array::<f32, _>::zeros((1, 3, 224, 224));
+/// let outputs = batcher.run(vec![array])?;
+/// let probabilities = outputs[0].try_extract::<f32>()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Batcher {
+    session: Arc<Session<'static>>,
+    /// Requests sent to the worker thread; `None` once the batcher is dropped
+    requests: Mutex<Option<mpsc::Sender<Request>>>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl Batcher {
+    /// Create a new [`BatcherBuilder`](struct.BatcherBuilder.html) grouping the requests to `session`
+    pub fn builder(session: Arc<Session<'static>>) -> BatcherBuilder {
+        BatcherBuilder {
+            session,
+            max_batch_size: 32,
+            max_latency: Duration::from_millis(5),
+        }
+    }
+
+    /// Run the inference on the inputs of a request, returning its outputs
+    ///
+    /// Blocks until the batch containing the request was run. Inputs are the same as for
+    /// [`Session::run()`](../session/struct.Session.html#method.run); their first dimension is the
+    /// number of samples of the request (usually one) and must be the same for all inputs. The
+    /// outputs only contain the request's samples.
+    ///
+    /// If the inference call of a batch fails, each of its requests receives an
+    /// [`OrtError::BatchedRun`](../error/enum.OrtError.html#variant.BatchedRun) error.
+    pub fn run<I>(&self, input_arrays: Vec<I>) -> RequestOutput
+    where
+        I: Into<InputTensor<'static>>,
+    {
+        let inputs: Vec<InputTensor<'static>> = input_arrays.into_iter().map(Into::into).collect();
+        self.session.validate_input_shapes(&inputs)?;
+        let batch_size = inputs.first().map_or(0, |input| input.shape()[0]);
+        for (input, model_input) in inputs.iter().zip(&self.session.inputs) {
+            if input.shape()[0] != batch_size {
+                return Err(OrtError::NonMatchingDimensions(
+                    NonMatchingDimensionsError::BatchSize {
+                        name: model_input.name.clone(),
+                        expected: batch_size,
+                        actual: input.shape()[0],
+                    },
+                ));
+            }
+        }
+
+        let (response_tx, response_rx) = mpsc::channel();
+        let request = Request {
+            inputs,
+            batch_size,
+            enqueued_at: Instant::now(),
+            response: response_tx,
+        };
+        self.requests
+            .lock()
+            .unwrap()
+            .as_ref()
+            .ok_or(OrtError::BatcherStopped)?
+            .send(request)
+            .map_err(|_| OrtError::BatcherStopped)?;
+
+        response_rx.recv().map_err(|_| OrtError::BatcherStopped)?
+    }
+}
+
+impl Drop for Batcher {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping the Batcher.");
+        // Closing the channel stops the worker once the pending requests are answered
+        self.requests.lock().unwrap().take();
+        if let Some(worker) = self.worker.take() {
+            if worker.join().is_err() {
+                error!("Batcher worker thread panicked.");
+            }
+        }
+    }
+}
+
+/// Struct used to build a [`Batcher`](struct.Batcher.html)
+///
+/// Created with [`Batcher::builder()`](struct.Batcher.html#method.builder).
+#[derive(Debug)]
+pub struct BatcherBuilder {
+    session: Arc<Session<'static>>,
+    max_batch_size: usize,
+    max_latency: Duration,
+}
+
+impl BatcherBuilder {
+    /// Configure the maximum number of samples run in a single inference call
+    ///
+    /// A single request containing more samples is run on its own. Defaults to 32.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> BatcherBuilder {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    /// Configure how long a request can wait for other requests to fill its batch
+    ///
+    /// Defaults to 5 milliseconds.
+    pub fn with_max_latency(mut self, max_latency: Duration) -> BatcherBuilder {
+        self.max_latency = max_latency;
+        self
+    }
+
+    /// Start the batcher's worker thread
+    ///
+    /// Returns an [`OrtError::NonBatchableModel`](../error/enum.OrtError.html#variant.NonBatchableModel)
+    /// error if one of the model's inputs or outputs does not have a dynamic first dimension, or
+    /// an [`OrtError::SpawnThread`](../error/enum.OrtError.html#variant.SpawnThread) error if the
+    /// worker thread cannot be spawned.
+    pub fn build(self) -> Result<Batcher> {
+        let dimensions = self
+            .session
+            .inputs
+            .iter()
+            .map(|input| (&input.name, &input.dimensions))
+            .chain(
+                self.session
+                    .outputs
+                    .iter()
+                    .map(|output| (&output.name, &output.dimensions)),
+            );
+        for (name, dimensions) in dimensions {
+            if !matches!(dimensions.first(), Some(d) if d.size().is_none()) {
+                error!("Model input or output {:?} is not batchable", name);
+                return Err(OrtError::NonBatchableModel { name: name.clone() });
+            }
+        }
+
+        let (requests_tx, requests_rx) = mpsc::channel();
+        let worker = Worker {
+            session: Arc::clone(&self.session),
+            requests: requests_rx,
+            pending: VecDeque::new(),
+            max_batch_size: self.max_batch_size,
+            max_latency: self.max_latency,
+        };
+        let worker = thread::Builder::new()
+            .name("onnxruntime-batcher".to_string())
+            .spawn(move || worker.run())
+            .map_err(OrtError::SpawnThread)?;
+
+        Ok(Batcher {
+            session: self.session,
+            requests: Mutex::new(Some(requests_tx)),
+            worker: Some(worker),
+        })
+    }
+}
+
+/// Inference request waiting to be batched
+#[derive(Debug)]
+struct Request {
+    inputs: Vec<InputTensor<'static>>,
+    /// Size of the first dimension of the inputs
+    batch_size: usize,
+    enqueued_at: Instant,
+    response: mpsc::Sender<RequestOutput>,
+}
+
+impl Request {
+    /// Return `true` if the inputs of both requests can be concatenated
+    fn is_compatible(&self, other: &Request) -> bool {
+        self.inputs
+            .iter()
+            .zip(&other.inputs)
+            .all(|(input, other_input)| input.shape()[1..] == other_input.shape()[1..])
+    }
+}
+
+/// Batcher's worker thread, grouping requests and running the session
+struct Worker {
+    session: Arc<Session<'static>>,
+    requests: mpsc::Receiver<Request>,
+    /// Requests received while filling a batch they could not be part of
+    pending: VecDeque<Request>,
+    max_batch_size: usize,
+    max_latency: Duration,
+}
+
+impl Worker {
+    fn run(mut self) {
+        while let Some(batch) = self.next_batch() {
+            self.run_batch(batch);
+        }
+        debug!("Batcher worker thread stopped.");
+    }
+
+    /// Wait for the next batch of requests, or return `None` once the batcher is dropped
+    fn next_batch(&mut self) -> Option<Vec<Request>> {
+        let first = match self.pending.pop_front() {
+            Some(request) => request,
+            None => self.requests.recv().ok()?,
+        };
+        let deadline = first.enqueued_at + self.max_latency;
+        let mut batch_size = first.batch_size;
+        let mut batch = vec![first];
+
+        let mut index = 0;
+        while index < self.pending.len() && batch_size < self.max_batch_size {
+            let request = &self.pending[index];
+            if request.is_compatible(&batch[0])
+                && batch_size + request.batch_size <= self.max_batch_size
+            {
+                let request = self.pending.remove(index).unwrap();
+                batch_size += request.batch_size;
+                batch.push(request);
+            } else {
+                index += 1;
+            }
+        }
+
+        while batch_size < self.max_batch_size {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let request = match self.requests.recv_timeout(timeout) {
+                Ok(request) => request,
+                // Once the batcher is dropped, the requests already received are still answered
+                Err(_) => break,
+            };
+            if !request.is_compatible(&batch[0]) {
+                self.pending.push_back(request);
+            } else if batch_size + request.batch_size > self.max_batch_size {
+                self.pending.push_back(request);
+                break;
+            } else {
+                batch_size += request.batch_size;
+                batch.push(request);
+            }
+        }
+
+        Some(batch)
+    }
+
+    fn run_batch(&self, batch: Vec<Request>) {
+        debug!(requests = batch.len(), "Running a batch.");
+        let batch_sizes: Vec<usize> = batch.iter().map(|request| request.batch_size).collect();
+        let (inputs, responses): (Vec<_>, Vec<_>) = batch
+            .into_iter()
+            .map(|request| (request.inputs, request.response))
+            .unzip();
+
+        if responses.len() == 1 {
+            // Nothing to concatenate or split
+            let inputs = inputs.into_iter().next().unwrap();
+            let _ = responses[0].send(self.session.run(inputs));
+            return;
+        }
+
+        match self.run_concatenated(inputs, &batch_sizes) {
+            Ok(outputs) => {
+                for (response, outputs) in responses.iter().zip(outputs) {
+                    // The caller may have stopped waiting
+                    let _ = response.send(Ok(outputs));
+                }
+            }
+            Err(e) => {
+                error!("Batched inference failed: {}", e);
+                let e = Arc::new(e);
+                for response in responses {
+                    let _ = response.send(Err(OrtError::BatchedRun(Arc::clone(&e))));
+                }
+            }
+        }
+    }
+
+    /// Run the session on the concatenated inputs of the requests, returning the outputs of
+    /// each request
+    fn run_concatenated(
+        &self,
+        inputs: Vec<Vec<InputTensor<'static>>>,
+        batch_sizes: &[usize],
+    ) -> Result<Vec<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>> {
+        let mut inputs_per_model_input: Vec<Vec<InputTensor>> = self
+            .session
+            .inputs
+            .iter()
+            .map(|_| Vec::with_capacity(inputs.len()))
+            .collect();
+        for request_inputs in inputs {
+            for (input, model_input) in request_inputs.into_iter().zip(&mut inputs_per_model_input)
+            {
+                model_input.push(input);
+            }
+        }
+        let batched_inputs = inputs_per_model_input
+            .into_iter()
+            .map(InputTensor::concatenate)
+            .collect::<Result<Vec<_>>>()?;
+
+        let batched_outputs = self.session.run(batched_inputs)?;
+
+        let total_batch_size = batch_sizes.iter().sum();
+        let mut outputs: Vec<Vec<DynOrtTensor<'static, ndarray::IxDyn>>> = batch_sizes
+            .iter()
+            .map(|_| Vec::with_capacity(batched_outputs.len()))
+            .collect();
+        for (batched_output, model_output) in batched_outputs.iter().zip(&self.session.outputs) {
+            let output_batch_size = batched_output.shape().first().copied().unwrap_or(0);
+            if output_batch_size != total_batch_size {
+                return Err(OrtError::NonMatchingDimensions(
+                    NonMatchingDimensionsError::BatchSize {
+                        name: model_output.name.clone(),
+                        expected: total_batch_size,
+                        actual: output_batch_size,
+                    },
+                ));
+            }
+
+            let split_outputs =
+                batched_output.split_batch(batch_sizes, self.session.allocator_ptr)?;
+            for (request_outputs, output) in outputs.iter_mut().zip(split_outputs) {
+                request_outputs.push(output);
+            }
+        }
+
+        Ok(outputs)
+    }
+}
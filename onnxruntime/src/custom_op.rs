@@ -0,0 +1,701 @@
+//! Module containing custom operators implemented in Rust
+//!
+//! Models can contain operators unknown to the runtime, in their own domain. Their kernels are
+//! provided by implementing [`CustomOp`](trait.CustomOp.html), creating one
+//! [`CustomOpKernel`](trait.CustomOpKernel.html) per node of the graph, and registering the
+//! operators with [`SessionBuilder::with_custom_op_domain()`](../session/struct.SessionBuilder.html#method.with_custom_op_domain).
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! # use onnxruntime::{
+//! #     custom_op::{CustomOp, CustomOpDomain, CustomOpError, CustomOpKernel, KernelContext, KernelInfo},
+//! #     environment::Environment, LoggingLevel, TensorElementDataType,
+//! # };
+//! /// Multiplies its input by its `alpha` attribute
+//! struct Scale;
+//!
+//! struct ScaleKernel {
+//!     alpha: f32,
+//! }
+//!
+//! impl CustomOp for Scale {
+//!     type Kernel = ScaleKernel;
+//!
+//!     fn name(&self) -> &str {
+//!         "Scale"
+//!     }
+//!
+//!     fn input_types(&self) -> Vec<TensorElementDataType> {
+//!         vec![TensorElementDataType::Float]
+//!     }
+//!
+//!     fn output_types(&self) -> Vec<TensorElementDataType> {
+//!         vec![TensorElementDataType::Float]
+//!     }
+//!
+//!     fn create_kernel(&self, info: &KernelInfo) -> Result<ScaleKernel, CustomOpError> {
+//!         Ok(ScaleKernel {
+//!             alpha: info.attribute_f32("alpha")?,
+//!         })
+//!     }
+//! }
+//!
+//! impl CustomOpKernel for ScaleKernel {
+//!     fn compute(&self, context: &mut KernelContext) -> Result<(), CustomOpError> {
+//!         let x = context.input::<f32>(0)?;
+//!         let mut y = context.output::<f32>(0, x.shape())?;
+//!         y.assign(&(&x * self.alpha));
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+//! let session = environment
+//!     .new_session_builder()?
+//!     .with_custom_op_domain(CustomOpDomain::new("com.example")?.with_op(Scale)?)?
+//!     .with_model_from_file("model.onnx")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Operators implemented in C or C++ and compiled into a shared library, like the ones of
+//! [onnxruntime-extensions](https://github.com/microsoft/onnxruntime-extensions), are loaded
+//! with [`SessionBuilder::with_custom_ops_library()`](../session/struct.SessionBuilder.html#method.with_custom_ops_library)
+//! instead.
+
+use std::{
+    ffi::{c_void, CString},
+    fmt::Debug,
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    ptr::NonNull,
+    sync::{Arc, Mutex},
+};
+
+use ndarray::{ArrayView, ArrayViewMut, IxDyn};
+use tracing::{debug, error};
+
+use onnxruntime_sys as sys;
+
+use crate::{
+    error::{call_ort, OrtError, Result},
+    g_ort,
+    session::get_tensor_type_and_shape,
+    TensorElementDataType, TypeToTensorElementDataType,
+};
+
+/// Error returned by custom operators, from any error type
+pub type CustomOpError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Errors of the operators of a [`CustomOpDomain`](struct.CustomOpDomain.html), written by the
+/// runtime's callbacks and reported by the session using the domain
+pub(crate) type CustomOpErrors = Arc<Mutex<Vec<String>>>;
+
+/// Operator implemented in Rust, registered in a [`CustomOpDomain`](struct.CustomOpDomain.html)
+///
+/// The runtime creates one kernel per node of the graph using the operator, when the session is
+/// built. The operator has a fixed number of inputs and outputs, all tensors of the given types.
+pub trait CustomOp: Send + Sync + 'static {
+    /// Kernel computing the operator for a node of the graph
+    type Kernel: CustomOpKernel;
+
+    /// Name of the operator, as used by the nodes of the graph (the ONNX `op_type`)
+    fn name(&self) -> &str;
+
+    /// Element types of the inputs
+    fn input_types(&self) -> Vec<TensorElementDataType>;
+
+    /// Element types of the outputs
+    fn output_types(&self) -> Vec<TensorElementDataType>;
+
+    /// Create the kernel of a node, reading its attributes from `info`
+    ///
+    /// Errors and panics make the creation of the session fail with `OrtError::CustomOp`.
+    fn create_kernel(&self, info: &KernelInfo) -> std::result::Result<Self::Kernel, CustomOpError>;
+}
+
+/// Kernel of a [`CustomOp`](trait.CustomOp.html), computing its outputs from its inputs
+///
+/// Like the session, a kernel can be used by concurrent inference calls.
+pub trait CustomOpKernel: Send + Sync + 'static {
+    /// Compute the outputs of the node
+    ///
+    /// The C API of the runtime gives kernels no way to report errors: errors and panics are
+    /// recorded, the inference call continues with the outputs which were not requested from
+    /// `context` left unwritten, then fails with `OrtError::CustomOp`. When inference calls run
+    /// concurrently on the session, the error may be reported by another of them.
+    fn compute(&self, context: &mut KernelContext) -> std::result::Result<(), CustomOpError>;
+}
+
+/// Attributes of a node, given to [`CustomOp::create_kernel()`](trait.CustomOp.html#tymethod.create_kernel)
+#[derive(Debug)]
+pub struct KernelInfo {
+    ptr: *const sys::OrtKernelInfo,
+}
+
+impl KernelInfo {
+    /// Read a float attribute
+    pub fn attribute_f32(&self, name: &str) -> Result<f32> {
+        let name = CString::new(name)?;
+        let mut value = 0.0;
+        unsafe {
+            call_ort(|ort| {
+                ort.KernelInfoGetAttribute_float.unwrap()(self.ptr, name.as_ptr(), &mut value)
+            })
+        }
+        .map_err(OrtError::KernelAttribute)?;
+        Ok(value)
+    }
+
+    /// Read an integer attribute
+    pub fn attribute_i64(&self, name: &str) -> Result<i64> {
+        let name = CString::new(name)?;
+        let mut value = 0;
+        unsafe {
+            call_ort(|ort| {
+                ort.KernelInfoGetAttribute_int64.unwrap()(self.ptr, name.as_ptr(), &mut value)
+            })
+        }
+        .map_err(OrtError::KernelAttribute)?;
+        Ok(value)
+    }
+
+    /// Read a string attribute
+    pub fn attribute_string(&self, name: &str) -> Result<String> {
+        let name = CString::new(name)?;
+        // The first call only reads the size of the string, including its null terminator
+        let mut size = 0;
+        unsafe {
+            call_ort(|ort| {
+                ort.KernelInfoGetAttribute_string.unwrap()(
+                    self.ptr,
+                    name.as_ptr(),
+                    std::ptr::null_mut(),
+                    &mut size,
+                )
+            })
+        }
+        .map_err(OrtError::KernelAttribute)?;
+
+        let mut value: Vec<u8> = vec![0; size as usize];
+        unsafe {
+            call_ort(|ort| {
+                ort.KernelInfoGetAttribute_string.unwrap()(
+                    self.ptr,
+                    name.as_ptr(),
+                    value.as_mut_ptr() as *mut c_char,
+                    &mut size,
+                )
+            })
+        }
+        .map_err(OrtError::KernelAttribute)?;
+        value.truncate((size as usize).saturating_sub(1));
+        String::from_utf8(value).map_err(OrtError::NonUtf8String)
+    }
+}
+
+/// Inputs and outputs of a node, given to [`CustomOpKernel::compute()`](trait.CustomOpKernel.html#tymethod.compute)
+///
+/// Inputs are viewed without copy for the whole computation. Outputs are allocated by the
+/// runtime once their shape is known, and written through mutable views.
+#[derive(Debug)]
+pub struct KernelContext<'k> {
+    ptr: *mut sys::OrtKernelContext,
+    _context: std::marker::PhantomData<&'k ()>,
+}
+
+impl<'k> KernelContext<'k> {
+    /// Number of inputs of the node
+    pub fn input_count(&self) -> Result<usize> {
+        let mut count = 0;
+        unsafe { call_ort(|ort| ort.KernelContext_GetInputCount.unwrap()(self.ptr, &mut count)) }
+            .map_err(OrtError::KernelContext)?;
+        Ok(count as usize)
+    }
+
+    /// Number of outputs of the node
+    pub fn output_count(&self) -> Result<usize> {
+        let mut count = 0;
+        unsafe { call_ort(|ort| ort.KernelContext_GetOutputCount.unwrap()(self.ptr, &mut count)) }
+            .map_err(OrtError::KernelContext)?;
+        Ok(count as usize)
+    }
+
+    /// View of the input at `index`, with elements of type `T`
+    ///
+    /// Returns an [`OrtError::NonMatchingOutputDataType`](../error/enum.OrtError.html#variant.NonMatchingOutputDataType)
+    /// error if `T` does not match the input's element type.
+    pub fn input<T>(&self, index: usize) -> Result<ArrayView<'k, T, IxDyn>>
+    where
+        T: TypeToTensorElementDataType + Debug + Clone,
+    {
+        let mut value_ptr: *const sys::OrtValue = std::ptr::null();
+        unsafe {
+            call_ort(|ort| {
+                ort.KernelContext_GetInput.unwrap()(self.ptr, index as u64, &mut value_ptr)
+            })
+        }
+        .map_err(OrtError::KernelContext)?;
+        assert_ne!(value_ptr, std::ptr::null());
+
+        let (data_ptr, shape) = unsafe { tensor_data::<T>(value_ptr as *mut sys::OrtValue)? };
+        Ok(unsafe { ArrayView::from_shape_ptr(shape, data_ptr) })
+    }
+
+    /// Allocate the output at `index` with the given shape, returning a view to write it
+    ///
+    /// The output's content is unspecified until written. Requesting the same output again
+    /// returns the same data, if the shape is unchanged.
+    pub fn output<T>(&mut self, index: usize, shape: &[usize]) -> Result<ArrayViewMut<'_, T, IxDyn>>
+    where
+        T: TypeToTensorElementDataType + Debug + Clone,
+    {
+        let dims: Vec<i64> = shape.iter().map(|&d| d as i64).collect();
+        let mut value_ptr: *mut sys::OrtValue = std::ptr::null_mut();
+        unsafe {
+            call_ort(|ort| {
+                ort.KernelContext_GetOutput.unwrap()(
+                    self.ptr,
+                    index as u64,
+                    dims.as_ptr(),
+                    dims.len() as u64,
+                    &mut value_ptr,
+                )
+            })
+        }
+        .map_err(OrtError::KernelContext)?;
+        assert_ne!(value_ptr, std::ptr::null_mut());
+
+        let (data_ptr, shape) = unsafe { tensor_data::<T>(value_ptr)? };
+        Ok(unsafe { ArrayViewMut::from_shape_ptr(shape, data_ptr) })
+    }
+}
+
+/// Pointer to the data of a tensor value, and its shape
+///
+/// Returns an error if `T` does not match the tensor's element type.
+unsafe fn tensor_data<T>(value_ptr: *mut sys::OrtValue) -> Result<(*mut T, IxDyn)>
+where
+    T: TypeToTensorElementDataType,
+{
+    let (data_type, shape) = get_tensor_type_and_shape(value_ptr)?;
+    let requested_type = T::tensor_element_data_type();
+    if requested_type != data_type {
+        return Err(OrtError::NonMatchingOutputDataType {
+            requested_type,
+            output_type: data_type,
+        });
+    }
+    if data_type == TensorElementDataType::String {
+        // Strings are stored in the runtime as C strings; they can't be viewed as `String`s.
+        return Err(OrtError::StringTensorNotSupported);
+    }
+
+    let mut data_ptr: *mut c_void = std::ptr::null_mut();
+    call_ort(|ort| ort.GetTensorMutableData.unwrap()(value_ptr, &mut data_ptr))
+        .map_err(OrtError::GetTensorMutableData)?;
+    // Empty tensors may have no data, but views need an aligned pointer
+    let data_ptr = match data_ptr as *mut T {
+        data_ptr if data_ptr.is_null() => NonNull::dangling().as_ptr(),
+        data_ptr => data_ptr,
+    };
+    Ok((data_ptr, IxDyn(&shape)))
+}
+
+/// Domain of custom operators, registered with [`SessionBuilder::with_custom_op_domain()`](../session/struct.SessionBuilder.html#method.with_custom_op_domain)
+///
+/// The operators are kept alive by the session using them.
+pub struct CustomOpDomain {
+    ptr: *mut sys::OrtCustomOpDomain,
+    name: String,
+    // Each operator's `OrtCustomOp` is referenced by the runtime, so it must not move
+    ops: Vec<Box<dyn Send + Sync>>,
+    errors: CustomOpErrors,
+}
+
+// The domain is only modified through `&mut self`, and only read by the runtime when a session
+// is built.
+unsafe impl Send for CustomOpDomain {}
+unsafe impl Sync for CustomOpDomain {}
+
+impl Debug for CustomOpDomain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CustomOpDomain")
+            .field("ptr", &self.ptr)
+            .field("name", &self.name)
+            .field("ops", &self.ops.len())
+            .finish()
+    }
+}
+
+impl CustomOpDomain {
+    /// Create an empty domain, like `com.example`
+    ///
+    /// The nodes using the domain's operators must have the same domain.
+    pub fn new(name: &str) -> Result<CustomOpDomain> {
+        let name_c = CString::new(name)?;
+        let mut ptr: *mut sys::OrtCustomOpDomain = std::ptr::null_mut();
+        unsafe { call_ort(|ort| ort.CreateCustomOpDomain.unwrap()(name_c.as_ptr(), &mut ptr)) }
+            .map_err(OrtError::CustomOpDomain)?;
+        assert_ne!(ptr, std::ptr::null_mut());
+        Ok(CustomOpDomain {
+            ptr,
+            name: name.to_string(),
+            ops: Vec::new(),
+            errors: CustomOpErrors::default(),
+        })
+    }
+
+    /// Add an operator to the domain
+    pub fn with_op<O>(mut self, op: O) -> Result<CustomOpDomain>
+    where
+        O: CustomOp,
+    {
+        let op = Box::new(CustomOpAdapter::new(op, self.errors.clone())?);
+        unsafe { call_ort(|ort| ort.CustomOpDomain_Add.unwrap()(self.ptr, &op.vtable)) }
+            .map_err(OrtError::CustomOpDomain)?;
+        self.ops.push(op);
+        Ok(self)
+    }
+
+    pub(crate) fn ptr(&self) -> *mut sys::OrtCustomOpDomain {
+        self.ptr
+    }
+
+    pub(crate) fn errors(&self) -> CustomOpErrors {
+        self.errors.clone()
+    }
+}
+
+/// Return the errors recorded by the operators of the domains since the last call, if any
+pub(crate) fn take_errors(errors: &[CustomOpErrors]) -> Result<()> {
+    let messages: Vec<String> = errors
+        .iter()
+        .flat_map(|errors| std::mem::take(&mut *errors.lock().unwrap()))
+        .collect();
+    if messages.is_empty() {
+        Ok(())
+    } else {
+        Err(OrtError::CustomOp(messages))
+    }
+}
+
+impl Drop for CustomOpDomain {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping the custom operator domain.");
+        unsafe { g_ort().ReleaseCustomOpDomain.unwrap()(self.ptr) };
+
+        self.ptr = std::ptr::null_mut();
+    }
+}
+
+/// Shared library of custom operators loaded by the runtime, unloaded when dropped
+///
+/// The library registers its operators through its `RegisterCustomOps` function. Its code is
+/// used by the sessions built with the operators, so it must be unloaded after them.
+#[derive(Debug)]
+pub(crate) struct CustomOpsLibrary {
+    handle: *mut c_void,
+    path: PathBuf,
+}
+
+// The handle is only used to unload the library, which requires exclusive ownership.
+unsafe impl Send for CustomOpsLibrary {}
+unsafe impl Sync for CustomOpsLibrary {}
+
+impl CustomOpsLibrary {
+    /// Load the library at `path`, registering its operators in the session options
+    ///
+    /// A bare file name, without directory, is looked up by the system's library loader.
+    pub(crate) fn register(
+        session_options_ptr: *mut sys::OrtSessionOptions,
+        path: &Path,
+    ) -> Result<CustomOpsLibrary> {
+        // The runtime reports a missing file as a generic loading failure
+        let has_directory = path.components().count() > 1;
+        if has_directory && !path.exists() {
+            return Err(OrtError::FileDoesNotExists {
+                filename: path.to_path_buf(),
+            });
+        }
+        let path_c = CString::new(path.to_str().ok_or_else(|| OrtError::NonUtf8Path {
+            path: path.to_path_buf(),
+        })?)?;
+
+        let mut handle: *mut c_void = std::ptr::null_mut();
+        let result = unsafe {
+            call_ort(|ort| {
+                ort.RegisterCustomOpsLibrary.unwrap()(
+                    session_options_ptr,
+                    path_c.as_ptr(),
+                    &mut handle,
+                )
+            })
+        };
+        // The library stays loaded if it has no `RegisterCustomOps` function or if it fails
+        let library = CustomOpsLibrary {
+            handle,
+            path: path.to_path_buf(),
+        };
+        result.map_err(|source| {
+            // No handle is returned when the library cannot be loaded, which for a bare name
+            // means that the loader did not find it
+            if library.handle.is_null() && !has_directory {
+                OrtError::FileDoesNotExists {
+                    filename: path.to_path_buf(),
+                }
+            } else {
+                OrtError::CustomOpsLibrary {
+                    path: path.to_path_buf(),
+                    source,
+                }
+            }
+        })?;
+        assert_ne!(library.handle, std::ptr::null_mut());
+        Ok(library)
+    }
+}
+
+impl Drop for CustomOpsLibrary {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        if self.handle.is_null() {
+            return;
+        }
+        debug!("Unloading the custom operators library.");
+        if !unsafe { unload_library(self.handle) } {
+            error!("Failed to unload custom operators library {:?}", self.path);
+        }
+
+        self.handle = std::ptr::null_mut();
+    }
+}
+
+/// Unload a library loaded by the runtime, returning if it succeeded
+#[cfg(not(target_family = "windows"))]
+unsafe fn unload_library(handle: *mut c_void) -> bool {
+    extern "C" {
+        fn dlclose(handle: *mut c_void) -> std::os::raw::c_int;
+    }
+    dlclose(handle) == 0
+}
+
+/// Unload a library loaded by the runtime, returning if it succeeded
+#[cfg(target_family = "windows")]
+unsafe fn unload_library(handle: *mut c_void) -> bool {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn FreeLibrary(module: *mut c_void) -> i32;
+    }
+    FreeLibrary(handle) != 0
+}
+
+/// Runtime's description of a [`CustomOp`](trait.CustomOp.html), forwarding its callbacks
+///
+/// The callbacks receive a pointer to `vtable`, the first field, from which the adapter is found.
+#[repr(C)]
+struct CustomOpAdapter<O>
+where
+    O: CustomOp,
+{
+    vtable: sys::OrtCustomOp,
+    name: CString,
+    input_types: Vec<sys::ONNXTensorElementDataType>,
+    output_types: Vec<sys::ONNXTensorElementDataType>,
+    op: O,
+    errors: CustomOpErrors,
+}
+
+/// Kernel created by a [`CustomOpAdapter`](struct.CustomOpAdapter.html), recording its errors
+/// with the ones of its domain
+struct KernelState<K> {
+    kernel: std::result::Result<K, String>,
+    errors: CustomOpErrors,
+}
+
+/// Log an error of a custom operator and record it to be reported by the session
+fn record_error(errors: &CustomOpErrors, message: String) {
+    error!("{}", message);
+    // Never panic in a callback of the runtime; the lock is not held by panicking code
+    if let Ok(mut errors) = errors.lock() {
+        errors.push(message);
+    }
+}
+
+impl<O> CustomOpAdapter<O>
+where
+    O: CustomOp,
+{
+    fn new(op: O, errors: CustomOpErrors) -> Result<CustomOpAdapter<O>> {
+        Ok(CustomOpAdapter {
+            vtable: sys::OrtCustomOp {
+                version: sys::ORT_API_VERSION,
+                CreateKernel: Some(Self::create_kernel),
+                GetName: Some(Self::get_name),
+                // Run on the CPU execution provider
+                GetExecutionProviderType: Some(Self::get_execution_provider_type),
+                GetInputType: Some(Self::get_input_type),
+                GetInputTypeCount: Some(Self::get_input_type_count),
+                GetOutputType: Some(Self::get_output_type),
+                GetOutputTypeCount: Some(Self::get_output_type_count),
+                KernelCompute: Some(Self::kernel_compute),
+                KernelDestroy: Some(Self::kernel_destroy),
+            },
+            name: CString::new(op.name())?,
+            input_types: op.input_types().into_iter().map(Into::into).collect(),
+            output_types: op.output_types().into_iter().map(Into::into).collect(),
+            op,
+            errors,
+        })
+    }
+
+    unsafe fn from_vtable<'o>(op: *const sys::OrtCustomOp) -> &'o CustomOpAdapter<O> {
+        &*(op as *const CustomOpAdapter<O>)
+    }
+
+    unsafe extern "C" fn create_kernel(
+        op: *const sys::OrtCustomOp,
+        _api: *const sys::OrtApi,
+        info: *const sys::OrtKernelInfo,
+    ) -> *mut c_void {
+        let adapter = Self::from_vtable(op);
+        let info = KernelInfo { ptr: info };
+        let kernel = catch_panic(|| adapter.op.create_kernel(&info)).map_err(|error| {
+            let message = format!(
+                "Failed to create kernel of custom operator {:?}: {}",
+                adapter.name, error
+            );
+            record_error(&adapter.errors, message.clone());
+            message
+        });
+        // The runtime does not expect kernel creation to fail: the session reports the recorded
+        // error once built, and the kernel reports it again on compute
+        let state = KernelState {
+            kernel,
+            errors: adapter.errors.clone(),
+        };
+        Box::into_raw(Box::new(state)) as *mut c_void
+    }
+
+    unsafe extern "C" fn get_name(op: *const sys::OrtCustomOp) -> *const c_char {
+        Self::from_vtable(op).name.as_ptr()
+    }
+
+    unsafe extern "C" fn get_execution_provider_type(
+        _op: *const sys::OrtCustomOp,
+    ) -> *const c_char {
+        std::ptr::null()
+    }
+
+    unsafe extern "C" fn get_input_type(
+        op: *const sys::OrtCustomOp,
+        index: sys::size_t,
+    ) -> sys::ONNXTensorElementDataType {
+        Self::from_vtable(op)
+            .input_types
+            .get(index as usize)
+            .copied()
+            .unwrap_or(sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED)
+    }
+
+    unsafe extern "C" fn get_input_type_count(op: *const sys::OrtCustomOp) -> sys::size_t {
+        Self::from_vtable(op).input_types.len() as sys::size_t
+    }
+
+    unsafe extern "C" fn get_output_type(
+        op: *const sys::OrtCustomOp,
+        index: sys::size_t,
+    ) -> sys::ONNXTensorElementDataType {
+        Self::from_vtable(op)
+            .output_types
+            .get(index as usize)
+            .copied()
+            .unwrap_or(sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED)
+    }
+
+    unsafe extern "C" fn get_output_type_count(op: *const sys::OrtCustomOp) -> sys::size_t {
+        Self::from_vtable(op).output_types.len() as sys::size_t
+    }
+
+    unsafe extern "C" fn kernel_compute(
+        op_kernel: *mut c_void,
+        context: *mut sys::OrtKernelContext,
+    ) {
+        let state = &*(op_kernel as *const KernelState<O::Kernel>);
+        let kernel = match &state.kernel {
+            Ok(kernel) => kernel,
+            Err(error) => {
+                let message = format!(
+                    "Cannot compute custom operator, kernel creation failed: {}",
+                    error
+                );
+                record_error(&state.errors, message);
+                return;
+            }
+        };
+        let mut context = KernelContext {
+            ptr: context,
+            _context: std::marker::PhantomData,
+        };
+        if let Err(error) = catch_panic(|| kernel.compute(&mut context)) {
+            let message = format!("Failed to compute custom operator: {}", error);
+            record_error(&state.errors, message);
+        }
+    }
+
+    unsafe extern "C" fn kernel_destroy(op_kernel: *mut c_void) {
+        let state = Box::from_raw(op_kernel as *mut KernelState<O::Kernel>);
+        if catch_panic(|| {
+            std::mem::drop(state);
+            Ok(())
+        })
+        .is_err()
+        {
+            error!("Custom operator kernel panicked when dropped");
+        }
+    }
+}
+
+/// Call `f`, turning a panic into an error so it does not unwind into the runtime
+fn catch_panic<T, F>(f: F) -> std::result::Result<T, CustomOpError>
+where
+    F: FnOnce() -> std::result::Result<T, CustomOpError>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(format!("panicked: {}", message).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn catch_panic_results() {
+        assert_eq!(catch_panic(|| Ok(42)).unwrap(), 42);
+        let error = catch_panic::<(), _>(|| Err("failure".into())).unwrap_err();
+        assert_eq!(error.to_string(), "failure");
+    }
+
+    #[test]
+    fn catch_panic_messages() {
+        let error = catch_panic::<(), _>(|| panic!("static message")).unwrap_err();
+        assert_eq!(error.to_string(), "panicked: static message");
+        let error = catch_panic::<(), _>(|| panic!("formatted {}", "message")).unwrap_err();
+        assert_eq!(error.to_string(), "panicked: formatted message");
+    }
+}
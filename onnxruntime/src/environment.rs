@@ -1,7 +1,9 @@
 //! Module containing environment types
 
 use std::{
+    borrow::Cow,
     ffi::CString,
+    path::{Path, PathBuf},
     sync::{atomic::AtomicPtr, Arc, Mutex},
 };
 
@@ -15,7 +17,7 @@ use crate::{
     g_ort,
     onnxruntime::custom_logger,
     session::SessionBuilder,
-    LoggingLevel,
+    GraphOptimizationLevel, LoggingLevel,
 };
 
 lazy_static! {
@@ -150,7 +152,56 @@ impl Environment {
     /// Create a new [`SessionBuilder`](../session/struct.SessionBuilder.html)
     /// used to create a new ONNX session.
     pub fn new_session_builder(&self) -> Result<SessionBuilder> {
-        SessionBuilder::new(self)
+        SessionBuilder::new(Cow::Borrowed(self))
+    }
+
+    /// Create a new [`SessionBuilder`](../session/struct.SessionBuilder.html)
+    /// holding a clone of the environment.
+    ///
+    /// The sessions it creates don't borrow the environment and are thus `'static`: they can be
+    /// stored in application state or moved into spawned threads. The runtime's environment is
+    /// kept alive until the last of them is dropped.
+    pub fn new_owned_session_builder(&self) -> Result<SessionBuilder<'static>> {
+        SessionBuilder::new(Cow::Owned(self.clone()))
+    }
+
+    /// Optimize a model with the given optimization level, writing it next to the original model
+    ///
+    /// The optimized model is named after the original one and the optimization level, for
+    /// example `squeezenet.basic.onnx` for `squeezenet.onnx`, and its path is returned. Load it
+    /// with optimizations disabled to skip the optimization at startup; see
+    /// [`SessionBuilder::with_optimized_model_output()`](../session/struct.SessionBuilder.html#method.with_optimized_model_output).
+    pub fn optimize_model<P>(
+        &self,
+        model_filepath: P,
+        optimization_level: GraphOptimizationLevel,
+    ) -> Result<PathBuf>
+    where
+        P: AsRef<Path>,
+    {
+        let model_filepath = model_filepath.as_ref();
+        let suffix = match optimization_level {
+            GraphOptimizationLevel::DisableAll => "disable_all",
+            GraphOptimizationLevel::Basic => "basic",
+            GraphOptimizationLevel::Extended => "extended",
+            GraphOptimizationLevel::All => "all",
+        };
+        let mut optimized_model_filename =
+            model_filepath.file_stem().unwrap_or_default().to_owned();
+        optimized_model_filename.push(format!(".{}.onnx", suffix));
+        let optimized_model_filepath = model_filepath.with_file_name(optimized_model_filename);
+
+        debug!(
+            ?model_filepath,
+            ?optimized_model_filepath,
+            "Optimizing model."
+        );
+        self.new_session_builder()?
+            .with_optimization_level(optimization_level)?
+            .with_optimized_model_output(&optimized_model_filepath)?
+            .with_model_from_file(model_filepath)?;
+
+        Ok(optimized_model_filepath)
     }
 }
 
@@ -294,6 +345,26 @@ mod tests {
         assert_eq!(G_ENV.env_ptr(), std::ptr::null_mut());
     }
 
+    #[test]
+    fn owned_session_builder_keeps_env_alive() {
+        let _run_lock = CONCURRENT_TEST_RUN.single_test_run();
+
+        let env = Environment::builder()
+            .with_name("owned_session_builder_keeps_env_alive")
+            .with_log_level(LoggingLevel::Warning)
+            .build()
+            .unwrap();
+        let session_builder = env.new_owned_session_builder().unwrap();
+
+        std::mem::drop(env);
+        assert!(G_ENV.is_initialized());
+        assert_ne!(G_ENV.env_ptr(), std::ptr::null_mut());
+
+        std::mem::drop(session_builder);
+        assert!(!G_ENV.is_initialized());
+        assert_eq!(G_ENV.env_ptr(), std::ptr::null_mut());
+    }
+
     #[ignore]
     #[test]
     fn sequential_environment_creation() {
@@ -1,12 +1,17 @@
 //! Module containing error definitions.
 
-use std::{io, path::PathBuf};
+use std::{io, path::PathBuf, sync::Arc, time::Duration};
 
 use thiserror::Error;
 
 use onnxruntime_sys as sys;
 
-use crate::{char_p_to_string, g_ort};
+use crate::{
+    char_p_to_string, g_ort,
+    session::{Dimension, ValueType},
+    value::ValueKind,
+    TensorElementDataType,
+};
 
 /// Type alias for the `Result`
 pub type Result<T> = std::result::Result<T, OrtError>;
@@ -44,6 +49,19 @@ pub enum OrtError {
     /// Error occurred when casting ONNX type information to tensor information
     #[error("Failed to cast type info to tensor info: {0}")]
     CastTypeInfoToTensorInfo(OrtApiError),
+    /// Error occurred when casting ONNX type information to map information
+    #[error("Failed to cast type info to map info: {0}")]
+    CastTypeInfoToMapTypeInfo(OrtApiError),
+    /// Error occurred when casting ONNX type information to sequence information
+    #[error("Failed to cast type info to sequence info: {0}")]
+    CastTypeInfoToSequenceTypeInfo(OrtApiError),
+    /// Model input or output is neither a tensor, a sequence nor a map
+    #[error("Unsupported value type {0:?}")]
+    UnsupportedValueType(ValueKind),
+    /// Tensor element type reported by the runtime has no `TensorElementDataType` equivalent,
+    /// like `bool` or `float16`
+    #[error("Unsupported tensor element type {0:?}")]
+    UnsupportedElementType(sys::ONNXTensorElementDataType),
     /// Error occurred when getting tensor elements type
     #[error("Failed to get tensor element type: {0}")]
     TensorElementType(OrtApiError),
@@ -53,9 +71,21 @@ pub enum OrtError {
     /// Error occurred when getting ONNX dimensions
     #[error("Failed to get dimensions: {0}")]
     GetDimensions(OrtApiError),
+    /// Error occurred when getting the names of ONNX symbolic dimensions
+    #[error("Failed to get symbolic dimensions: {0}")]
+    GetSymbolicDimensions(OrtApiError),
     /// Error occurred when creating CPU memory information
     #[error("Failed to get dimensions: {0}")]
     CreateCpuMemoryInfo(OrtApiError),
+    /// Error occurred when reading memory information
+    #[error("Failed to get memory information: {0}")]
+    MemoryInfo(OrtApiError),
+    /// Allocator type returned by the runtime which has no equivalent in the crate
+    #[error("Unsupported allocator type {0:?}")]
+    UnsupportedAllocatorType(sys::OrtAllocatorType),
+    /// Memory type returned by the runtime which has no equivalent in the crate
+    #[error("Unsupported memory type {0:?}")]
+    UnsupportedMemType(sys::OrtMemType),
     /// Error occurred when creating ONNX tensor
     #[error("Failed to create tensor: {0}")]
     CreateTensor(OrtApiError),
@@ -71,12 +101,99 @@ pub enum OrtError {
     /// Error occurred when getting tensor type and shape
     #[error("Failed to get tensor type and shape: {0}")]
     GetTensorTypeAndShape(OrtApiError),
+    /// Error occurred when creating or configuring ONNX run options
+    #[error("Failed to create or configure run options: {0}")]
+    RunOptions(OrtApiError),
     /// Error occurred when ONNX inference operation was called
     #[error("Failed to run: {0}")]
     Run(OrtApiError),
+    /// Error occurred when creating or using an ONNX I/O binding
+    #[error("Failed to bind inputs or outputs: {0}")]
+    IoBinding(OrtApiError),
+    /// Inference call was cancelled through its run options' [`CancelHandle`](../run_options/struct.CancelHandle.html)
+    #[error("Run was cancelled")]
+    RunCancelled,
+    /// Inference call did not complete within its run options' timeout
+    #[error("Run did not complete within {timeout:?}")]
+    RunTimedOut {
+        /// Timeout configured on the run options
+        timeout: Duration,
+    },
+    /// Failed to spawn a thread of the crate, like the watchdog enforcing run timeouts, the
+    /// inference threads of `Session::run_async()` or the worker thread of a `Batcher`
+    #[error("Failed to spawn thread: {0}")]
+    SpawnThread(std::io::Error),
     /// Error occurred when extracting data from an ONNX tensor into an C array to be used as an `ndarray::ArrayView`
     #[error("Failed to get tensor data: {0}")]
     GetTensorMutableData(OrtApiError),
+    /// Element type requested when extracting an output tensor does not match the tensor's element type
+    #[error("Cannot extract output of type {output_type:?} as {requested_type:?}")]
+    NonMatchingOutputDataType {
+        /// Element type requested by the caller
+        requested_type: TensorElementDataType,
+        /// Element type of the output tensor
+        output_type: TensorElementDataType,
+    },
+    /// Error occurred when reading the content of a tensor of strings
+    #[error("Failed to get string tensor content: {0}")]
+    GetStringTensorContent(OrtApiError),
+    /// Tensor of strings contains invalid UTF-8
+    #[error("String tensor content is not valid UTF-8: {0}")]
+    NonUtf8String(std::string::FromUtf8Error),
+    /// Error occurred when creating a sequence or a map
+    #[error("Failed to create value: {0}")]
+    CreateValue(OrtApiError),
+    /// Error occurred when getting the kind or the elements of a sequence or a map
+    #[error("Failed to get value: {0}")]
+    GetValue(OrtApiError),
+    /// Kind of value requested when extracting an output does not match the value's kind
+    #[error("Cannot extract output of kind {kind:?} as {requested:?}")]
+    NonMatchingValueKind {
+        /// Kind of value requested by the caller
+        requested: ValueKind,
+        /// Kind of the output value
+        kind: ValueKind,
+    },
+    /// Operation only supports tensors but the model's input or output is a sequence or a map
+    #[error(
+        "Input or output {name:?} is not a tensor but a {value_type:?}, use Session::run_values()"
+    )]
+    NonTensorValue {
+        /// Name of the input or output
+        name: String,
+        /// Type of the input or output in the model
+        value_type: ValueType,
+    },
+    /// Value given for an input does not match the input's type in the model
+    #[error("Non-matching value for input {name:?}, expected {model_type:?}")]
+    NonMatchingValueType {
+        /// Name of the input
+        name: String,
+        /// Type of the input in the model
+        model_type: ValueType,
+    },
+    /// Operation does not support tensors of strings, like extracting an output tensor or
+    /// providing an initializer
+    #[error("String tensors are not supported by this operation")]
+    StringTensorNotSupported,
+    /// Array passed to the runtime without copy is not contiguous in memory or not in standard layout
+    #[error("Array is not contiguous or not in standard layout")]
+    NonContiguousArray,
+    /// Shape given for the data of a tensor is not valid
+    #[error("Invalid shape: {0}")]
+    InvalidShape(ndarray::ShapeError),
+    /// Model input or output used by a [`Batcher`](../batcher/struct.Batcher.html) has no dynamic first dimension
+    #[error("Input or output {name:?} has no dynamic batch dimension")]
+    NonBatchableModel {
+        /// Name of the input or output
+        name: String,
+    },
+    /// Inference call grouping the requests of a [`Batcher`](../batcher/struct.Batcher.html) failed
+    #[error("Batched inference failed: {0}")]
+    BatchedRun(Arc<OrtError>),
+    /// [`Batcher`](../batcher/struct.Batcher.html) stopped before answering the request
+    #[error("Batcher is stopped")]
+    BatcherStopped,
 
     /// Error occurred when downloading a pre-trained ONNX model from the [ONNX Model Zoo](https://github.com/onnx/models)
     #[error("Failed to download ONNX model: {0}")]
@@ -85,6 +202,46 @@ pub enum OrtError {
     /// Dimensions of input data and ONNX model loaded from file do not match
     #[error("Dimensions do not match: {0:?}")]
     NonMatchingDimensions(NonMatchingDimensionsError),
+    /// Element type of input data and ONNX model loaded from file do not match
+    #[error("Data types do not match for input {name:?}: {inference_type:?} for input vs {model_type:?} for model")]
+    NonMatchingDataType {
+        /// Name of the input
+        name: String,
+        /// Element type of the input used by inference call
+        inference_type: TensorElementDataType,
+        /// Element type of the input defined in model
+        model_type: TensorElementDataType,
+    },
+    /// Input name used by inference call is not one of the model's inputs
+    #[error("Unknown input name {name:?}")]
+    UnknownInputName {
+        /// Name which is not an input of the model
+        name: String,
+    },
+    /// Initializer name used by inference call is not one of the model's overridable initializers
+    #[error("Unknown overridable initializer name {name:?}")]
+    UnknownInitializerName {
+        /// Name which is not an overridable initializer of the model
+        name: String,
+    },
+    /// Input name used more than once by inference call
+    #[error("Input {name:?} given more than once")]
+    DuplicateInputName {
+        /// Name of the input given multiple times
+        name: String,
+    },
+    /// Model input not provided by inference call
+    #[error("Missing input {name:?}")]
+    MissingInput {
+        /// Name of the model's input which was not provided
+        name: String,
+    },
+    /// Output name requested by inference call is not one of the model's outputs
+    #[error("Unknown output name {name:?}")]
+    UnknownOutputName {
+        /// Name which is not an output of the model
+        name: String,
+    },
     /// File does not exists
     #[error("File {filename:?} does not exists")]
     FileDoesNotExists {
@@ -97,6 +254,70 @@ pub enum OrtError {
         /// Path with invalid UTF-8
         path: PathBuf,
     },
+    /// Error occurred when creating a domain of custom operators, adding operators to it or
+    /// registering it
+    #[error("Failed to register custom operators: {0}")]
+    CustomOpDomain(OrtApiError),
+    /// Shared library of custom operators could not be loaded, has no `RegisterCustomOps`
+    /// function or failed to register its operators
+    #[error("Failed to register custom operators library {path:?}: {source}")]
+    CustomOpsLibrary {
+        /// Path of the library
+        path: PathBuf,
+        /// Error reported by the runtime
+        source: OrtApiError,
+    },
+    /// Custom operators implemented in Rust failed to create their kernels or to compute their
+    /// outputs, or panicked
+    #[error("Custom operator failed: {}", .0.join("; "))]
+    CustomOp(Vec<String>),
+    /// Error occurred when reading an attribute of a custom operator's node
+    #[error("Failed to get custom operator attribute: {0}")]
+    KernelAttribute(OrtApiError),
+    /// Error occurred when accessing the inputs or outputs of a custom operator's node
+    #[error("Failed to get custom operator input or output: {0}")]
+    KernelContext(OrtApiError),
+    /// Error occurred when listing the execution providers of the runtime
+    #[error("Failed to get available execution providers: {0}")]
+    GetAvailableProviders(OrtApiError),
+    /// Execution provider is not compiled into the runtime library
+    #[error("Execution provider {provider} is not available (available: {available:?})")]
+    ExecutionProviderNotAvailable {
+        /// Name of the requested provider
+        provider: &'static str,
+        /// Names of the available providers
+        available: Vec<String>,
+    },
+    /// Execution provider failed to initialize, for example on a host without GPU
+    #[error("Failed to append execution provider {provider}: {source}")]
+    AppendExecutionProvider {
+        /// Name of the provider
+        provider: &'static str,
+        /// Error reported by the runtime
+        source: OrtApiError,
+    },
+    /// Error occurred when getting the metadata of a model
+    #[error("Failed to get model metadata: {0}")]
+    ModelMetadata(OrtApiError),
+    /// Error occurred when getting the profiling results of a session
+    #[error("Failed to get profiling results: {0}")]
+    Profiling(OrtApiError),
+    /// Profiling results requested from a session which was not built with profiling enabled
+    #[error("Profiling is not enabled on the session")]
+    ProfilingNotEnabled,
+    /// Profiling trace could not be read
+    #[cfg(feature = "profiling")]
+    #[error("Failed to read profiling trace {path:?}: {source}")]
+    ProfileRead {
+        /// Path of the trace
+        path: PathBuf,
+        /// Error reading the trace
+        source: io::Error,
+    },
+    /// Profiling trace is not valid JSON, or not in the runtime's format
+    #[cfg(feature = "profiling")]
+    #[error("Failed to parse profiling trace: {0}")]
+    ProfileParsing(serde_json::Error),
     /// Attempt to build a Rust `CString` from a null pointer
     #[error("Failed to build CString when original contains null: {0}")]
     CStringNulError(#[from] std::ffi::NulError),
@@ -117,7 +338,69 @@ pub enum NonMatchingDimensionsError {
         /// Input dimensions used by inference call
         inference_input: Vec<Vec<usize>>,
         /// Input dimensions defined in model
-        model_input: Vec<Vec<Option<u32>>>,
+        model_input: Vec<Vec<Dimension>>,
+    },
+    /// Shape of an input from inference call does not match the model's input shape
+    #[error("Non-matching shape for input {name:?}: {inference_input:?} for input vs {model_input:?} for model")]
+    InputShape {
+        /// Name of the input
+        name: String,
+        /// Input shape used by inference call
+        inference_input: Vec<usize>,
+        /// Input dimensions defined in model
+        model_input: Vec<Dimension>,
+    },
+    /// Number of output buffers does not match number of outputs from model
+    #[error("Non-matching number of outputs: {output_buffer_count:?} buffers vs {model_output_count:?} for model")]
+    OutputsCount {
+        /// Number of output buffers given to the inference call
+        output_buffer_count: usize,
+        /// Number of outputs defined in model
+        model_output_count: usize,
+    },
+    /// Shape of an output buffer does not match the model's fixed output dimensions
+    #[error("Non-matching shape for output {name:?}: {output_buffer:?} for buffer vs {model_output:?} for model")]
+    OutputShape {
+        /// Name of the output
+        name: String,
+        /// Shape of the output buffer
+        output_buffer: Vec<usize>,
+        /// Output dimensions defined in model
+        model_output: Vec<Dimension>,
+    },
+    /// Inputs sharing a symbolic dimension have different sizes for it
+    #[error("Non-matching sizes for symbolic dimension {dimension:?}: {size:?} for input {name:?} vs {previous_size:?} for input {previous_name:?}")]
+    SymbolicDimension {
+        /// Name of the symbolic dimension
+        dimension: String,
+        /// Name of the input with a different size for the dimension
+        name: String,
+        /// Size of the dimension in that input
+        size: usize,
+        /// Name of a previous input sharing the dimension
+        previous_name: String,
+        /// Size of the dimension in the previous input
+        previous_size: usize,
+    },
+    /// Size of the first (batch) dimension of an input or output does not match the batch size
+    #[error("Non-matching batch size for {name:?}: {actual:?} vs {expected:?} expected")]
+    BatchSize {
+        /// Name of the input or output
+        name: String,
+        /// Expected batch size
+        expected: usize,
+        /// Size of the first dimension
+        actual: usize,
+    },
+    /// Shape of an output buffer does not match the shape of the output computed for dynamic dimensions
+    #[error("Non-matching shape for output {name:?}: {output_buffer:?} for buffer vs {inference_output:?} computed by inference")]
+    DynamicOutputShape {
+        /// Name of the output
+        name: String,
+        /// Shape of the output buffer
+        output_buffer: Vec<usize>,
+        /// Shape of the output computed by the inference call
+        inference_output: Vec<usize>,
     },
 }
 
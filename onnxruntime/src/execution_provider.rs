@@ -0,0 +1,307 @@
+//! Module containing the execution providers: the backends (CPU, CUDA, OpenVINO, ...) which run
+//! the nodes of a model
+//!
+//! The providers compiled into the runtime library are listed by [`available_providers()`](fn.available_providers.html).
+//! A session is given an ordered preference list with
+//! [`SessionBuilder::with_execution_providers()`](../session/struct.SessionBuilder.html#method.with_execution_providers):
+//! each node of the model is assigned to the first provider supporting it, and the CPU provider
+//! always comes last to handle the remaining nodes.
+//!
+//! Providers missing from the runtime library, or failing to initialize (for example on a host
+//! without GPU), are skipped with a warning or reported as an error, depending on the
+//! [`ProviderFallback`](enum.ProviderFallback.html) policy. With
+//! [`ProviderFallback::Skip`](enum.ProviderFallback.html#variant.Skip), a session which can't be
+//! created with the remaining providers is created again on the CPU alone, so the same
+//! application runs on every host, falling back to the CPU where nothing else is usable:
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! # use onnxruntime::{
+//! #     environment::Environment,
+//! #     execution_provider::{CudaProviderOptions, ExecutionProvider, ProviderFallback},
+//! # };
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # let environment = Environment::builder().build()?;
+//! let session = environment
+//!     .new_session_builder()?
+//!     .with_execution_providers(
+//!         vec![ExecutionProvider::Cuda(CudaProviderOptions::default())],
+//!         ProviderFallback::Skip,
+//!     )?
+//!     .with_model_from_file("model.onnx")?;
+//! println!("Running on {:?}", session.execution_providers);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{ffi::CString, os::raw::c_char};
+
+use tracing::{debug, warn};
+
+use onnxruntime_sys as sys;
+
+use crate::{
+    char_p_to_string,
+    error::{call_ort, OrtError, Result},
+};
+
+/// Name of the CPU provider, always available
+const CPU_PROVIDER_NAME: &str = "CPUExecutionProvider";
+
+/// List the names of the execution providers compiled into the runtime library, like
+/// `CUDAExecutionProvider` or `CPUExecutionProvider`
+///
+/// A provider being listed does not mean it can be used on this host: the CUDA provider needs
+/// a GPU, for example.
+pub fn available_providers() -> Result<Vec<String>> {
+    let mut providers_ptr: *mut *mut c_char = std::ptr::null_mut();
+    let mut num_providers = 0;
+    unsafe {
+        call_ort(|ort| ort.GetAvailableProviders.unwrap()(&mut providers_ptr, &mut num_providers))
+    }
+    .map_err(OrtError::GetAvailableProviders)?;
+    assert_ne!(providers_ptr, std::ptr::null_mut());
+
+    // Release the list even if one of the names fails to be converted
+    let providers = (0..num_providers as usize)
+        .map(|i| char_p_to_string(unsafe { *providers_ptr.add(i) }))
+        .collect();
+    unsafe { call_ort(|ort| ort.ReleaseAvailableProviders.unwrap()(providers_ptr, num_providers)) }
+        .map_err(OrtError::GetAvailableProviders)?;
+
+    providers
+}
+
+/// Execution provider of a session, with its options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionProvider {
+    /// Default provider, always available and supporting every operator
+    Cpu,
+    /// NVIDIA GPUs through CUDA
+    Cuda(CudaProviderOptions),
+    /// Intel CPUs, GPUs and VPUs through OpenVINO
+    OpenVino(OpenVinoProviderOptions),
+}
+
+impl ExecutionProvider {
+    /// Name of the provider in the runtime, as listed by [`available_providers()`](fn.available_providers.html)
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExecutionProvider::Cpu => CPU_PROVIDER_NAME,
+            ExecutionProvider::Cuda(_) => "CUDAExecutionProvider",
+            ExecutionProvider::OpenVino(_) => "OpenVINOExecutionProvider",
+        }
+    }
+
+    /// Whether the provider is compiled into the runtime library
+    pub fn is_available(&self) -> Result<bool> {
+        Ok(available_providers()?
+            .iter()
+            .any(|name| name == self.name()))
+    }
+
+    /// Append the provider to the session options
+    fn append(&self, session_options_ptr: *mut sys::OrtSessionOptions) -> Result<()> {
+        let result = match self {
+            // The runtime always appends the CPU provider after the other ones
+            ExecutionProvider::Cpu => Ok(()),
+            ExecutionProvider::Cuda(options) => {
+                let options = options.to_sys();
+                unsafe {
+                    call_ort(|ort| {
+                        ort.SessionOptionsAppendExecutionProvider_CUDA.unwrap()(
+                            session_options_ptr,
+                            &options,
+                        )
+                    })
+                }
+            }
+            ExecutionProvider::OpenVino(options) => {
+                // The runtime copies the strings, which only need to outlive the call
+                let device_type = options.device_type.clone().map(CString::new).transpose()?;
+                let device_id = options.device_id.clone().map(CString::new).transpose()?;
+                let options = sys::OrtOpenVINOProviderOptions {
+                    device_type: c_str_or_empty(&device_type),
+                    enable_vpu_fast_compile: options.enable_vpu_fast_compile as u8,
+                    device_id: c_str_or_empty(&device_id),
+                    num_of_threads: options.num_threads as u64,
+                };
+                unsafe {
+                    call_ort(|ort| {
+                        ort.SessionOptionsAppendExecutionProvider_OpenVINO.unwrap()(
+                            session_options_ptr,
+                            &options,
+                        )
+                    })
+                }
+            }
+        };
+        result.map_err(|source| OrtError::AppendExecutionProvider {
+            provider: self.name(),
+            source,
+        })
+    }
+}
+
+/// Pointer to a C string, or to an empty one, letting the runtime pick its default
+fn c_str_or_empty(string: &Option<CString>) -> *const c_char {
+    string
+        .as_ref()
+        .map_or(b"\0".as_ptr() as *const c_char, |string| string.as_ptr())
+}
+
+/// Options of the CUDA provider
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CudaProviderOptions {
+    /// Index of the GPU to run on
+    pub device_id: i32,
+    /// Maximum size in bytes of the GPU memory arena, or `None` for no limit
+    pub memory_limit: Option<usize>,
+    /// Algorithm search performed by cuDNN for convolutions
+    pub cudnn_conv_algo_search: CudnnConvAlgoSearch,
+    /// Whether to copy data in the default CUDA stream, which is slower but avoids
+    /// synchronization issues
+    pub do_copy_in_default_stream: bool,
+}
+
+impl Default for CudaProviderOptions {
+    fn default() -> Self {
+        CudaProviderOptions {
+            device_id: 0,
+            memory_limit: None,
+            cudnn_conv_algo_search: CudnnConvAlgoSearch::Exhaustive,
+            do_copy_in_default_stream: true,
+        }
+    }
+}
+
+impl CudaProviderOptions {
+    fn to_sys(&self) -> sys::OrtCUDAProviderOptions {
+        sys::OrtCUDAProviderOptions {
+            device_id: self.device_id,
+            cudnn_conv_algo_search: self.cudnn_conv_algo_search.into(),
+            cuda_mem_limit: self.memory_limit.unwrap_or(usize::MAX) as u64,
+            // Grow the memory arena by powers of two, the runtime's default
+            arena_extend_strategy: 0,
+            do_copy_in_default_stream: self.do_copy_in_default_stream as i32,
+        }
+    }
+}
+
+/// Algorithm search performed by cuDNN to pick the convolution implementation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CudnnConvAlgoSearch {
+    /// Benchmark all the algorithms, slow on the first run
+    Exhaustive,
+    /// Pick an algorithm from heuristics
+    Heuristic,
+    /// Use the default algorithm
+    Default,
+}
+
+impl From<CudnnConvAlgoSearch> for sys::OrtCudnnConvAlgoSearch {
+    fn from(search: CudnnConvAlgoSearch) -> Self {
+        match search {
+            CudnnConvAlgoSearch::Exhaustive => sys::OrtCudnnConvAlgoSearch::EXHAUSTIVE,
+            CudnnConvAlgoSearch::Heuristic => sys::OrtCudnnConvAlgoSearch::HEURISTIC,
+            CudnnConvAlgoSearch::Default => sys::OrtCudnnConvAlgoSearch::DEFAULT,
+        }
+    }
+}
+
+/// Options of the OpenVINO provider
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenVinoProviderOptions {
+    /// Device and precision to run on, like `CPU_FP32` or `GPU_FP16`, or `None` for the one
+    /// the runtime library was built for
+    pub device_type: Option<String>,
+    /// Identifier of the device to run on, or `None` for any device of `device_type`
+    pub device_id: Option<String>,
+    /// Whether to speed up the compilation of the model for VPUs
+    pub enable_vpu_fast_compile: bool,
+    /// Number of threads used by the provider, or 0 for its default
+    pub num_threads: usize,
+}
+
+/// Policy of [`SessionBuilder::with_execution_providers()`](../session/struct.SessionBuilder.html#method.with_execution_providers)
+/// for the providers which can't be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderFallback {
+    /// Skip the provider with a warning, falling back to the next ones
+    Skip,
+    /// Fail with an [`OrtError::ExecutionProviderNotAvailable`](../error/enum.OrtError.html#variant.ExecutionProviderNotAvailable)
+    /// or [`OrtError::AppendExecutionProvider`](../error/enum.OrtError.html#variant.AppendExecutionProvider)
+    /// error
+    Error,
+}
+
+/// Keep the providers of `providers` compiled into the runtime library, in order, each paired
+/// with `fallback`
+///
+/// The CPU provider supports every operator, so the providers listed after it are never used
+/// and are ignored; it is always appended last by [`append_providers()`](fn.append_providers.html).
+pub(crate) fn select_providers<I>(
+    providers: I,
+    fallback: ProviderFallback,
+) -> Result<Vec<(ExecutionProvider, ProviderFallback)>>
+where
+    I: IntoIterator<Item = ExecutionProvider>,
+{
+    let available = available_providers()?;
+    let mut selected = Vec::new();
+    for provider in providers {
+        if provider == ExecutionProvider::Cpu {
+            break;
+        }
+
+        if available.iter().any(|name| name == provider.name()) {
+            selected.push((provider, fallback));
+            continue;
+        }
+        let err = OrtError::ExecutionProviderNotAvailable {
+            provider: provider.name(),
+            available: available.clone(),
+        };
+        match fallback {
+            ProviderFallback::Skip => warn!("Skipping execution provider: {}", err),
+            ProviderFallback::Error => return Err(err),
+        }
+    }
+    Ok(selected)
+}
+
+/// Append the providers selected by [`select_providers()`](fn.select_providers.html) to the
+/// session options, in order, returning the appended ones followed by the CPU provider
+pub(crate) fn append_providers(
+    session_options_ptr: *mut sys::OrtSessionOptions,
+    providers: &[(ExecutionProvider, ProviderFallback)],
+) -> Result<Vec<ExecutionProvider>> {
+    let mut appended = Vec::new();
+    for (provider, fallback) in providers {
+        match (provider.append(session_options_ptr), fallback) {
+            (Ok(()), _) => {
+                debug!(provider = provider.name(), "Appended execution provider.");
+                appended.push(provider.clone());
+            }
+            (Err(err), ProviderFallback::Skip) => {
+                warn!("Skipping execution provider: {}", err);
+            }
+            (Err(err), ProviderFallback::Error) => return Err(err),
+        }
+    }
+    appended.push(ExecutionProvider::Cpu);
+    Ok(appended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn cpu_always_available() {
+        let providers = available_providers().unwrap();
+        assert!(providers.iter().any(|name| name == CPU_PROVIDER_NAME));
+        assert!(ExecutionProvider::Cpu.is_available().unwrap());
+    }
+}
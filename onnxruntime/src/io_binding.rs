@@ -0,0 +1,293 @@
+//! Module containing the binding of a session's inputs and outputs to values
+
+use std::{collections::HashMap, ffi::CString};
+
+use tracing::debug;
+
+use onnxruntime_sys as sys;
+
+use crate::{
+    custom_op,
+    error::{status_to_result, OrtError, Result},
+    g_ort,
+    memory::MemoryInfo,
+    run_options::RunOptions,
+    session::{validate_input_data_type, validate_output_buffer, validate_tensor, Session},
+    tensor::{
+        input_tensor::OrtInput, output_buffer::OrtBufferTensor, DynOrtTensor, InputTensor,
+        OutputBuffer,
+    },
+    value::DynOrtValue,
+};
+
+/// Binding of a [`Session`](../session/struct.Session.html)'s inputs and outputs to values
+///
+/// Inputs bound once are reused by every inference call performed through the binding, which
+/// avoids converting and copying them again when only some of the inputs change between runs.
+/// Outputs can be bound to caller-owned buffers ([`OutputBuffer`](../tensor/output_buffer/enum.OutputBuffer.html))
+/// or to a [`MemoryInfo`](../memory/struct.MemoryInfo.html), in which case the runtime allocates them.
+///
+/// An `IoBinding` is created with [`Session::io_binding()`](../session/struct.Session.html#method.io_binding).
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::error::Error;
+/// # use onnxruntime::{environment::Environment, LoggingLevel};
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+/// # let session = environment.new_session_builder()?.with_model_from_file("model.onnx")?;
+/// let mut output = ndarray::Array::<f32, _>::zeros((1, 1000));
+/// let mut io_binding = session.io_binding()?;
+/// io_binding.bind_output("output", output.view_mut())?;
+///
+/// for frame in 0..100 {
+///     let array = ndarray::Array::<f32, _>::from_elem((1, 3, 224, 224), frame as f32);
+///     io_binding.bind_input("input", array)?;
+///     io_binding.run()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct IoBinding<'s> {
+    ptr: *mut sys::OrtIoBinding,
+    session: &'s Session<'s>,
+    /// Values of the bound inputs, kept alive as long as they are bound
+    inputs: HashMap<String, Box<dyn OrtInput + 's>>,
+    /// Caller-owned buffers of the bound outputs, kept alive as long as they are bound
+    output_buffers: HashMap<String, OrtBufferTensor<'s>>,
+}
+
+impl<'s> IoBinding<'s> {
+    pub(crate) fn new(session: &'s Session<'s>) -> Result<IoBinding<'s>> {
+        let mut ptr: *mut sys::OrtIoBinding = std::ptr::null_mut();
+        let status = unsafe { g_ort().CreateIoBinding.unwrap()(session.session_ptr, &mut ptr) };
+        status_to_result(status).map_err(OrtError::IoBinding)?;
+        assert_ne!(ptr, std::ptr::null_mut());
+
+        Ok(IoBinding {
+            ptr,
+            session,
+            inputs: HashMap::new(),
+            output_buffers: HashMap::new(),
+        })
+    }
+
+    /// Bind an input of the session to a value
+    ///
+    /// The value is provided to the runtime once and used by all following inference calls,
+    /// until the input is bound again or [`clear_bound_inputs()`](#method.clear_bound_inputs) is called.
+    /// Borrowed values, like views, stay borrowed for as long as the binding exists.
+    pub fn bind_input<V>(&mut self, name: &str, input_array: V) -> Result<()>
+    where
+        V: Into<InputTensor<'s>>,
+    {
+        let input_array = input_array.into();
+        let input = self
+            .session
+            .inputs
+            .iter()
+            .find(|input| input.name == name)
+            .ok_or_else(|| OrtError::UnknownInputName {
+                name: name.to_string(),
+            })?;
+        validate_input_data_type(input, &input_array)?;
+
+        let input_ort_tensor =
+            input_array.into_ort_tensor(&self.session.memory_info, self.session.allocator_ptr)?;
+        let name_cstring = CString::new(name)?;
+        let status = unsafe {
+            g_ort().BindInput.unwrap()(self.ptr, name_cstring.as_ptr(), input_ort_tensor.c_ptr())
+        };
+        status_to_result(status).map_err(OrtError::IoBinding)?;
+
+        self.inputs.insert(name.to_string(), input_ort_tensor);
+        Ok(())
+    }
+
+    /// Bind an output of the session to a caller-owned buffer
+    ///
+    /// The runtime writes the output directly into the buffer, whose shape must match the
+    /// output's shape. The buffer stays borrowed for as long as the binding exists.
+    ///
+    /// Returns an error if the output is not a tensor, or if the buffer's element type or shape
+    /// does not match the output's fixed dimensions.
+    pub fn bind_output<B>(&mut self, name: &str, output_buffer: B) -> Result<()>
+    where
+        B: Into<OutputBuffer<'s>>,
+    {
+        let output_buffer = output_buffer.into();
+        let output = self
+            .session
+            .outputs
+            .iter()
+            .find(|output| output.name == name)
+            .ok_or_else(|| OrtError::UnknownOutputName {
+                name: name.to_string(),
+            })?;
+        validate_output_buffer(output, &output_buffer)?;
+
+        let buffer_tensor = output_buffer.into_ort_tensor(&self.session.memory_info)?;
+        let name_cstring = CString::new(name)?;
+        let status = unsafe {
+            g_ort().BindOutput.unwrap()(self.ptr, name_cstring.as_ptr(), buffer_tensor.c_ptr)
+        };
+        status_to_result(status).map_err(OrtError::IoBinding)?;
+
+        self.output_buffers.insert(name.to_string(), buffer_tensor);
+        Ok(())
+    }
+
+    /// Bind an output of the session to a memory location, leaving its allocation to the runtime
+    ///
+    /// The output is allocated by each inference call and retrieved with [`outputs()`](#method.outputs).
+    /// Only tensor outputs can be bound.
+    pub fn bind_output_to_device(&mut self, name: &str, memory_info: &MemoryInfo) -> Result<()> {
+        let output = self
+            .session
+            .outputs
+            .iter()
+            .find(|output| output.name == name)
+            .ok_or_else(|| OrtError::UnknownOutputName {
+                name: name.to_string(),
+            })?;
+        validate_tensor(&output.name, &output.value_type)?;
+
+        let name_cstring = CString::new(name)?;
+        let status = unsafe {
+            g_ort().BindOutputToDevice.unwrap()(self.ptr, name_cstring.as_ptr(), memory_info.ptr)
+        };
+        status_to_result(status).map_err(OrtError::IoBinding)?;
+
+        self.output_buffers.remove(name);
+        Ok(())
+    }
+
+    /// Remove all the input bindings
+    pub fn clear_bound_inputs(&mut self) {
+        unsafe { g_ort().ClearBoundInputs.unwrap()(self.ptr) };
+        self.inputs.clear();
+    }
+
+    /// Remove all the output bindings
+    pub fn clear_bound_outputs(&mut self) {
+        unsafe { g_ort().ClearBoundOutputs.unwrap()(self.ptr) };
+        self.output_buffers.clear();
+    }
+
+    /// Run the inference using the bound inputs, writing to the bound outputs
+    pub fn run(&mut self) -> Result<()> {
+        let status = unsafe {
+            g_ort().RunWithBinding.unwrap()(self.session.session_ptr, std::ptr::null(), self.ptr)
+        };
+        let result = status_to_result(status).map_err(OrtError::Run);
+        let custom_op_result = custom_op::take_errors(&self.session.custom_op_errors);
+        result.and(custom_op_result)
+    }
+
+    /// Run the inference using the bound inputs and the given options, writing to the bound outputs
+    pub fn run_with_options(&mut self, run_options: &RunOptions) -> Result<()> {
+        let session_ptr = self.session.session_ptr;
+        let ptr = self.ptr;
+        let result = run_options.run(|run_options_ptr| unsafe {
+            g_ort().RunWithBinding.unwrap()(session_ptr, run_options_ptr, ptr)
+        });
+        let custom_op_result = custom_op::take_errors(&self.session.custom_op_errors);
+        result.and(custom_op_result)
+    }
+
+    /// Return the names of the bound outputs, in the same order as [`outputs()`](#method.outputs)
+    pub fn output_names(&self) -> Result<Vec<String>> {
+        let allocator_ptr = self.session.allocator_ptr;
+        let mut buffer: *mut i8 = std::ptr::null_mut();
+        let mut lengths: *mut u64 = std::ptr::null_mut();
+        let mut count = 0;
+        let status = unsafe {
+            g_ort().GetBoundOutputNames.unwrap()(
+                self.ptr,
+                allocator_ptr,
+                &mut buffer,
+                &mut lengths,
+                &mut count,
+            )
+        };
+        status_to_result(status).map_err(OrtError::IoBinding)?;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        assert_ne!(buffer, std::ptr::null_mut());
+        assert_ne!(lengths, std::ptr::null_mut());
+
+        // The names are concatenated in a single buffer, without null terminators
+        let lengths_slice = unsafe { std::slice::from_raw_parts(lengths, count as usize) };
+        let total_length = lengths_slice.iter().sum::<u64>() as usize;
+        let buffer_slice = unsafe { std::slice::from_raw_parts(buffer as *const u8, total_length) };
+        let mut names = Vec::with_capacity(count as usize);
+        let mut start = 0;
+        for &length in lengths_slice {
+            let end = start + length as usize;
+            names.push(String::from_utf8_lossy(&buffer_slice[start..end]).into_owned());
+            start = end;
+        }
+
+        unsafe {
+            free_with_allocator(allocator_ptr, buffer as *mut std::ffi::c_void)?;
+            free_with_allocator(allocator_ptr, lengths as *mut std::ffi::c_void)?;
+        }
+
+        Ok(names)
+    }
+
+    /// Return the values of the bound outputs, as computed by the last inference call
+    ///
+    /// The outputs are in the same order as [`output_names()`](#method.output_names). Outputs bound
+    /// to a caller-owned buffer are views of that buffer.
+    pub fn outputs<'b>(&'b self) -> Result<Vec<DynOrtTensor<'b, ndarray::IxDyn>>>
+    where
+        's: 'b, // 's outlives 'b
+    {
+        let allocator_ptr = self.session.allocator_ptr;
+        let mut values: *mut *mut sys::OrtValue = std::ptr::null_mut();
+        let mut count = 0;
+        let status = unsafe {
+            g_ort().GetBoundOutputValues.unwrap()(self.ptr, allocator_ptr, &mut values, &mut count)
+        };
+        status_to_result(status).map_err(OrtError::IoBinding)?;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        assert_ne!(values, std::ptr::null_mut());
+
+        // Each value is owned by us, take ownership of them all before freeing the array, so they
+        // are all released on error
+        let values_slice = unsafe { std::slice::from_raw_parts(values, count as usize) };
+        let outputs: Vec<DynOrtValue> = values_slice
+            .iter()
+            .map(|&value| DynOrtValue::new(value))
+            .collect();
+        unsafe { free_with_allocator(allocator_ptr, values as *mut std::ffi::c_void)? };
+
+        outputs.into_iter().map(DynOrtValue::into_tensor).collect()
+    }
+}
+
+impl<'s> Drop for IoBinding<'s> {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping the I/O binding.");
+        assert_ne!(self.ptr, std::ptr::null_mut());
+        unsafe { g_ort().ReleaseIoBinding.unwrap()(self.ptr) };
+
+        self.ptr = std::ptr::null_mut();
+    }
+}
+
+/// Free memory allocated by the runtime on our behalf
+unsafe fn free_with_allocator(
+    allocator_ptr: *mut sys::OrtAllocator,
+    ptr: *mut std::ffi::c_void,
+) -> Result<()> {
+    let status = g_ort().AllocatorFree.unwrap()(allocator_ptr, ptr);
+    status_to_result(status).map_err(OrtError::Allocator)
+}
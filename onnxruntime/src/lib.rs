@@ -47,7 +47,7 @@
 //! #     .with_name("test")
 //! #     .with_log_level(LoggingLevel::Verbose)
 //! #     .build()?;
-//! let mut session = environment
+//! let session = environment
 //!     .new_session_builder()?
 //!     .with_optimization_level(GraphOptimizationLevel::Basic)?
 //!     .with_number_threads(1)?
@@ -72,7 +72,7 @@ a model can be fetched directly from the [ONNX Model Zoo](https://github.com/onn
 #     .with_name("test")
 #     .with_log_level(LoggingLevel::Verbose)
 #     .build()?;
-let mut session = environment
+let session = environment
     .new_session_builder()?
     .with_optimization_level(GraphOptimizationLevel::Basic)?
     .with_number_threads(1)?
@@ -90,13 +90,13 @@ to download.
 //!
 //! ```no_run
 //! # use std::error::Error;
-//! # use onnxruntime::{environment::Environment, LoggingLevel, GraphOptimizationLevel, tensor::OrtOwnedTensor};
+//! # use onnxruntime::{environment::Environment, LoggingLevel, GraphOptimizationLevel, tensor::{DynOrtTensor, OrtOwnedTensor}};
 //! # fn main() -> Result<(), Box<dyn Error>> {
 //! # let environment = Environment::builder()
 //! #     .with_name("test")
 //! #     .with_log_level(LoggingLevel::Verbose)
 //! #     .build()?;
-//! # let mut session = environment
+//! # let session = environment
 //! #     .new_session_builder()?
 //! #     .with_optimization_level(GraphOptimizationLevel::Basic)?
 //! #     .with_number_threads(1)?
@@ -104,32 +104,54 @@ to download.
 //! let array = ndarray::Array::linspace(0.0_f32, 1.0, 100);
 //! // Multiple inputs and outputs are possible
 //! let input_tensor = vec![array];
-//! let outputs: Vec<OrtOwnedTensor<f32,_>> = session.run(input_tensor)?;
+//! let outputs: Vec<DynOrtTensor<_>> = session.run(input_tensor)?;
+//! let output: OrtOwnedTensor<f32, _> = outputs[0].try_extract()?;
 //! # Ok(())
 //! # }
 //! ```
 //!
-//! The outputs are of type [`OrtOwnedTensor`](tensor/struct.OrtOwnedTensor.html)s inside a vector,
-//! with the same length as the inputs.
+//! The outputs are of type [`DynOrtTensor`](tensor/struct.DynOrtTensor.html)s inside a vector,
+//! with the same length as the model's outputs. Their element type is only known at runtime;
+//! [`try_extract()`](tensor/struct.DynOrtTensor.html#method.try_extract) gives access to the data
+//! as an [`OrtOwnedTensor`](tensor/struct.OrtOwnedTensor.html) if the requested type matches.
+//! Models taking or returning sequences and maps, like scikit-learn classifiers, are run with
+//! [`run_values()`](session/struct.Session.html#method.run_values) instead; see the
+//! [`value`](value/index.html) module.
 //!
 //! See the [`sample.rs`](https://github.com/nbigaouette/onnxruntime-rs/blob/master/onnxruntime/examples/sample.rs)
 //! example for more details.
 
-use std::sync::{atomic::AtomicPtr, Arc, Mutex};
+use std::{
+    convert::TryFrom,
+    sync::{atomic::AtomicPtr, Arc, Mutex},
+};
 
 use lazy_static::lazy_static;
 
 use onnxruntime_sys as sys;
 
+#[cfg(feature = "async")]
+pub mod async_run;
+pub mod batcher;
+pub mod custom_op;
 pub mod download;
 pub mod environment;
 pub mod error;
-mod memory;
+pub mod execution_provider;
+pub mod io_binding;
+pub mod memory;
+pub mod metadata;
+#[cfg(feature = "profiling")]
+pub mod profiling;
+pub mod run_options;
 pub mod session;
+pub mod session_pool;
 pub mod tensor;
+pub mod value;
 
 // Re-export
 pub use error::{OrtApiError, OrtError, Result};
+pub use execution_provider::available_providers;
 use sys::OnnxEnumInt;
 
 // Re-export ndarray as it's part of the public API anyway
@@ -322,10 +344,32 @@ impl Into<sys::GraphOptimizationLevel> for GraphOptimizationLevel {
     }
 }
 
+/// Execution mode of a session's graph
+///
+/// See [`SessionBuilder::with_execution_mode()`](session/struct.SessionBuilder.html#method.with_execution_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(windows), repr(u32))]
+#[cfg_attr(windows, repr(i32))]
+pub enum ExecutionMode {
+    /// Execute the graph's nodes one after the other
+    Sequential = sys::ExecutionMode::ORT_SEQUENTIAL as OnnxEnumInt,
+    /// Execute independent nodes of the graph in parallel, using the inter-op thread pool
+    Parallel = sys::ExecutionMode::ORT_PARALLEL as OnnxEnumInt,
+}
+
+impl From<ExecutionMode> for sys::ExecutionMode {
+    fn from(execution_mode: ExecutionMode) -> sys::ExecutionMode {
+        match execution_mode {
+            ExecutionMode::Sequential => sys::ExecutionMode::ORT_SEQUENTIAL,
+            ExecutionMode::Parallel => sys::ExecutionMode::ORT_PARALLEL,
+        }
+    }
+}
+
 // FIXME: Use https://docs.rs/bindgen/0.54.1/bindgen/struct.Builder.html#method.rustified_enum
 // FIXME: Add tests to cover the commented out types
 /// Enum mapping ONNX Runtime's supported tensor types
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(not(windows), repr(u32))]
 #[cfg_attr(windows, repr(i32))]
 pub enum TensorElementDataType {
@@ -397,6 +441,36 @@ impl Into<sys::ONNXTensorElementDataType> for TensorElementDataType {
     }
 }
 
+impl TryFrom<sys::ONNXTensorElementDataType> for TensorElementDataType {
+    type Error = OrtError;
+
+    fn try_from(type_sys: sys::ONNXTensorElementDataType) -> Result<Self> {
+        use sys::ONNXTensorElementDataType::*;
+        use TensorElementDataType::*;
+        Ok(match type_sys {
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_FLOAT => Float,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_UINT8 => Uint8,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_INT8 => Int8,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_UINT16 => Uint16,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_INT16 => Int16,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_INT32 => Int32,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_INT64 => Int64,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_STRING => String,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_DOUBLE => Double,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_UINT32 => Uint32,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_UINT64 => Uint64,
+            ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED
+            | ONNX_TENSOR_ELEMENT_DATA_TYPE_BOOL
+            | ONNX_TENSOR_ELEMENT_DATA_TYPE_FLOAT16
+            | ONNX_TENSOR_ELEMENT_DATA_TYPE_COMPLEX64
+            | ONNX_TENSOR_ELEMENT_DATA_TYPE_COMPLEX128
+            | ONNX_TENSOR_ELEMENT_DATA_TYPE_BFLOAT16 => {
+                return Err(OrtError::UnsupportedElementType(type_sys))
+            }
+        })
+    }
+}
+
 /// Trait used to map Rust types (for example `f32`) to ONNX types (for example `Float`)
 pub trait TypeToTensorElementDataType {
     /// Return the ONNX type for a Rust type
@@ -471,7 +545,7 @@ impl<T: Utf8Data> TypeToTensorElementDataType for T {
 }
 
 /// Allocator type
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(i32)]
 pub enum AllocatorType {
     // Invalid = sys::OrtAllocatorType::Invalid as i32,
@@ -492,10 +566,24 @@ impl Into<sys::OrtAllocatorType> for AllocatorType {
     }
 }
 
+impl TryFrom<sys::OrtAllocatorType> for AllocatorType {
+    type Error = OrtError;
+
+    fn try_from(allocator_sys: sys::OrtAllocatorType) -> Result<Self> {
+        match allocator_sys {
+            sys::OrtAllocatorType::OrtDeviceAllocator => Ok(AllocatorType::Device),
+            sys::OrtAllocatorType::OrtArenaAllocator => Ok(AllocatorType::Arena),
+            sys::OrtAllocatorType::Invalid => {
+                Err(OrtError::UnsupportedAllocatorType(allocator_sys))
+            }
+        }
+    }
+}
+
 /// Memory type
 ///
 /// Only support ONNX's default type for now.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(i32)]
 pub enum MemType {
     // FIXME: C API's `OrtMemType_OrtMemTypeCPU` defines it equal to `OrtMemType_OrtMemTypeCPUOutput`. How to handle this??
@@ -518,6 +606,19 @@ impl Into<sys::OrtMemType> for MemType {
     }
 }
 
+impl TryFrom<sys::OrtMemType> for MemType {
+    type Error = OrtError;
+
+    fn try_from(memory_type_sys: sys::OrtMemType) -> Result<Self> {
+        match memory_type_sys {
+            sys::OrtMemType::OrtMemTypeDefault => Ok(MemType::Default),
+            sys::OrtMemType::OrtMemTypeCPUInput | sys::OrtMemType::OrtMemTypeCPUOutput => {
+                Err(OrtError::UnsupportedMemType(memory_type_sys))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -528,4 +629,26 @@ mod test {
         let ptr = s.as_c_str().as_ptr();
         assert_eq!("foo", char_p_to_string(ptr).unwrap());
     }
+
+    #[test]
+    fn tensor_element_data_type_from_sys() {
+        for data_type in &[
+            TensorElementDataType::Float,
+            TensorElementDataType::Int64,
+            TensorElementDataType::String,
+            TensorElementDataType::Uint64,
+        ] {
+            let type_sys: sys::ONNXTensorElementDataType = (*data_type).into();
+            assert_eq!(
+                TensorElementDataType::try_from(type_sys).unwrap(),
+                *data_type
+            );
+        }
+
+        let bool_sys = sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_BOOL;
+        assert!(matches!(
+            TensorElementDataType::try_from(bool_sys),
+            Err(OrtError::UnsupportedElementType(type_sys)) if type_sys == bool_sys
+        ));
+    }
 }
@@ -1,3 +1,7 @@
+//! Module containing the description of memory locations
+
+use std::convert::TryFrom;
+
 use tracing::debug;
 
 use onnxruntime_sys as sys;
@@ -7,12 +11,21 @@ use crate::{
     g_ort, AllocatorType, MemType,
 };
 
+/// Description of a memory location used by the runtime to store tensors
+///
+/// A `MemoryInfo` can be used to bind an output of an [`IoBinding`](../io_binding/struct.IoBinding.html)
+/// to a memory location, leaving its allocation to the runtime.
 #[derive(Debug)]
-pub(crate) struct MemoryInfo {
-    pub ptr: *mut sys::OrtMemoryInfo,
+pub struct MemoryInfo {
+    pub(crate) ptr: *mut sys::OrtMemoryInfo,
 }
 
+// The memory information is never modified after its creation; the runtime only reads it.
+unsafe impl Send for MemoryInfo {}
+unsafe impl Sync for MemoryInfo {}
+
 impl MemoryInfo {
+    /// Describe the CPU memory allocated by an allocator of the given type
     #[tracing::instrument]
     pub fn new(allocator: AllocatorType, memory_type: MemType) -> Result<Self> {
         debug!("Creating new memory info.");
@@ -31,6 +44,23 @@ impl MemoryInfo {
             ptr: memory_info_ptr,
         })
     }
+
+    /// Type of the allocator of the memory
+    pub fn allocator_type(&self) -> Result<AllocatorType> {
+        let mut allocator_sys = sys::OrtAllocatorType::Invalid;
+        let status = unsafe { g_ort().MemoryInfoGetType.unwrap()(self.ptr, &mut allocator_sys) };
+        status_to_result(status).map_err(OrtError::MemoryInfo)?;
+        AllocatorType::try_from(allocator_sys)
+    }
+
+    /// Type of the memory
+    pub fn memory_type(&self) -> Result<MemType> {
+        let mut memory_type_sys = sys::OrtMemType::OrtMemTypeDefault;
+        let status =
+            unsafe { g_ort().MemoryInfoGetMemType.unwrap()(self.ptr, &mut memory_type_sys) };
+        status_to_result(status).map_err(OrtError::MemoryInfo)?;
+        MemType::try_from(memory_type_sys)
+    }
 }
 
 impl Drop for MemoryInfo {
@@ -55,4 +85,19 @@ mod tests {
         let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
         std::mem::drop(memory_info);
     }
+
+    #[test]
+    fn memory_info_types() {
+        let memory_info = MemoryInfo::new(AllocatorType::Device, MemType::Default).unwrap();
+        assert_eq!(memory_info.allocator_type().unwrap(), AllocatorType::Device);
+        assert_eq!(memory_info.memory_type().unwrap(), MemType::Default);
+    }
+
+    #[test]
+    fn memory_info_dropped_in_another_thread() {
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        std::thread::spawn(move || std::mem::drop(memory_info))
+            .join()
+            .unwrap();
+    }
 }
@@ -0,0 +1,155 @@
+//! Module containing the metadata of ONNX models
+
+use std::{collections::HashMap, ffi::c_void};
+
+use tracing::debug;
+
+use onnxruntime_sys as sys;
+
+use crate::{
+    char_p_to_string,
+    error::{call_ort, status_to_result, OrtError, Result},
+    g_ort,
+};
+
+/// Metadata of an ONNX model, as stored in loaded file
+///
+/// Obtained with [`Session::metadata()`](../session/struct.Session.html#method.metadata).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelMetadata {
+    /// Name of the tool which produced the model, like `pytorch` or `tf2onnx`
+    pub producer_name: String,
+    /// Name of the model's graph
+    pub graph_name: String,
+    /// Domain of the model, in reverse DNS notation (like `com.example`)
+    pub domain: String,
+    /// Documentation of the model
+    pub description: String,
+    /// Version of the model
+    pub version: i64,
+    /// Custom properties of the model (the ONNX `metadata_props`), like label lists or
+    /// preprocessing parameters
+    pub custom: HashMap<String, String>,
+}
+
+/// Runtime's metadata of a session, released when dropped
+#[derive(Debug)]
+struct ModelMetadataPtr(*mut sys::OrtModelMetadata);
+
+impl Drop for ModelMetadataPtr {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping the model metadata.");
+        unsafe { g_ort().ReleaseModelMetadata.unwrap()(self.0) };
+    }
+}
+
+impl ModelMetadata {
+    pub(crate) fn new(
+        session_ptr: *mut sys::OrtSession,
+        allocator_ptr: *mut sys::OrtAllocator,
+    ) -> Result<ModelMetadata> {
+        let mut metadata_ptr: *mut sys::OrtModelMetadata = std::ptr::null_mut();
+        unsafe {
+            call_ort(|ort| ort.SessionGetModelMetadata.unwrap()(session_ptr, &mut metadata_ptr))
+        }
+        .map_err(OrtError::ModelMetadata)?;
+        assert_ne!(metadata_ptr, std::ptr::null_mut());
+        let metadata = ModelMetadataPtr(metadata_ptr);
+
+        let get_string = |f: GetStringFn| {
+            let mut value_ptr: *mut i8 = std::ptr::null_mut();
+            let status = unsafe { f(metadata.0, allocator_ptr, &mut value_ptr) };
+            status_to_result(status).map_err(OrtError::ModelMetadata)?;
+            unsafe { allocated_string(allocator_ptr, value_ptr) }
+        };
+        let producer_name = get_string(g_ort().ModelMetadataGetProducerName.unwrap())?;
+        let graph_name = get_string(g_ort().ModelMetadataGetGraphName.unwrap())?;
+        let domain = get_string(g_ort().ModelMetadataGetDomain.unwrap())?;
+        let description = get_string(g_ort().ModelMetadataGetDescription.unwrap())?;
+
+        let mut version = 0;
+        unsafe { call_ort(|ort| ort.ModelMetadataGetVersion.unwrap()(metadata.0, &mut version)) }
+            .map_err(OrtError::ModelMetadata)?;
+
+        let custom = custom_metadata(&metadata, allocator_ptr)?;
+
+        Ok(ModelMetadata {
+            producer_name,
+            graph_name,
+            domain,
+            description,
+            version,
+            custom,
+        })
+    }
+}
+
+type GetStringFn = unsafe extern "C" fn(
+    *const sys::OrtModelMetadata,
+    *mut sys::OrtAllocator,
+    *mut *mut i8,
+) -> *mut sys::OrtStatus;
+
+/// Read the custom metadata map of a model
+fn custom_metadata(
+    metadata: &ModelMetadataPtr,
+    allocator_ptr: *mut sys::OrtAllocator,
+) -> Result<HashMap<String, String>> {
+    let mut keys_ptr: *mut *mut i8 = std::ptr::null_mut();
+    let mut num_keys = 0;
+    unsafe {
+        call_ort(|ort| {
+            ort.ModelMetadataGetCustomMetadataMapKeys.unwrap()(
+                metadata.0,
+                allocator_ptr,
+                &mut keys_ptr,
+                &mut num_keys,
+            )
+        })
+    }
+    .map_err(OrtError::ModelMetadata)?;
+    if keys_ptr.is_null() {
+        // The runtime does not allocate the array of keys when there are none
+        return Ok(HashMap::new());
+    }
+
+    // Free all the keys and the array, even if one of them fails to be converted
+    let keys: Vec<Result<String>> = (0..num_keys as usize)
+        .map(|i| unsafe { allocated_string(allocator_ptr, *keys_ptr.add(i)) })
+        .collect();
+    unsafe { free(allocator_ptr, keys_ptr as *mut c_void)? };
+
+    keys.into_iter()
+        .map(|key| {
+            let key = key?;
+            let key_c = std::ffi::CString::new(key.clone())?;
+            let mut value_ptr: *mut i8 = std::ptr::null_mut();
+            unsafe {
+                call_ort(|ort| {
+                    ort.ModelMetadataLookupCustomMetadataMap.unwrap()(
+                        metadata.0,
+                        allocator_ptr,
+                        key_c.as_ptr(),
+                        &mut value_ptr,
+                    )
+                })
+            }
+            .map_err(OrtError::ModelMetadata)?;
+            let value = unsafe { allocated_string(allocator_ptr, value_ptr)? };
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Convert a string allocated by the runtime with `allocator_ptr`, freeing it
+unsafe fn allocated_string(allocator_ptr: *mut sys::OrtAllocator, ptr: *mut i8) -> Result<String> {
+    assert_ne!(ptr, std::ptr::null_mut());
+    let string = char_p_to_string(ptr);
+    free(allocator_ptr, ptr as *mut c_void)?;
+    string
+}
+
+unsafe fn free(allocator_ptr: *mut sys::OrtAllocator, ptr: *mut c_void) -> Result<()> {
+    call_ort(|ort| ort.AllocatorFree.unwrap()(allocator_ptr, ptr)).map_err(OrtError::Allocator)
+}
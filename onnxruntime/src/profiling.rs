@@ -0,0 +1,337 @@
+//! Module containing the parser of the runtime's profiling traces
+//!
+//! When profiling is enabled with [`SessionBuilder::with_profiling()`](../session/struct.SessionBuilder.html#method.with_profiling),
+//! the runtime records the execution time of each node of the graph, for each inference call.
+//! [`Session::end_profiling()`](../session/struct.Session.html#method.end_profiling) writes those
+//! events to a JSON trace (in the [Chrome tracing](https://www.chromium.org/developers/how-tos/trace-event-profiling-tool)
+//! format), which [`Profile`](struct.Profile.html) parses and summarizes per node and per operator type.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::error::Error;
+//! # use onnxruntime::{environment::Environment, profiling::Profile, LoggingLevel};
+//! # fn main() -> Result<(), Box<dyn Error>> {
+//! # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+//! let session = environment
+//!     .new_session_builder()?
+//!     .with_profiling("squeezenet")?
+//!     .with_model_from_file("squeezenet.onnx")?;
+//!
+//! let array = ndarray::Array::<f32, _>::zeros((1, 3, 224, 224));
+//! session.run(vec![array])?;
+//!
+//! let profile = Profile::from_file(session.end_profiling()?)?;
+//! for summary in profile.op_type_summaries() {
+//!     println!("{}: {:?} in {} calls", summary.op_type, summary.total, summary.calls);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{cmp::Reverse, collections::HashMap, fs, path::Path, time::Duration};
+
+use serde::Deserialize;
+
+use crate::{OrtError, Result};
+
+/// Suffix of the name of the events measuring the execution of a node's kernel
+const KERNEL_TIME_SUFFIX: &str = "_kernel_time";
+
+/// Profiling trace written by the runtime
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// Events of the trace, in the order they were recorded
+    pub events: Vec<ProfileEvent>,
+}
+
+/// Event of a profiling trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileEvent {
+    /// Category of the event, like `Session` (model loading, inference calls) or `Node`
+    pub category: String,
+    /// Name of the event; node events are named after the node, with a suffix like `_kernel_time`
+    pub name: String,
+    /// Identifier of the thread which recorded the event
+    pub thread_id: u64,
+    /// Start of the event, since the start of profiling
+    pub start: Duration,
+    /// Duration of the event
+    pub duration: Duration,
+    /// Type of the operator executed, for node events
+    pub op_type: Option<String>,
+    /// Execution provider of the node, for node events
+    pub provider: Option<String>,
+}
+
+/// Execution times of a node, over all the inference calls of a trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeSummary {
+    /// Name of the node
+    pub name: String,
+    /// Type of the node's operator
+    pub op_type: String,
+    /// Execution provider of the node
+    pub provider: Option<String>,
+    /// Number of executions
+    pub calls: usize,
+    /// Total execution time
+    pub total: Duration,
+    /// Shortest execution time
+    pub min: Duration,
+    /// Longest execution time
+    pub max: Duration,
+}
+
+/// Execution times of the nodes of an operator type, over all the inference calls of a trace
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpTypeSummary {
+    /// Type of the operator
+    pub op_type: String,
+    /// Number of nodes of this type
+    pub nodes: usize,
+    /// Number of executions, of all the nodes
+    pub calls: usize,
+    /// Total execution time, of all the nodes
+    pub total: Duration,
+}
+
+/// Event as written by the runtime
+#[derive(Debug, Deserialize)]
+struct RawEvent {
+    cat: String,
+    name: String,
+    #[serde(default)]
+    tid: u64,
+    /// Start, in microseconds
+    #[serde(default)]
+    ts: u64,
+    /// Duration, in microseconds
+    #[serde(default)]
+    dur: u64,
+    #[serde(default)]
+    args: HashMap<String, serde_json::Value>,
+}
+
+impl From<RawEvent> for ProfileEvent {
+    fn from(mut raw: RawEvent) -> ProfileEvent {
+        let mut take_arg = |key| match raw.args.remove(key) {
+            Some(serde_json::Value::String(value)) => Some(value),
+            _ => None,
+        };
+        ProfileEvent {
+            op_type: take_arg("op_name"),
+            provider: take_arg("provider"),
+            category: raw.cat,
+            name: raw.name,
+            thread_id: raw.tid,
+            start: Duration::from_micros(raw.ts),
+            duration: Duration::from_micros(raw.dur),
+        }
+    }
+}
+
+impl Profile {
+    /// Parse a profiling trace written by the runtime
+    pub fn from_file<P>(path: P) -> Result<Profile>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|source| OrtError::ProfileRead {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Profile::from_json(&json)
+    }
+
+    /// Parse the JSON content of a profiling trace
+    pub fn from_json(json: &str) -> Result<Profile> {
+        let events: Vec<RawEvent> = serde_json::from_str(json).map_err(OrtError::ProfileParsing)?;
+        Ok(Profile {
+            events: events.into_iter().map(ProfileEvent::from).collect(),
+        })
+    }
+
+    /// Events measuring the execution of a node's kernel, with the node's name
+    fn kernel_events(&self) -> impl Iterator<Item = (&str, &ProfileEvent)> {
+        self.events.iter().filter_map(|event| {
+            let node_name = event.name.strip_suffix(KERNEL_TIME_SUFFIX)?;
+            event.op_type.as_ref()?;
+            Some((node_name, event))
+        })
+    }
+
+    /// Summarize the execution times of each node, slowest (in total) first
+    pub fn node_summaries(&self) -> Vec<NodeSummary> {
+        let mut summaries: Vec<NodeSummary> = Vec::new();
+        let mut indices: HashMap<&str, usize> = HashMap::new();
+        for (node_name, event) in self.kernel_events() {
+            let index = *indices.entry(node_name).or_insert_with(|| {
+                summaries.push(NodeSummary {
+                    name: node_name.to_string(),
+                    op_type: event.op_type.clone().unwrap_or_default(),
+                    provider: event.provider.clone(),
+                    calls: 0,
+                    total: Duration::from_secs(0),
+                    min: event.duration,
+                    max: event.duration,
+                });
+                summaries.len() - 1
+            });
+
+            let summary = &mut summaries[index];
+            summary.calls += 1;
+            summary.total += event.duration;
+            summary.min = summary.min.min(event.duration);
+            summary.max = summary.max.max(event.duration);
+        }
+
+        summaries.sort_by_key(|summary| Reverse(summary.total));
+        summaries
+    }
+
+    /// Summarize the execution times of each operator type, slowest (in total) first
+    pub fn op_type_summaries(&self) -> Vec<OpTypeSummary> {
+        let mut summaries: Vec<OpTypeSummary> = Vec::new();
+        for node in self.node_summaries() {
+            match summaries
+                .iter_mut()
+                .find(|summary| summary.op_type == node.op_type)
+            {
+                Some(summary) => {
+                    summary.nodes += 1;
+                    summary.calls += node.calls;
+                    summary.total += node.total;
+                }
+                None => summaries.push(OpTypeSummary {
+                    op_type: node.op_type,
+                    nodes: 1,
+                    calls: node.calls,
+                    total: node.total,
+                }),
+            }
+        }
+
+        summaries.sort_by_key(|summary| Reverse(summary.total));
+        summaries
+    }
+}
+
+impl NodeSummary {
+    /// Average execution time
+    pub fn mean(&self) -> Duration {
+        mean(self.total, self.calls)
+    }
+}
+
+impl OpTypeSummary {
+    /// Average execution time of a node of this type
+    pub fn mean(&self) -> Duration {
+        mean(self.total, self.calls)
+    }
+}
+
+fn mean(total: Duration, calls: usize) -> Duration {
+    if calls == 0 {
+        Duration::from_secs(0)
+    } else {
+        total / calls as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    // Trace of two inference calls on a model with two `Conv` nodes and a `Relu` node
+    const TRACE: &str = r#"[
+{"cat" : "Session","pid" :1,"tid" :1,"dur" :950,"ts" :3,"ph" : "X","name" :"model_loading_uri","args" : {}},
+{"cat" : "Session","pid" :1,"tid" :1,"dur" :120,"ts" :960,"ph" : "X","name" :"session_initialization","args" : {}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :0,"ts" :1100,"ph" : "X","name" :"conv1_fence_before","args" : {"op_name" : "Conv"}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :40,"ts" :1101,"ph" : "X","name" :"conv1_kernel_time","args" : {"op_name" : "Conv","activation_size" : "602112","parameter_size" : "6912","output_size" : "3211264","provider" : "CPUExecutionProvider"}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :0,"ts" :1142,"ph" : "X","name" :"conv1_fence_after","args" : {"op_name" : "Conv"}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :5,"ts" :1143,"ph" : "X","name" :"relu1_kernel_time","args" : {"op_name" : "Relu","provider" : "CPUExecutionProvider"}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :30,"ts" :1149,"ph" : "X","name" :"conv2_kernel_time","args" : {"op_name" : "Conv","provider" : "CPUExecutionProvider"}},
+{"cat" : "Session","pid" :1,"tid" :1,"dur" :90,"ts" :1095,"ph" : "X","name" :"model_run","args" : {}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :60,"ts" :1201,"ph" : "X","name" :"conv1_kernel_time","args" : {"op_name" : "Conv","provider" : "CPUExecutionProvider"}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :7,"ts" :1262,"ph" : "X","name" :"relu1_kernel_time","args" : {"op_name" : "Relu","provider" : "CPUExecutionProvider"}},
+{"cat" : "Node","pid" :1,"tid" :1,"dur" :20,"ts" :1270,"ph" : "X","name" :"conv2_kernel_time","args" : {"op_name" : "Conv","provider" : "CPUExecutionProvider"}},
+{"cat" : "Session","pid" :1,"tid" :1,"dur" :95,"ts" :1195,"ph" : "X","name" :"model_run","args" : {}}
+]
+"#;
+
+    #[test]
+    fn parse_events() {
+        let profile = Profile::from_json(TRACE).unwrap();
+        assert_eq!(profile.events.len(), 12);
+
+        let event = &profile.events[3];
+        assert_eq!(event.category, "Node");
+        assert_eq!(event.name, "conv1_kernel_time");
+        assert_eq!(event.start, Duration::from_micros(1101));
+        assert_eq!(event.duration, Duration::from_micros(40));
+        assert_eq!(event.op_type.as_deref(), Some("Conv"));
+        assert_eq!(event.provider.as_deref(), Some("CPUExecutionProvider"));
+
+        let event = &profile.events[0];
+        assert_eq!(event.category, "Session");
+        assert_eq!(event.op_type, None);
+    }
+
+    #[test]
+    fn summarize_nodes() {
+        let profile = Profile::from_json(TRACE).unwrap();
+        let summaries = profile.node_summaries();
+
+        let names: Vec<&str> = summaries.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["conv1", "conv2", "relu1"]);
+
+        let conv1 = &summaries[0];
+        assert_eq!(conv1.op_type, "Conv");
+        assert_eq!(conv1.provider.as_deref(), Some("CPUExecutionProvider"));
+        assert_eq!(conv1.calls, 2);
+        assert_eq!(conv1.total, Duration::from_micros(100));
+        assert_eq!(conv1.min, Duration::from_micros(40));
+        assert_eq!(conv1.max, Duration::from_micros(60));
+        assert_eq!(conv1.mean(), Duration::from_micros(50));
+    }
+
+    #[test]
+    fn summarize_op_types() {
+        let profile = Profile::from_json(TRACE).unwrap();
+        let summaries = profile.op_type_summaries();
+
+        assert_eq!(
+            summaries,
+            vec![
+                OpTypeSummary {
+                    op_type: "Conv".to_string(),
+                    nodes: 2,
+                    calls: 4,
+                    total: Duration::from_micros(150),
+                },
+                OpTypeSummary {
+                    op_type: "Relu".to_string(),
+                    nodes: 1,
+                    calls: 2,
+                    total: Duration::from_micros(12),
+                },
+            ]
+        );
+        assert_eq!(summaries[0].mean(), Duration::from_nanos(37_500));
+    }
+
+    #[test]
+    fn parse_invalid_trace() {
+        assert!(matches!(
+            Profile::from_json("[{\"cat\": \"Node\"}"),
+            Err(OrtError::ProfileParsing(_))
+        ));
+        assert!(matches!(
+            Profile::from_file("does-not-exist.json"),
+            Err(OrtError::ProfileRead { .. })
+        ));
+    }
+}
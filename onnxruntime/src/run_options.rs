@@ -0,0 +1,547 @@
+//! Module containing the options of a single inference call
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    ffi::CString,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use tracing::{debug, error};
+
+use onnxruntime_sys as sys;
+
+use crate::{
+    char_p_to_string,
+    error::{status_to_result, OrtApiError, OrtError, Result},
+    g_ort, LoggingLevel,
+};
+
+/// Options controlling a single inference call
+///
+/// A `RunOptions` is passed to [`Session::run_with_options()`](../session/struct.Session.html#method.run_with_options).
+/// It can be reused for multiple calls.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::error::Error;
+/// # use onnxruntime::{environment::Environment, run_options::RunOptions, LoggingLevel};
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+/// # let session = environment.new_session_builder()?.with_model_from_file("model.onnx")?;
+/// let run_options = RunOptions::new()?
+///     .with_run_tag("request-1234")?
+///     .with_log_level(LoggingLevel::Verbose)?;
+/// let array = ndarray::Array::linspace(0.0_f32, 1.0, 100);
+/// let outputs = session.run_with_options(&run_options, vec![array])?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct RunOptions {
+    inner: Arc<RunOptionsPtr>,
+    timeout: Option<Duration>,
+}
+
+/// The runtime's terminate flag is not set
+const RUNNABLE: u8 = 0;
+/// Termination was requested through `RunOptions::terminate()` or a `CancelHandle`
+const CANCELLED: u8 = 1;
+/// Termination was requested by the watchdog enforcing the timeout
+const TIMED_OUT: u8 = 2;
+
+/// Run options pointer, shared between `RunOptions` and its `CancelHandle`s
+#[derive(Debug)]
+struct RunOptionsPtr {
+    ptr: *mut sys::OrtRunOptions,
+    /// Why the runtime's terminate flag was set, if it was
+    state: AtomicU8,
+}
+
+// The runtime's terminate flag is meant to be set from another thread than the one running the
+// inference. The other settings are only modified through `RunOptions`' builder methods, which
+// take ownership of it.
+unsafe impl Send for RunOptionsPtr {}
+unsafe impl Sync for RunOptionsPtr {}
+
+impl RunOptionsPtr {
+    fn terminate(&self) -> Result<()> {
+        self.state.store(CANCELLED, Ordering::SeqCst);
+        let status = unsafe { g_ort().RunOptionsSetTerminate.unwrap()(self.ptr) };
+        status_to_result(status).map_err(OrtError::RunOptions)
+    }
+
+    fn unset_terminate(&self) -> Result<()> {
+        let status = unsafe { g_ort().RunOptionsUnsetTerminate.unwrap()(self.ptr) };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        self.state.store(RUNNABLE, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for RunOptionsPtr {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping the run options.");
+        assert_ne!(self.ptr, std::ptr::null_mut());
+        unsafe { g_ort().ReleaseRunOptions.unwrap()(self.ptr) };
+
+        self.ptr = std::ptr::null_mut();
+    }
+}
+
+impl RunOptions {
+    /// Create run options using the runtime's defaults
+    pub fn new() -> Result<RunOptions> {
+        let mut ptr: *mut sys::OrtRunOptions = std::ptr::null_mut();
+        let status = unsafe { g_ort().CreateRunOptions.unwrap()(&mut ptr) };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        assert_ne!(ptr, std::ptr::null_mut());
+
+        Ok(RunOptions {
+            inner: Arc::new(RunOptionsPtr {
+                ptr,
+                state: AtomicU8::new(RUNNABLE),
+            }),
+            timeout: None,
+        })
+    }
+
+    /// Tag the inference call, for example with a request identifier
+    ///
+    /// The tag is used by the runtime to identify the run in its logs.
+    pub fn with_run_tag<S>(self, run_tag: S) -> Result<RunOptions>
+    where
+        S: Into<String>,
+    {
+        let run_tag = CString::new(run_tag.into())?;
+        let status =
+            unsafe { g_ort().RunOptionsSetRunTag.unwrap()(self.inner.ptr, run_tag.as_ptr()) };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        Ok(self)
+    }
+
+    /// Set the minimum severity of the runtime's log messages emitted during this run only
+    ///
+    /// Messages are still filtered by the [`Environment`](../environment/struct.Environment.html)'s
+    /// logger, which is where they are forwarded to `tracing`.
+    pub fn with_log_level(self, log_level: LoggingLevel) -> Result<RunOptions> {
+        let log_level: sys::OrtLoggingLevel = log_level.into();
+        let status = unsafe {
+            g_ort().RunOptionsSetRunLogSeverityLevel.unwrap()(self.inner.ptr, log_level as i32)
+        };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        Ok(self)
+    }
+
+    /// Set the verbosity of the runtime's verbose log messages emitted during this run only
+    ///
+    /// Only used when the log level is [`LoggingLevel::Verbose`](../enum.LoggingLevel.html#variant.Verbose).
+    pub fn with_log_verbosity_level(self, verbosity_level: i32) -> Result<RunOptions> {
+        let status = unsafe {
+            g_ort().RunOptionsSetRunLogVerbosityLevel.unwrap()(self.inner.ptr, verbosity_level)
+        };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        Ok(self)
+    }
+
+    /// Abort inference calls using these options if they did not complete within `timeout`
+    ///
+    /// A watchdog thread shared by all the run options watches the inference calls; once the
+    /// timeout expired it requests termination and the call fails with [`OrtError::RunTimedOut`](../error/enum.OrtError.html#variant.RunTimedOut).
+    /// The termination flag is cleared once the aborted call returned, so the options can be reused.
+    ///
+    /// Since the termination flag is shared by all calls using these options, options with a
+    /// timeout should not be used by concurrent inference calls.
+    pub fn with_timeout(mut self, timeout: Duration) -> RunOptions {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Return the tag of the inference call
+    pub fn run_tag(&self) -> Result<String> {
+        let mut run_tag: *const i8 = std::ptr::null();
+        let status = unsafe { g_ort().RunOptionsGetRunTag.unwrap()(self.inner.ptr, &mut run_tag) };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        assert_ne!(run_tag, std::ptr::null());
+        char_p_to_string(run_tag)
+    }
+
+    /// Return the log severity level of the inference call, as the runtime's integer value
+    ///
+    /// A negative value means the environment's log level is used.
+    pub fn log_severity_level(&self) -> Result<i32> {
+        let mut level = 0;
+        let status = unsafe {
+            g_ort().RunOptionsGetRunLogSeverityLevel.unwrap()(self.inner.ptr, &mut level)
+        };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        Ok(level)
+    }
+
+    /// Return the log verbosity level of the inference call
+    pub fn log_verbosity_level(&self) -> Result<i32> {
+        let mut level = 0;
+        let status = unsafe {
+            g_ort().RunOptionsGetRunLogVerbosityLevel.unwrap()(self.inner.ptr, &mut level)
+        };
+        status_to_result(status).map_err(OrtError::RunOptions)?;
+        Ok(level)
+    }
+
+    /// Return the timeout of the inference calls, if any
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Return a handle that can cancel the inference calls using these options from another thread
+    pub fn cancel_handle(&self) -> CancelHandle {
+        CancelHandle {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
+    /// Request termination of the inference calls using these options
+    ///
+    /// The runtime checks the flag before running the graph and between the execution of its
+    /// nodes; a run using these options fails with [`OrtError::RunCancelled`](../error/enum.OrtError.html#variant.RunCancelled)
+    /// once the flag is set.
+    pub fn terminate(&self) -> Result<()> {
+        self.inner.terminate()
+    }
+
+    /// Clear the termination flag, allowing the options to be used for new inference calls
+    pub fn unset_terminate(&self) -> Result<()> {
+        self.inner.unset_terminate()
+    }
+
+    /// Perform an inference call with these options, enforcing the timeout and reporting cancellation
+    ///
+    /// `run` receives the run options pointer and returns the status of the runtime's call.
+    pub(crate) fn run<F>(&self, run: F) -> Result<()>
+    where
+        F: FnOnce(*const sys::OrtRunOptions) -> *mut sys::OrtStatus,
+    {
+        let watched_run = self
+            .timeout
+            .map(|timeout| WATCHDOG.watch(&self.inner, timeout))
+            .transpose()?;
+
+        let status = run(self.inner.ptr);
+
+        drop(watched_run);
+        let result = status_to_result(status);
+
+        // Terminating because of the timeout only affects the call that timed out
+        let timed_out = self
+            .inner
+            .state
+            .compare_exchange(TIMED_OUT, RUNNABLE, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok();
+        if timed_out {
+            let status = unsafe { g_ort().RunOptionsUnsetTerminate.unwrap()(self.inner.ptr) };
+            status_to_result(status).map_err(OrtError::RunOptions)?;
+        }
+
+        result.map_err(|e| self.run_error(e, timed_out))
+    }
+
+    fn run_error(&self, e: OrtApiError, timed_out: bool) -> OrtError {
+        match self.timeout {
+            Some(timeout) if timed_out => OrtError::RunTimedOut { timeout },
+            _ if self.inner.state.load(Ordering::SeqCst) == CANCELLED => OrtError::RunCancelled,
+            _ => OrtError::Run(e),
+        }
+    }
+}
+
+/// Handle to cancel the inference calls using a [`RunOptions`](struct.RunOptions.html)
+///
+/// The handle is cheap to clone and can be sent to another thread, for example to cancel a
+/// long running inference call:
+///
+/// ```no_run
+/// # use std::error::Error;
+/// # use onnxruntime::{environment::Environment, error::OrtError, run_options::RunOptions, LoggingLevel};
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+/// # let session = environment.new_session_builder()?.with_model_from_file("model.onnx")?;
+/// let run_options = RunOptions::new()?;
+/// let cancel_handle = run_options.cancel_handle();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+///     cancel_handle.cancel().unwrap();
+/// });
+///
+/// let array = ndarray::Array::linspace(0.0_f32, 1.0, 100);
+/// match session.run_with_options(&run_options, vec![array]) {
+///     Err(OrtError::RunCancelled) => println!("Inference was cancelled"),
+///     result => println!("Inference returned {} outputs", result?.len()),
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CancelHandle {
+    inner: Arc<RunOptionsPtr>,
+}
+
+impl CancelHandle {
+    /// Cancel the inference calls using the run options
+    ///
+    /// Same as [`RunOptions::terminate()`](struct.RunOptions.html#method.terminate): the flag
+    /// stays set until [`RunOptions::unset_terminate()`](struct.RunOptions.html#method.unset_terminate)
+    /// is called.
+    pub fn cancel(&self) -> Result<()> {
+        self.inner.terminate()
+    }
+
+    /// Return `true` if the inference calls using the run options were cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.state.load(Ordering::SeqCst) == CANCELLED
+    }
+}
+
+lazy_static! {
+    /// Watchdog shared by all the run options
+    static ref WATCHDOG: Watchdog = Watchdog {
+        state: Mutex::new(WatchdogState::default()),
+        condvar: Condvar::new(),
+    };
+}
+
+/// Timer thread terminating inference calls once their timeout expired
+///
+/// A single thread serves all the calls with a timeout, waiting for the earliest deadline. It is
+/// started by the first such call.
+struct Watchdog {
+    state: Mutex<WatchdogState>,
+    /// Notified when a deadline is added
+    condvar: Condvar,
+}
+
+#[derive(Default)]
+struct WatchdogState {
+    /// Deadlines of the calls, earliest first; those of finished calls are skipped
+    deadlines: BinaryHeap<Reverse<(Instant, u64)>>,
+    /// Calls still running, by identifier
+    runs: HashMap<u64, (Arc<RunOptionsPtr>, Duration)>,
+    next_id: u64,
+    thread_started: bool,
+}
+
+/// Inference call watched by the [`Watchdog`], which stops watching it when dropped
+struct WatchedRun {
+    id: u64,
+}
+
+impl Watchdog {
+    /// Watch an inference call, terminating it after `timeout`
+    fn watch(&'static self, inner: &Arc<RunOptionsPtr>, timeout: Duration) -> Result<WatchedRun> {
+        let mut state = self.lock();
+        if !state.thread_started {
+            thread::Builder::new()
+                .name("onnxruntime-run-watchdog".to_string())
+                .spawn(move || self.run())
+                .map_err(OrtError::SpawnThread)?;
+            state.thread_started = true;
+        }
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state
+            .deadlines
+            .push(Reverse((Instant::now() + timeout, id)));
+        state.runs.insert(id, (Arc::clone(inner), timeout));
+        self.condvar.notify_one();
+        Ok(WatchedRun { id })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, WatchdogState> {
+        self.state
+            .lock()
+            .expect("Failed to acquire lock: another thread panicked?")
+    }
+
+    /// Loop of the watchdog thread
+    fn run(&self) {
+        let mut state = self.lock();
+        loop {
+            let now = Instant::now();
+            while let Some(&Reverse((deadline, id))) = state.deadlines.peek() {
+                if deadline > now {
+                    break;
+                }
+                state.deadlines.pop();
+                // Terminating while holding the lock ensures the call is still running
+                if let Some((inner, timeout)) = state.runs.remove(&id) {
+                    terminate_after_timeout(&inner, timeout);
+                }
+            }
+
+            state = match state.deadlines.peek() {
+                Some(&Reverse((deadline, _))) => {
+                    self.condvar
+                        .wait_timeout(state, deadline.saturating_duration_since(now))
+                        .expect("Failed to acquire lock: another thread panicked?")
+                        .0
+                }
+                None => self
+                    .condvar
+                    .wait(state)
+                    .expect("Failed to acquire lock: another thread panicked?"),
+            };
+        }
+    }
+}
+
+impl Drop for WatchedRun {
+    fn drop(&mut self) {
+        // The deadline is left in the heap, and skipped once expired
+        WATCHDOG.lock().runs.remove(&self.id);
+    }
+}
+
+fn terminate_after_timeout(inner: &RunOptionsPtr, timeout: Duration) {
+    // Don't hide an explicit cancellation
+    let running = inner
+        .state
+        .compare_exchange(RUNNABLE, TIMED_OUT, Ordering::SeqCst, Ordering::SeqCst)
+        .is_ok();
+    if running {
+        debug!(?timeout, "Terminating the run after its timeout expired.");
+        let status = unsafe { g_ort().RunOptionsSetTerminate.unwrap()(inner.ptr) };
+        if let Err(e) = status_to_result(status) {
+            error!("Failed to terminate the run: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_env_log::test;
+
+    #[test]
+    fn run_options_defaults() {
+        let run_options = RunOptions::new().unwrap();
+        assert_eq!(run_options.run_tag().unwrap(), "");
+        assert_eq!(run_options.log_verbosity_level().unwrap(), 0);
+        assert_eq!(run_options.timeout(), None);
+    }
+
+    #[test]
+    fn run_options_setters() {
+        let run_options = RunOptions::new()
+            .unwrap()
+            .with_run_tag("request-1234")
+            .unwrap()
+            .with_log_level(LoggingLevel::Verbose)
+            .unwrap()
+            .with_log_verbosity_level(2)
+            .unwrap()
+            .with_timeout(Duration::from_secs(1));
+        assert_eq!(run_options.run_tag().unwrap(), "request-1234");
+        assert_eq!(
+            run_options.log_severity_level().unwrap(),
+            sys::OrtLoggingLevel::ORT_LOGGING_LEVEL_VERBOSE as i32
+        );
+        assert_eq!(run_options.log_verbosity_level().unwrap(), 2);
+        assert_eq!(run_options.timeout(), Some(Duration::from_secs(1)));
+
+        run_options.terminate().unwrap();
+        run_options.unset_terminate().unwrap();
+    }
+
+    #[test]
+    fn cancel_handle_from_another_thread() {
+        let run_options = RunOptions::new().unwrap();
+        let cancel_handle = run_options.cancel_handle();
+        assert!(!cancel_handle.is_cancelled());
+
+        let cancel_handle_clone = cancel_handle.clone();
+        thread::spawn(move || cancel_handle_clone.cancel().unwrap())
+            .join()
+            .unwrap();
+        assert!(cancel_handle.is_cancelled());
+
+        run_options.unset_terminate().unwrap();
+        assert!(!cancel_handle.is_cancelled());
+    }
+
+    #[test]
+    fn run_reports_cancellation_and_timeout() {
+        let run_options = RunOptions::new().unwrap();
+        run_options.terminate().unwrap();
+        let result = run_options.run(|_| unsafe {
+            g_ort().CreateStatus.unwrap()(
+                sys::OrtErrorCode::ORT_FAIL,
+                b"terminated\0".as_ptr() as _,
+            )
+        });
+        assert!(matches!(result, Err(OrtError::RunCancelled)));
+        run_options.unset_terminate().unwrap();
+
+        let run_options = run_options.with_timeout(Duration::from_millis(10));
+        let result = run_options.run(|_| {
+            thread::sleep(Duration::from_millis(200));
+            unsafe {
+                g_ort().CreateStatus.unwrap()(
+                    sys::OrtErrorCode::ORT_FAIL,
+                    b"terminated\0".as_ptr() as _,
+                )
+            }
+        });
+        assert!(matches!(result, Err(OrtError::RunTimedOut { .. })));
+        // The terminate flag set by the timeout is cleared after the run
+        assert!(!run_options.cancel_handle().is_cancelled());
+        assert!(run_options.run(|_| std::ptr::null_mut()).is_ok());
+    }
+
+    #[test]
+    fn concurrent_timeouts() {
+        let failed_status = || unsafe {
+            g_ort().CreateStatus.unwrap()(
+                sys::OrtErrorCode::ORT_FAIL,
+                b"terminated\0".as_ptr() as _,
+            )
+        };
+
+        // Only the calls whose timeout expired are terminated
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                thread::spawn(move || {
+                    let expires = i % 2 == 0;
+                    let timeout = if expires {
+                        Duration::from_millis(10)
+                    } else {
+                        Duration::from_secs(60)
+                    };
+                    let run_options = RunOptions::new().unwrap().with_timeout(timeout);
+                    let result = run_options.run(|_| {
+                        thread::sleep(Duration::from_millis(200));
+                        if run_options.inner.state.load(Ordering::SeqCst) == TIMED_OUT {
+                            failed_status()
+                        } else {
+                            std::ptr::null_mut()
+                        }
+                    });
+                    (expires, result)
+                })
+            })
+            .collect();
+        for handle in handles {
+            match handle.join().unwrap() {
+                (true, result) => assert!(matches!(result, Err(OrtError::RunTimedOut { .. }))),
+                (false, result) => assert!(result.is_ok()),
+            }
+        }
+    }
+}
@@ -1,6 +1,13 @@
 //! Module containing session types
 
-use std::{ffi::CString, fmt::Debug, path::Path};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::TryFrom,
+    ffi::{c_void, CString},
+    fmt::Debug,
+    path::{Path, PathBuf},
+};
 
 #[cfg(not(target_family = "windows"))]
 use std::os::unix::ffi::OsStrExt;
@@ -10,33 +17,121 @@ use std::os::windows::ffi::OsStrExt;
 #[cfg(feature = "model-fetching")]
 use std::env;
 
-use ndarray::Array;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 use onnxruntime_sys as sys;
 
 use crate::{
     char_p_to_string,
+    custom_op::{self, CustomOpDomain, CustomOpErrors, CustomOpsLibrary},
     environment::Environment,
     error::{status_to_result, NonMatchingDimensionsError, OrtError, Result},
+    execution_provider::{self, ExecutionProvider, ProviderFallback},
     g_ort,
+    io_binding::IoBinding,
     memory::MemoryInfo,
+    metadata::ModelMetadata,
+    run_options::RunOptions,
     tensor::{
-        ort_owned_tensor::{OrtOwnedTensor, OrtOwnedTensorExtractor},
-        OrtTensor,
+        input_tensor::{InitializerTensor, OrtInput},
+        DynOrtTensor, InputTensor, OutputBuffer,
     },
-    AllocatorType, GraphOptimizationLevel, MemType, TensorElementDataType,
-    TypeToTensorElementDataType,
+    value::{DynOrtValue, InputValue},
+    AllocatorType, ExecutionMode, GraphOptimizationLevel, MemType, TensorElementDataType,
 };
 
 #[cfg(feature = "model-fetching")]
 use crate::{download::AvailableOnnxModel, error::OrtDownloadError};
 
+/// Session configuration entry, set with [`SessionBuilder::with_config_entry()`](struct.SessionBuilder.html#method.with_config_entry)
+///
+/// The runtime reads those entries as key/value string pairs; the known keys are listed in its
+/// `onnxruntime_session_options_config_keys.h` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionConfigEntry {
+    /// Disable the pre-packing of constant initializers (`session.disable_prepacking`)
+    DisablePrepacking(bool),
+    /// Use the allocators registered in the environment instead of the session's own
+    /// (`session.use_env_allocators`)
+    UseEnvAllocators(bool),
+    /// Format of the model to load (`session.load_model_format`)
+    ///
+    /// By default, the format is deduced from the model's file extension.
+    LoadModelFormat(ModelFormat),
+    /// Format of the optimized model to save (`session.save_model_format`)
+    ///
+    /// By default, the format is deduced from the output file extension.
+    SaveModelFormat(ModelFormat),
+    /// Flush denormal floating point numbers to zero (`session.set_denormal_as_zero`)
+    SetDenormalAsZero(bool),
+    /// Any other entry, given as its key and value
+    Other {
+        /// Key of the entry
+        key: String,
+        /// Value of the entry
+        value: String,
+    },
+}
+
+impl SessionConfigEntry {
+    /// Key of the entry, as known by the runtime
+    pub fn key(&self) -> &str {
+        match self {
+            SessionConfigEntry::DisablePrepacking(_) => "session.disable_prepacking",
+            SessionConfigEntry::UseEnvAllocators(_) => "session.use_env_allocators",
+            SessionConfigEntry::LoadModelFormat(_) => "session.load_model_format",
+            SessionConfigEntry::SaveModelFormat(_) => "session.save_model_format",
+            SessionConfigEntry::SetDenormalAsZero(_) => "session.set_denormal_as_zero",
+            SessionConfigEntry::Other { key, .. } => key,
+        }
+    }
+
+    /// Value of the entry, as given to the runtime
+    pub fn value(&self) -> &str {
+        fn flag(enable: bool) -> &'static str {
+            if enable {
+                "1"
+            } else {
+                "0"
+            }
+        }
+
+        match self {
+            SessionConfigEntry::DisablePrepacking(enable)
+            | SessionConfigEntry::UseEnvAllocators(enable)
+            | SessionConfigEntry::SetDenormalAsZero(enable) => flag(*enable),
+            SessionConfigEntry::LoadModelFormat(format)
+            | SessionConfigEntry::SaveModelFormat(format) => format.as_str(),
+            SessionConfigEntry::Other { value, .. } => value,
+        }
+    }
+}
+
+/// Format of a model file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFormat {
+    /// ONNX protobuf format
+    Onnx,
+    /// ONNX Runtime's own format, loaded by minimal builds of the runtime
+    Ort,
+}
+
+impl ModelFormat {
+    fn as_str(self) -> &'static str {
+        match self {
+            ModelFormat::Onnx => "ONNX",
+            ModelFormat::Ort => "ORT",
+        }
+    }
+}
+
 /// Type used to create a session using the _builder pattern_
 ///
 /// A `SessionBuilder` is created by calling the
 /// [`Environment::new_session_builder()`](../env/struct.Environment.html#method.new_session_builder)
-/// method on the environment.
+/// method on the environment, or by calling
+/// [`Environment::new_owned_session_builder()`](../environment/struct.Environment.html#method.new_owned_session_builder)
+/// to obtain a `'static` session which does not borrow the environment.
 ///
 /// Once created, use the different methods to configure the session.
 ///
@@ -53,7 +148,7 @@ use crate::{download::AvailableOnnxModel, error::OrtDownloadError};
 ///     .with_name("test")
 ///     .with_log_level(LoggingLevel::Verbose)
 ///     .build()?;
-/// let mut session = environment
+/// let session = environment
 ///     .new_session_builder()?
 ///     .with_optimization_level(GraphOptimizationLevel::Basic)?
 ///     .with_number_threads(1)?
@@ -63,11 +158,16 @@ use crate::{download::AvailableOnnxModel, error::OrtDownloadError};
 /// ```
 #[derive(Debug)]
 pub struct SessionBuilder<'a> {
-    env: &'a Environment,
+    env: Cow<'a, Environment>,
     session_options_ptr: *mut sys::OrtSessionOptions,
 
     allocator: AllocatorType,
     memory_type: MemType,
+    initializers: Vec<InitializerTensor<'a>>,
+    custom_op_domains: Vec<CustomOpDomain>,
+    custom_ops_libraries: Vec<CustomOpsLibrary>,
+    /// Execution providers appended to a copy of the session options when the session is created
+    execution_providers: Vec<(ExecutionProvider, ProviderFallback)>,
 }
 
 impl<'a> Drop for SessionBuilder<'a> {
@@ -80,7 +180,7 @@ impl<'a> Drop for SessionBuilder<'a> {
 }
 
 impl<'a> SessionBuilder<'a> {
-    pub(crate) fn new(env: &'a Environment) -> Result<SessionBuilder<'a>> {
+    pub(crate) fn new(env: Cow<'a, Environment>) -> Result<SessionBuilder<'a>> {
         let mut session_options_ptr: *mut sys::OrtSessionOptions = std::ptr::null_mut();
         let status = unsafe { g_ort().CreateSessionOptions.unwrap()(&mut session_options_ptr) };
 
@@ -93,6 +193,10 @@ impl<'a> SessionBuilder<'a> {
             session_options_ptr,
             allocator: AllocatorType::Arena,
             memory_type: MemType::Default,
+            initializers: Vec::new(),
+            custom_op_domains: Vec::new(),
+            custom_ops_libraries: Vec::new(),
+            execution_providers: Vec::new(),
         })
     }
 
@@ -109,6 +213,82 @@ impl<'a> SessionBuilder<'a> {
         Ok(self)
     }
 
+    /// Configure the session to use a number of threads to run independent nodes in parallel
+    ///
+    /// Only used in the [`ExecutionMode::Parallel`](../enum.ExecutionMode.html#variant.Parallel)
+    /// execution mode.
+    pub fn with_inter_op_number_threads(self, num_threads: i16) -> Result<SessionBuilder<'a>> {
+        let num_threads = i32::from(num_threads);
+        let status =
+            unsafe { g_ort().SetInterOpNumThreads.unwrap()(self.session_options_ptr, num_threads) };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
+    /// Set the session's execution mode
+    ///
+    /// Defaults to [`ExecutionMode::Sequential`](../enum.ExecutionMode.html#variant.Sequential)
+    pub fn with_execution_mode(self, execution_mode: ExecutionMode) -> Result<SessionBuilder<'a>> {
+        let status = unsafe {
+            g_ort().SetSessionExecutionMode.unwrap()(
+                self.session_options_ptr,
+                execution_mode.into(),
+            )
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
+    /// Enable or disable the memory pattern optimization
+    ///
+    /// The runtime records the memory allocations of an inference call to pre-allocate memory for
+    /// the next calls with the same input shapes. Disabling it can reduce memory usage when the input
+    /// shapes often change. Enabled by default.
+    pub fn with_memory_pattern(self, enable: bool) -> Result<SessionBuilder<'a>> {
+        let status = unsafe {
+            if enable {
+                g_ort().EnableMemPattern.unwrap()(self.session_options_ptr)
+            } else {
+                g_ort().DisableMemPattern.unwrap()(self.session_options_ptr)
+            }
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
+    /// Enable or disable the arena of the CPU memory allocated by the runtime
+    ///
+    /// The arena keeps freed memory for later allocations instead of returning it to the system.
+    /// Enabled by default.
+    pub fn with_cpu_memory_arena(self, enable: bool) -> Result<SessionBuilder<'a>> {
+        let status = unsafe {
+            if enable {
+                g_ort().EnableCpuMemArena.unwrap()(self.session_options_ptr)
+            } else {
+                g_ort().DisableCpuMemArena.unwrap()(self.session_options_ptr)
+            }
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
+    /// Add a session configuration entry
+    ///
+    /// See [`SessionConfigEntry`](enum.SessionConfigEntry.html) for the known entries.
+    pub fn with_config_entry(self, entry: SessionConfigEntry) -> Result<SessionBuilder<'a>> {
+        let key = CString::new(entry.key())?;
+        let value = CString::new(entry.value())?;
+        let status = unsafe {
+            g_ort().AddSessionConfigEntry.unwrap()(
+                self.session_options_ptr,
+                key.as_ptr(),
+                value.as_ptr(),
+            )
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
     /// Set the session's optimization level
     pub fn with_optimization_level(
         self,
@@ -126,6 +306,8 @@ impl<'a> SessionBuilder<'a> {
 
     /// Set the session's allocator
     ///
+    /// Used to describe the memory of the inputs and initializers given to the runtime.
+    ///
     /// Defaults to [`AllocatorType::Arena`](../enum.AllocatorType.html#variant.Arena)
     pub fn with_allocator(mut self, allocator: AllocatorType) -> Result<SessionBuilder<'a>> {
         self.allocator = allocator;
@@ -134,12 +316,144 @@ impl<'a> SessionBuilder<'a> {
 
     /// Set the session's memory type
     ///
+    /// Used to describe the memory of the inputs and initializers given to the runtime.
+    ///
     /// Defaults to [`MemType::Default`](../enum.MemType.html#variant.Default)
     pub fn with_memory_type(mut self, memory_type: MemType) -> Result<SessionBuilder<'a>> {
         self.memory_type = memory_type;
         Ok(self)
     }
 
+    /// Fix the size of the free (symbolic) dimensions with the given denotation, like
+    /// `DATA_BATCH` or `DATA_CHANNEL`
+    ///
+    /// Models exported with symbolic dimensions prevent the runtime from planning memory ahead of
+    /// time; fixing their size allows better optimizations. The session's
+    /// [`inputs`](struct.Session.html#structfield.inputs) then report the fixed size, which inputs
+    /// must match.
+    ///
+    /// **NOTE**: The overrides are applied by the graph optimizer, so they are ignored when
+    /// optimizations are disabled with [`GraphOptimizationLevel::DisableAll`](../enum.GraphOptimizationLevel.html#variant.DisableAll).
+    pub fn with_free_dimension_override(
+        self,
+        dimension_denotation: &str,
+        dimension_size: u32,
+    ) -> Result<SessionBuilder<'a>> {
+        let dimension_denotation = CString::new(dimension_denotation)?;
+        let status = unsafe {
+            g_ort().AddFreeDimensionOverride.unwrap()(
+                self.session_options_ptr,
+                dimension_denotation.as_ptr(),
+                i64::from(dimension_size),
+            )
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
+    /// Fix the size of the free (symbolic) dimensions with the given name, like `batch` or
+    /// `seq_len`
+    ///
+    /// See [`with_free_dimension_override()`](#method.with_free_dimension_override).
+    pub fn with_free_dimension_override_by_name(
+        self,
+        dimension_name: &str,
+        dimension_size: u32,
+    ) -> Result<SessionBuilder<'a>> {
+        let dimension_name = CString::new(dimension_name)?;
+        let status = unsafe {
+            g_ort().AddFreeDimensionOverrideByName.unwrap()(
+                self.session_options_ptr,
+                dimension_name.as_ptr(),
+                i64::from(dimension_size),
+            )
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
+    /// Add an initializer to the session, replacing the one of the same name in the model
+    ///
+    /// The tensor's data is not copied: it is kept alive (or borrowed, for a view) for the lifetime
+    /// of the resulting [`Session`](../session/struct.Session.html). This allows sharing large
+    /// weights between sessions, or providing weights not stored in the model file.
+    ///
+    /// **NOTE**: String tensors are not supported.
+    pub fn with_initializer<T>(mut self, name: &str, tensor: T) -> Result<SessionBuilder<'a>>
+    where
+        T: Into<InputTensor<'a>>,
+    {
+        let memory_info = MemoryInfo::new(self.allocator.clone(), self.memory_type.clone())?;
+        let initializer = tensor.into().into_initializer(&memory_info)?;
+        let name = CString::new(name)?;
+
+        let status = unsafe {
+            g_ort().AddInitializer.unwrap()(
+                self.session_options_ptr,
+                name.as_ptr(),
+                initializer.c_ptr,
+            )
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+
+        self.initializers.push(initializer);
+        Ok(self)
+    }
+
+    /// Register a domain of custom operators implemented in Rust, used by the nodes of the model
+    /// which the runtime does not implement
+    ///
+    /// See the [`custom_op`](../custom_op/index.html) module. The operators are kept alive by the
+    /// session.
+    pub fn with_custom_op_domain(mut self, domain: CustomOpDomain) -> Result<SessionBuilder<'a>> {
+        let status =
+            unsafe { g_ort().AddCustomOpDomain.unwrap()(self.session_options_ptr, domain.ptr()) };
+        status_to_result(status).map_err(OrtError::CustomOpDomain)?;
+
+        self.custom_op_domains.push(domain);
+        Ok(self)
+    }
+
+    /// Set the execution providers of the session, in order of preference
+    ///
+    /// Each node of the model runs on the first provider supporting it; the CPU provider always
+    /// comes last and supports every node. Providers which are not compiled into the runtime
+    /// library are skipped with a warning or reported as an error by this method, depending on
+    /// `fallback`. The other ones are appended when the model is loaded: with
+    /// [`ProviderFallback::Skip`](../execution_provider/enum.ProviderFallback.html#variant.Skip),
+    /// the providers failing to initialize are skipped, and if the session can't be created with
+    /// the remaining ones, it is created again on the CPU provider alone. See the
+    /// [`execution_provider`](../execution_provider/index.html) module.
+    ///
+    /// Calling this method more than once adds the providers after the previous ones.
+    pub fn with_execution_providers<I>(
+        mut self,
+        providers: I,
+        fallback: ProviderFallback,
+    ) -> Result<SessionBuilder<'a>>
+    where
+        I: IntoIterator<Item = ExecutionProvider>,
+    {
+        let selected = execution_provider::select_providers(providers, fallback)?;
+        self.execution_providers.extend(selected);
+        Ok(self)
+    }
+
+    /// Load a shared library of custom operators, like the ones of
+    /// [onnxruntime-extensions](https://github.com/microsoft/onnxruntime-extensions)
+    ///
+    /// The library must export a `RegisterCustomOps` function registering its operators. It stays
+    /// loaded for as long as the session exists. A bare file name, like `libcustom_ops.so`, is
+    /// looked up in the system's library search path.
+    pub fn with_custom_ops_library<P>(mut self, library_path: P) -> Result<SessionBuilder<'a>>
+    where
+        P: AsRef<Path>,
+    {
+        let library = CustomOpsLibrary::register(self.session_options_ptr, library_path.as_ref())?;
+        self.custom_ops_libraries.push(library);
+        Ok(self)
+    }
+
     /// Download an ONNX pre-trained model from the [ONNX Model Zoo](https://github.com/onnx/models) and commit the session
     #[cfg(feature = "model-fetching")]
     pub fn with_model_downloaded<M>(self, model: M) -> Result<Session<'a>>
@@ -156,11 +470,57 @@ impl<'a> SessionBuilder<'a> {
         self.with_model_from_file(downloaded_path)
     }
 
+    /// Enable profiling, writing the trace of the session to a file whose name starts with
+    /// `profile_file_prefix`
+    ///
+    /// The runtime records the execution time of each node of the graph, for each inference call.
+    /// Use [`Session::end_profiling()`](struct.Session.html#method.end_profiling) to write the trace
+    /// and [`Profile`](../profiling/struct.Profile.html) to parse it (requires the `profiling` feature).
+    pub fn with_profiling<P>(self, profile_file_prefix: P) -> Result<SessionBuilder<'a>>
+    where
+        P: AsRef<Path>,
+    {
+        let profile_file_prefix = path_to_ort_string(profile_file_prefix.as_ref());
+        let status = unsafe {
+            g_ort().EnableProfiling.unwrap()(self.session_options_ptr, profile_file_prefix.as_ptr())
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
+    /// Write the graph to `optimized_model_filepath` once optimized, when committing the session
+    ///
+    /// Loading the optimized model in a session with optimizations disabled
+    /// ([`GraphOptimizationLevel::DisableAll`](../enum.GraphOptimizationLevel.html#variant.DisableAll))
+    /// skips the optimization at startup. See also [`Environment::optimize_model()`](../environment/struct.Environment.html#method.optimize_model).
+    ///
+    /// **NOTE**: The [`Extended`](../enum.GraphOptimizationLevel.html#variant.Extended) and
+    /// [`All`](../enum.GraphOptimizationLevel.html#variant.All) levels may fuse nodes into
+    /// operators specific to the runtime and to the session's execution providers; the optimized
+    /// model must then be loaded in the same conditions.
+    pub fn with_optimized_model_output<P>(
+        self,
+        optimized_model_filepath: P,
+    ) -> Result<SessionBuilder<'a>>
+    where
+        P: AsRef<Path>,
+    {
+        let optimized_model_filepath = path_to_ort_string(optimized_model_filepath.as_ref());
+        let status = unsafe {
+            g_ort().SetOptimizedModelFilePath.unwrap()(
+                self.session_options_ptr,
+                optimized_model_filepath.as_ptr(),
+            )
+        };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        Ok(self)
+    }
+
     // TODO: Add all functions changing the options.
     //       See all OrtApi methods taking a `options: *mut OrtSessionOptions`.
 
     /// Load an ONNX graph from a file and commit the session
-    pub fn with_model_from_file<P>(self, model_filepath_ref: P) -> Result<Session<'a>>
+    pub fn with_model_from_file<P>(mut self, model_filepath_ref: P) -> Result<Session<'a>>
     where
         P: AsRef<Path> + 'a,
     {
@@ -173,33 +533,18 @@ impl<'a> SessionBuilder<'a> {
             });
         }
 
-        // Build an OsString than a vector of bytes to pass to C
-        let model_path = std::ffi::OsString::from(model_filepath);
-        #[cfg(target_family = "windows")]
-        let model_path: Vec<u16> = model_path
-            .encode_wide()
-            .chain(std::iter::once(0)) // Make sure we have a null terminated string
-            .collect();
-        #[cfg(not(target_family = "windows"))]
-        let model_path: Vec<std::os::raw::c_char> = model_path
-            .as_bytes()
-            .iter()
-            .chain(std::iter::once(&b'\0')) // Make sure we have a null terminated string
-            .map(|b| *b as std::os::raw::c_char)
-            .collect();
+        let model_path = path_to_ort_string(model_filepath);
 
         let env_ptr: *const sys::OrtEnv = self.env.env_ptr();
 
-        let status = unsafe {
+        let execution_providers = self.create_session(|session_options_ptr| unsafe {
             g_ort().CreateSession.unwrap()(
                 env_ptr,
                 model_path.as_ptr(),
-                self.session_options_ptr,
+                session_options_ptr,
                 &mut session_ptr,
             )
-        };
-        status_to_result(status).map_err(OrtError::Session)?;
-        assert_eq!(status, std::ptr::null_mut());
+        })?;
         assert_ne!(session_ptr, std::ptr::null_mut());
 
         let mut allocator_ptr: *mut sys::OrtAllocator = std::ptr::null_mut();
@@ -208,7 +553,7 @@ impl<'a> SessionBuilder<'a> {
         assert_eq!(status, std::ptr::null_mut());
         assert_ne!(allocator_ptr, std::ptr::null_mut());
 
-        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default)?;
+        let memory_info = MemoryInfo::new(self.allocator.clone(), self.memory_type.clone())?;
 
         // Extract input and output properties
         let num_input_nodes = dangerous::extract_inputs_count(session_ptr)?;
@@ -219,15 +564,33 @@ impl<'a> SessionBuilder<'a> {
         let outputs = (0..num_output_nodes)
             .map(|i| dangerous::extract_output(session_ptr, allocator_ptr, i))
             .collect::<Result<Vec<Output>>>()?;
-
-        Ok(Session {
-            env: self.env,
+        let num_overridable_initializers =
+            dangerous::extract_overridable_initializers_count(session_ptr)?;
+        let overridable_initializers = (0..num_overridable_initializers)
+            .map(|i| dangerous::extract_overridable_initializer(session_ptr, allocator_ptr, i))
+            .collect::<Result<Vec<OverridableInitializer>>>()?;
+
+        let session = Session {
+            _env: self.env.clone(),
             session_ptr,
             allocator_ptr,
             memory_info,
             inputs,
             outputs,
-        })
+            overridable_initializers,
+            _initializers: std::mem::take(&mut self.initializers),
+            custom_op_errors: self
+                .custom_op_domains
+                .iter()
+                .map(CustomOpDomain::errors)
+                .collect(),
+            _custom_op_domains: std::mem::take(&mut self.custom_op_domains),
+            _custom_ops_libraries: std::mem::take(&mut self.custom_ops_libraries),
+            execution_providers,
+        };
+        // The kernels of custom operators are created with the session
+        custom_op::take_errors(&session.custom_op_errors)?;
+        Ok(session)
     }
 
     /// Load an ONNX graph from memory and commit the session
@@ -238,24 +601,22 @@ impl<'a> SessionBuilder<'a> {
         self.with_model_from_memory_monomorphized(model_bytes.as_ref())
     }
 
-    fn with_model_from_memory_monomorphized(self, model_bytes: &[u8]) -> Result<Session<'a>> {
+    fn with_model_from_memory_monomorphized(mut self, model_bytes: &[u8]) -> Result<Session<'a>> {
         let mut session_ptr: *mut sys::OrtSession = std::ptr::null_mut();
 
         let env_ptr: *const sys::OrtEnv = self.env.env_ptr();
 
-        let status = unsafe {
+        let execution_providers = self.create_session(|session_options_ptr| unsafe {
             let model_data = model_bytes.as_ptr() as *const std::ffi::c_void;
             let model_data_length = model_bytes.len() as u64;
             g_ort().CreateSessionFromArray.unwrap()(
                 env_ptr,
                 model_data,
                 model_data_length,
-                self.session_options_ptr,
+                session_options_ptr,
                 &mut session_ptr,
             )
-        };
-        status_to_result(status).map_err(OrtError::Session)?;
-        assert_eq!(status, std::ptr::null_mut());
+        })?;
         assert_ne!(session_ptr, std::ptr::null_mut());
 
         let mut allocator_ptr: *mut sys::OrtAllocator = std::ptr::null_mut();
@@ -264,7 +625,7 @@ impl<'a> SessionBuilder<'a> {
         assert_eq!(status, std::ptr::null_mut());
         assert_ne!(allocator_ptr, std::ptr::null_mut());
 
-        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default)?;
+        let memory_info = MemoryInfo::new(self.allocator.clone(), self.memory_type.clone())?;
 
         // Extract input and output properties
         let num_input_nodes = dangerous::extract_inputs_count(session_ptr)?;
@@ -275,42 +636,166 @@ impl<'a> SessionBuilder<'a> {
         let outputs = (0..num_output_nodes)
             .map(|i| dangerous::extract_output(session_ptr, allocator_ptr, i))
             .collect::<Result<Vec<Output>>>()?;
-
-        Ok(Session {
-            env: self.env,
+        let num_overridable_initializers =
+            dangerous::extract_overridable_initializers_count(session_ptr)?;
+        let overridable_initializers = (0..num_overridable_initializers)
+            .map(|i| dangerous::extract_overridable_initializer(session_ptr, allocator_ptr, i))
+            .collect::<Result<Vec<OverridableInitializer>>>()?;
+
+        let session = Session {
+            _env: self.env.clone(),
             session_ptr,
             allocator_ptr,
             memory_info,
             inputs,
             outputs,
-        })
+            overridable_initializers,
+            _initializers: std::mem::take(&mut self.initializers),
+            custom_op_errors: self
+                .custom_op_domains
+                .iter()
+                .map(CustomOpDomain::errors)
+                .collect(),
+            _custom_op_domains: std::mem::take(&mut self.custom_op_domains),
+            _custom_ops_libraries: std::mem::take(&mut self.custom_ops_libraries),
+            execution_providers,
+        };
+        // The kernels of custom operators are created with the session
+        custom_op::take_errors(&session.custom_op_errors)?;
+        Ok(session)
+    }
+
+    /// Create the session with `create`, given the session options, returning the execution
+    /// providers it uses
+    ///
+    /// The providers are appended to a copy of the session options, so that the session can be
+    /// created again without them if it fails and they all have the `Skip` fallback.
+    fn create_session<F>(&mut self, mut create: F) -> Result<Vec<ExecutionProvider>>
+    where
+        F: FnMut(*const sys::OrtSessionOptions) -> *mut sys::OrtStatus,
+    {
+        if self.execution_providers.is_empty() {
+            status_to_result(create(self.session_options_ptr)).map_err(OrtError::Session)?;
+            return Ok(vec![ExecutionProvider::Cpu]);
+        }
+
+        let session_options = SessionOptionsCopy::new(self.session_options_ptr)?;
+        let execution_providers =
+            execution_provider::append_providers(session_options.ptr, &self.execution_providers)?;
+        let err = match status_to_result(create(session_options.ptr)) {
+            Ok(()) => return Ok(execution_providers),
+            Err(err) => OrtError::Session(err),
+        };
+
+        let skippable = self
+            .execution_providers
+            .iter()
+            .all(|(_, fallback)| *fallback == ProviderFallback::Skip);
+        if execution_providers.len() == 1 || !skippable {
+            return Err(err);
+        }
+        warn!(
+            "Failed to create the session with execution providers {:?}, falling back to the CPU: {}",
+            execution_providers, err
+        );
+        status_to_result(create(self.session_options_ptr)).map_err(OrtError::Session)?;
+        Ok(vec![ExecutionProvider::Cpu])
+    }
+}
+
+/// Copy of session options, released when dropped
+#[derive(Debug)]
+struct SessionOptionsCopy {
+    ptr: *mut sys::OrtSessionOptions,
+}
+
+impl SessionOptionsCopy {
+    fn new(session_options_ptr: *const sys::OrtSessionOptions) -> Result<SessionOptionsCopy> {
+        let mut ptr: *mut sys::OrtSessionOptions = std::ptr::null_mut();
+        let status = unsafe { g_ort().CloneSessionOptions.unwrap()(session_options_ptr, &mut ptr) };
+        status_to_result(status).map_err(OrtError::SessionOptions)?;
+        assert_ne!(ptr, std::ptr::null_mut());
+        Ok(SessionOptionsCopy { ptr })
+    }
+}
+
+impl Drop for SessionOptionsCopy {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping the copy of the session options.");
+        unsafe { g_ort().ReleaseSessionOptions.unwrap()(self.ptr) };
+
+        self.ptr = std::ptr::null_mut();
     }
 }
 
 /// Type storing the session information, built from an [`Environment`](environment/struct.Environment.html)
+///
+/// Inference methods take `&self`: the runtime supports concurrent inference calls on the same
+/// session, so a `Session` can be shared between threads (for example behind an `Arc`) without
+/// a `Mutex`.
+///
+/// A session either borrows its environment (`Session<'a>`, created with
+/// [`Environment::new_session_builder()`](../environment/struct.Environment.html#method.new_session_builder))
+/// or holds a clone of it (`Session<'static>`, created with
+/// [`Environment::new_owned_session_builder()`](../environment/struct.Environment.html#method.new_owned_session_builder)).
+/// An owned session can be stored in application state or moved into spawned threads.
 #[derive(Debug)]
 pub struct Session<'a> {
-    env: &'a Environment,
-    session_ptr: *mut sys::OrtSession,
-    allocator_ptr: *mut sys::OrtAllocator,
-    memory_info: MemoryInfo,
+    /// Environment of the session, kept alive as long as the session
+    _env: Cow<'a, Environment>,
+    pub(crate) session_ptr: *mut sys::OrtSession,
+    pub(crate) allocator_ptr: *mut sys::OrtAllocator,
+    pub(crate) memory_info: MemoryInfo,
     /// Information about the ONNX's inputs as stored in loaded file
     pub inputs: Vec<Input>,
     /// Information about the ONNX's outputs as stored in loaded file
     pub outputs: Vec<Output>,
+    /// Information about the ONNX's initializers which can be overridden by inference calls
+    pub overridable_initializers: Vec<OverridableInitializer>,
+    /// Execution providers of the session, in order of preference, ending with the CPU provider
+    ///
+    /// Providers skipped by [`SessionBuilder::with_execution_providers()`](struct.SessionBuilder.html#method.with_execution_providers),
+    /// or dropped because the session could not be created with them, are not listed.
+    pub execution_providers: Vec<ExecutionProvider>,
+    /// Initializers added by the builder, released after the session
+    _initializers: Vec<InitializerTensor<'a>>,
+    /// Errors recorded by the custom operators, reported by the session's calls
+    pub(crate) custom_op_errors: Vec<CustomOpErrors>,
+    /// Custom operators added by the builder, released after the session
+    _custom_op_domains: Vec<CustomOpDomain>,
+    /// Libraries of custom operators loaded by the builder, unloaded after the session
+    _custom_ops_libraries: Vec<CustomOpsLibrary>,
 }
 
+// Safety audit of the pointers held by a `Session`:
+// - `session_ptr`: the runtime documents `Run()` as safe to call concurrently on the same session.
+//   The session is otherwise only read (inputs/outputs information at construction) and released
+//   in `Drop`, which requires exclusive ownership.
+// - `allocator_ptr`: the runtime's default CPU allocator, a process-wide allocator which is
+//   thread-safe and never released.
+// - `memory_info`: see `MemoryInfo`, only read after its creation.
+// - `_initializers`: values only read by the runtime, released in `Drop` after the session.
+// - `_custom_op_domains`: see `CustomOpDomain`, released in `Drop` after the session.
+// - `_custom_ops_libraries`: handles only used to unload the libraries in `Drop` after the session.
+unsafe impl<'a> Send for Session<'a> {}
+unsafe impl<'a> Sync for Session<'a> {}
+
 /// Information about an ONNX's input as stored in loaded file
 #[derive(Debug)]
 pub struct Input {
     /// Name of the input layer
     pub name: String,
     /// Type of the input layer's elements
+    ///
+    /// For sequences and maps, type of the elements of their innermost tensors.
     pub input_type: TensorElementDataType,
     /// Shape of the input layer
     ///
-    /// C API uses a i64 for the dimensions. We use an unsigned of the same range of the positive values.
-    pub dimensions: Vec<Option<u32>>,
+    /// Empty for sequences and maps.
+    pub dimensions: Vec<Dimension>,
+    /// Type of the input layer: tensor, sequence or map
+    pub value_type: ValueType,
 }
 
 /// Information about an ONNX's output as stored in loaded file
@@ -319,32 +804,151 @@ pub struct Output {
     /// Name of the output layer
     pub name: String,
     /// Type of the output layer's elements
+    ///
+    /// For sequences and maps, type of the elements of their innermost tensors.
     pub output_type: TensorElementDataType,
     /// Shape of the output layer
     ///
+    /// Empty for sequences and maps.
+    pub dimensions: Vec<Dimension>,
+    /// Type of the output layer: tensor, sequence or map
+    pub value_type: ValueType,
+}
+
+/// Information about an ONNX's initializer which can be overridden at inference time
+///
+/// Initializers are constant values stored in the model, like weights. Those also declared as
+/// graph inputs can be replaced by inference calls, see [`Session::run_with_initializers()`](struct.Session.html#method.run_with_initializers).
+#[derive(Debug)]
+pub struct OverridableInitializer {
+    /// Name of the initializer
+    pub name: String,
+    /// Type of the initializer's elements
+    pub initializer_type: TensorElementDataType,
+    /// Shape of the initializer
+    pub dimensions: Vec<Dimension>,
+    /// Type of the initializer, always a tensor
+    pub value_type: ValueType,
+}
+
+/// Dimension of an ONNX's input, output or initializer, as stored in loaded file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Dimension {
+    /// Dimension of a fixed size
+    ///
     /// C API uses a i64 for the dimensions. We use an unsigned of the same range of the positive values.
-    pub dimensions: Vec<Option<u32>>,
+    Fixed(u32),
+    /// Dynamic dimension named in the model, like `batch` or `seq_len`
+    ///
+    /// All the inputs sharing a symbolic dimension must have the same size for it.
+    Symbolic(String),
+    /// Dynamic dimension without name
+    Unknown,
+}
+
+/// Type of an ONNX's input, output or initializer, as stored in loaded file
+///
+/// Besides tensors, models can take and return sequences and maps, see the
+/// [`value`](../value/index.html) module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueType {
+    /// Tensor
+    Tensor {
+        /// Type of the tensor's elements
+        element_type: TensorElementDataType,
+        /// Shape of the tensor
+        dimensions: Vec<Dimension>,
+    },
+    /// Sequence of values of the same type
+    Sequence(Box<ValueType>),
+    /// Map from keys (integers or strings) to values
+    Map {
+        /// Type of the keys
+        key_type: TensorElementDataType,
+        /// Type of the values
+        value_type: Box<ValueType>,
+    },
+}
+
+impl ValueType {
+    /// Type of the elements of the innermost tensors of the value, and the shape of the value
+    /// if it is a tensor
+    fn element_type_and_dimensions(&self) -> (TensorElementDataType, Vec<Dimension>) {
+        match self {
+            ValueType::Tensor {
+                element_type,
+                dimensions,
+            } => (*element_type, dimensions.clone()),
+            ValueType::Sequence(value_type) | ValueType::Map { value_type, .. } => {
+                (value_type.element_type_and_dimensions().0, Vec::new())
+            }
+        }
+    }
+}
+
+impl Dimension {
+    /// Size of the dimension, if fixed
+    pub fn size(&self) -> Option<u32> {
+        match self {
+            Dimension::Fixed(size) => Some(*size),
+            Dimension::Symbolic(_) | Dimension::Unknown => None,
+        }
+    }
+
+    /// Name of the dimension, if symbolic
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Dimension::Symbolic(name) => Some(name),
+            Dimension::Fixed(_) | Dimension::Unknown => None,
+        }
+    }
+
+    /// Check if an input's dimension of size `size` matches this dimension
+    pub(crate) fn matches(&self, size: usize) -> bool {
+        match self {
+            Dimension::Fixed(fixed) => *fixed as usize == size,
+            // Dynamic size; in that case shape always match
+            Dimension::Symbolic(_) | Dimension::Unknown => true,
+        }
+    }
 }
 
 impl Input {
     /// Return an iterator over the shape elements of the input layer
     ///
+    /// Dynamic dimensions, symbolic or not, are `None`.
+    ///
     /// Note: The member [`Input::dimensions`](struct.Input.html#structfield.dimensions)
     /// stores `u32` (since ONNX uses `i64` but which cannot be negative) so the
     /// iterator converts to `usize`.
     pub fn dimensions(&self) -> impl Iterator<Item = Option<usize>> + '_ {
-        self.dimensions.iter().map(|d| d.map(|d2| d2 as usize))
+        self.dimensions
+            .iter()
+            .map(|d| d.size().map(|d2| d2 as usize))
     }
 }
 
 impl Output {
     /// Return an iterator over the shape elements of the output layer
     ///
+    /// Dynamic dimensions, symbolic or not, are `None`.
+    ///
     /// Note: The member [`Output::dimensions`](struct.Output.html#structfield.dimensions)
     /// stores `u32` (since ONNX uses `i64` but which cannot be negative) so the
     /// iterator converts to `usize`.
     pub fn dimensions(&self) -> impl Iterator<Item = Option<usize>> + '_ {
-        self.dimensions.iter().map(|d| d.map(|d2| d2 as usize))
+        self.dimensions
+            .iter()
+            .map(|d| d.size().map(|d2| d2 as usize))
+    }
+}
+
+impl OverridableInitializer {
+    /// Return an iterator over the shape elements of the initializer
+    pub fn dimensions(&self) -> impl Iterator<Item = Option<usize>> + '_ {
+        self.dimensions
+            .iter()
+            .map(|d| d.size().map(|d2| d2 as usize))
     }
 }
 
@@ -365,64 +969,406 @@ impl<'a> Session<'a> {
     ///
     /// Note that ONNX models can have multiple inputs; a `Vec<_>` is thus
     /// used for the input data here.
-    pub fn run<'s, 't, 'm, TIn, TOut, D>(
-        &'s mut self,
-        input_arrays: Vec<Array<TIn, D>>,
-    ) -> Result<Vec<OrtOwnedTensor<'t, 'm, TOut, ndarray::IxDyn>>>
+    ///
+    /// Inputs are anything that can be converted into an [`InputTensor`](../tensor/enum.InputTensor.html),
+    /// for example an `ndarray::Array`. To mix inputs of different element types (or dimensions),
+    /// convert each of them to an `InputTensor` first.
+    ///
+    /// The outputs are returned as [`DynOrtTensor`](../tensor/struct.DynOrtTensor.html)s, which
+    /// record the element type of each output; use
+    /// [`DynOrtTensor::try_extract()`](../tensor/struct.DynOrtTensor.html#method.try_extract)
+    /// to access their data. Outputs don't borrow the session: they can outlive it and be sent to
+    /// another thread.
+    pub fn run<'i, I>(
+        &self,
+        input_arrays: Vec<I>,
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>
+    where
+        I: Into<InputTensor<'i>>,
+    {
+        self.run_positional(None, input_arrays)
+    }
+
+    /// Run the input data through the ONNX graph, configuring the inference call with [`RunOptions`](../run_options/struct.RunOptions.html).
+    ///
+    /// Run options allow tagging the call (for example with a request identifier appearing in the
+    /// runtime's logs) or changing the runtime's log level for this call only. See
+    /// [`run()`](#method.run) for the inputs and outputs.
+    pub fn run_with_options<'i, I>(
+        &self,
+        run_options: &RunOptions,
+        input_arrays: Vec<I>,
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>
+    where
+        I: Into<InputTensor<'i>>,
+    {
+        self.run_positional(Some(run_options), input_arrays)
+    }
+
+    /// Run the input data through the ONNX graph, using the input and output names.
+    ///
+    /// Inputs are given as `(name, array)` pairs (for example a `HashMap<&str, InputTensor>`)
+    /// instead of relying on the position of the inputs in the model. Only the outputs listed
+    /// in `output_names` are fetched, in the requested order; the others are not computed
+    /// unless needed.
+    ///
+    /// All the model's inputs must be provided. Values for
+    /// [overridable initializers](#structfield.overridable_initializers) can be given the same
+    /// way. Names are validated against [`Session::inputs`](#structfield.inputs),
+    /// [`Session::overridable_initializers`](#structfield.overridable_initializers) and
+    /// [`Session::outputs`](#structfield.outputs).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::{collections::HashMap, error::Error};
+    /// # use onnxruntime::{environment::Environment, LoggingLevel};
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+    /// # let session = environment.new_session_builder()?.with_model_from_file("model.onnx")?;
+    /// let mut inputs = HashMap::new();
+    /// inputs.insert("input", ndarray::Array::linspace(0.0_f32, 1.0, 100));
+    /// let outputs = session.run_with(inputs, &["scores"])?;
+    /// let scores = outputs[0].try_extract::<f32>()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_with<'i, I, K, V, N>(
+        &self,
+        inputs: I,
+        output_names: &[N],
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<InputTensor<'i>>,
+        N: AsRef<str>,
+    {
+        let (input_names, input_arrays): (Vec<String>, Vec<InputTensor>) = inputs
+            .into_iter()
+            .map(|(name, array)| (name.as_ref().to_string(), array.into()))
+            .unzip();
+        let output_names: Vec<String> = output_names
+            .iter()
+            .map(|name| name.as_ref().to_string())
+            .collect();
+
+        self.validate_input_names(&input_names, &input_arrays, input_names.len())?;
+        self.validate_output_names(&output_names)?;
+
+        self.run_named(None, input_names, input_arrays, output_names)
+    }
+
+    /// Run the input data through the ONNX graph, overriding some of the model's initializers.
+    ///
+    /// Inputs and outputs are the same as for [`run()`](#method.run). Initializers are given as
+    /// `(name, array)` pairs and replace the values stored in the model for this call only,
+    /// for example to change a temperature constant or an embedding table per request. Names are
+    /// validated against [`Session::overridable_initializers`](#structfield.overridable_initializers);
+    /// initializers which are not given keep their stored value.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// # use onnxruntime::{environment::Environment, LoggingLevel};
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+    /// # let session = environment.new_session_builder()?.with_model_from_file("model.onnx")?;
+    /// let array = ndarray::Array::linspace(0.0_f32, 1.0, 100);
+    /// let temperature = ndarray::arr0(0.7_f32);
+    /// let outputs = session.run_with_initializers(vec![array], vec![("temperature", temperature)])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_with_initializers<'i, I, J, K, V>(
+        &self,
+        input_arrays: Vec<I>,
+        initializers: J,
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>
     where
-        TIn: TypeToTensorElementDataType + Debug + Clone,
-        TOut: TypeToTensorElementDataType + Debug + Clone,
-        D: ndarray::Dimension,
-        'm: 't, // 'm outlives 't (memory info outlives tensor)
-        's: 'm, // 's outlives 'm (session outlives memory info)
+        I: Into<InputTensor<'i>>,
+        J: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<InputTensor<'i>>,
     {
+        let mut input_arrays: Vec<InputTensor> = input_arrays.into_iter().map(Into::into).collect();
         self.validate_input_shapes(&input_arrays)?;
+        let mut input_names: Vec<String> =
+            self.inputs.iter().map(|input| input.name.clone()).collect();
+        let initializers_start = input_names.len();
+
+        for (name, array) in initializers {
+            let name = name.as_ref();
+            if !self
+                .overridable_initializers
+                .iter()
+                .any(|initializer| initializer.name == name)
+            {
+                error!("Unknown overridable initializer name {:?}", name);
+                return Err(OrtError::UnknownInitializerName {
+                    name: name.to_string(),
+                });
+            }
+            input_names.push(name.to_string());
+            input_arrays.push(array.into());
+        }
+        self.validate_input_names(&input_names, &input_arrays, initializers_start)?;
 
-        // Build arguments to Run()
+        let output_names: Vec<String> = self
+            .outputs
+            .iter()
+            .map(|output| output.name.clone())
+            .collect();
+        self.run_named(None, input_names, input_arrays, output_names)
+    }
+
+    /// Run the input data through the ONNX graph, writing the outputs into caller-owned buffers.
+    ///
+    /// One [`OutputBuffer`](../tensor/output_buffer/enum.OutputBuffer.html) must be given per model
+    /// output, in the order of [`Session::outputs`](#structfield.outputs), for example as
+    /// `ArrayViewMut`s. Outputs with a fixed shape are written by the runtime directly into the
+    /// buffers, without allocation. Outputs with dynamic dimensions are only known once computed:
+    /// they are copied into the buffers, after checking that their shape matches the buffer's.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// # use onnxruntime::{environment::Environment, LoggingLevel};
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+    /// # let session = environment.new_session_builder()?.with_model_from_file("model.onnx")?;
+    /// let mut ring_buffer = ndarray::Array::<f32, _>::zeros((16, 1, 1000));
+    /// for frame in 0..100 {
+    ///     let array = ndarray::Array::<f32, _>::from_elem((1, 3, 224, 224), frame as f32);
+    ///     let slot = ring_buffer.index_axis_mut(ndarray::Axis(0), frame % 16);
+    ///     session.run_into(vec![array], vec![slot])?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_into<'i, 'o, I, O>(&self, input_arrays: Vec<I>, output_buffers: Vec<O>) -> Result<()>
+    where
+        I: Into<InputTensor<'i>>,
+        O: Into<OutputBuffer<'o>>,
+    {
+        let input_arrays: Vec<InputTensor> = input_arrays.into_iter().map(Into::into).collect();
+        self.validate_input_shapes(&input_arrays)?;
+        let output_buffers: Vec<OutputBuffer> =
+            output_buffers.into_iter().map(Into::into).collect();
+        self.validate_output_buffers(&output_buffers)?;
 
         let input_names: Vec<String> = self.inputs.iter().map(|input| input.name.clone()).collect();
-        let input_names_cstring: Vec<CString> = input_names
+        let output_names: Vec<String> = self
+            .outputs
             .iter()
-            .cloned()
-            .map(|n| CString::new(n).unwrap())
+            .map(|output| output.name.clone())
             .collect();
-        let input_names_ptr: Vec<*const i8> = input_names_cstring
+
+        let mut output_ptrs: Vec<*mut sys::OrtValue> = Vec::with_capacity(output_buffers.len());
+        // Keep the values pointing to the buffers alive until the end of the run
+        let mut buffer_tensors = Vec::new();
+        let mut dynamic_output_buffers = Vec::new();
+        for (index, (output, output_buffer)) in self.outputs.iter().zip(output_buffers).enumerate()
+        {
+            if output.dimensions.iter().all(|d| d.size().is_some()) {
+                let buffer_tensor = output_buffer.into_ort_tensor(&self.memory_info)?;
+                output_ptrs.push(buffer_tensor.c_ptr);
+                buffer_tensors.push(buffer_tensor);
+            } else {
+                output_ptrs.push(std::ptr::null_mut());
+                dynamic_output_buffers.push((index, output_buffer));
+            }
+        }
+
+        let input_values = input_arrays.into_iter().map(InputValue::Tensor).collect();
+        let custom_op_result = self.run_ort(
+            None,
+            input_names,
+            input_values,
+            output_names,
+            &mut output_ptrs,
+        )?;
+
+        // Take ownership of all the values allocated by the runtime first, so they are all
+        // released on error
+        let dynamic_outputs: Vec<_> = dynamic_output_buffers
+            .into_iter()
+            .map(|(index, output_buffer)| {
+                (index, DynOrtValue::new(output_ptrs[index]), output_buffer)
+            })
+            .collect();
+        custom_op_result?;
+        let dynamic_outputs = dynamic_outputs
             .into_iter()
-            .map(|n| n.into_raw() as *const i8)
+            .map(|(index, output, output_buffer)| Ok((index, output.into_tensor()?, output_buffer)))
+            .collect::<Result<Vec<_>>>()?;
+        for (index, output, mut output_buffer) in dynamic_outputs {
+            if output.shape() != output_buffer.shape() {
+                error!(
+                    "Output {:?} has shape {:?} but its buffer has shape {:?}",
+                    self.outputs[index].name,
+                    output.shape(),
+                    output_buffer.shape()
+                );
+                return Err(OrtError::NonMatchingDimensions(
+                    NonMatchingDimensionsError::DynamicOutputShape {
+                        name: self.outputs[index].name.clone(),
+                        output_buffer: output_buffer.shape().to_vec(),
+                        inference_output: output.shape().to_vec(),
+                    },
+                ));
+            }
+            output_buffer.copy_from(&output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Run the input data through the ONNX graph, for models taking or returning sequences and maps
+    ///
+    /// Inputs are anything that can be converted into an [`InputValue`](../value/enum.InputValue.html),
+    /// like an `ndarray::Array` for a tensor, given in the order of [`Session::inputs`](#structfield.inputs).
+    /// Each input must match its [`ValueType`](enum.ValueType.html).
+    ///
+    /// The outputs are returned as [`DynOrtValue`](../value/struct.DynOrtValue.html)s, whose content
+    /// is extracted according to their kind, for example with
+    /// [`try_extract_sequence_of_maps()`](../value/struct.DynOrtValue.html#method.try_extract_sequence_of_maps)
+    /// for the probabilities of a scikit-learn classifier.
+    pub fn run_values<'i, I>(&self, inputs: Vec<I>) -> Result<Vec<DynOrtValue>>
+    where
+        I: Into<InputValue<'i>>,
+    {
+        let input_values: Vec<InputValue> = inputs.into_iter().map(Into::into).collect();
+        self.validate_input_values(&input_values)?;
+
+        let input_names: Vec<String> = self.inputs.iter().map(|input| input.name.clone()).collect();
+        let output_names: Vec<String> = self
+            .outputs
+            .iter()
+            .map(|output| output.name.clone())
             .collect();
 
+        let mut output_ptrs: Vec<*mut sys::OrtValue> =
+            vec![std::ptr::null_mut(); output_names.len()];
+        let custom_op_result = self.run_ort(
+            None,
+            input_names,
+            input_values,
+            output_names,
+            &mut output_ptrs,
+        )?;
+
+        // Take ownership of all the values first, so they are all released on error
+        let outputs: Vec<DynOrtValue> = output_ptrs.into_iter().map(DynOrtValue::new).collect();
+        custom_op_result?;
+        Ok(outputs)
+    }
+
+    pub(crate) fn run_positional<'i, I>(
+        &self,
+        run_options: Option<&RunOptions>,
+        input_arrays: Vec<I>,
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>
+    where
+        I: Into<InputTensor<'i>>,
+    {
+        let input_arrays: Vec<InputTensor> = input_arrays.into_iter().map(Into::into).collect();
+        self.validate_input_shapes(&input_arrays)?;
+
+        let input_names: Vec<String> = self.inputs.iter().map(|input| input.name.clone()).collect();
         let output_names: Vec<String> = self
             .outputs
             .iter()
             .map(|output| output.name.clone())
             .collect();
-        let output_names_cstring: Vec<CString> = output_names
+
+        self.run_named(run_options, input_names, input_arrays, output_names)
+    }
+
+    fn run_named(
+        &self,
+        run_options: Option<&RunOptions>,
+        input_names: Vec<String>,
+        input_arrays: Vec<InputTensor>,
+        output_names: Vec<String>,
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>> {
+        for name in &output_names {
+            if let Some(output) = self.outputs.iter().find(|output| &output.name == name) {
+                validate_tensor(&output.name, &output.value_type)?;
+            }
+        }
+
+        let mut output_tensor_extractors_ptrs: Vec<*mut sys::OrtValue> =
+            vec![std::ptr::null_mut(); output_names.len()];
+        let input_values = input_arrays.into_iter().map(InputValue::Tensor).collect();
+        let custom_op_result = self.run_ort(
+            run_options,
+            input_names,
+            input_values,
+            output_names,
+            &mut output_tensor_extractors_ptrs,
+        )?;
+
+        // Take ownership of all the values first, so they are all released on error
+        let outputs: Vec<DynOrtValue> = output_tensor_extractors_ptrs
             .into_iter()
-            .map(|n| CString::new(n).unwrap())
+            .map(DynOrtValue::new)
             .collect();
-        let output_names_ptr: Vec<*const i8> = output_names_cstring
+        custom_op_result?;
+        outputs.into_iter().map(DynOrtValue::into_tensor).collect()
+    }
+
+    /// Call the runtime's `Run()`
+    ///
+    /// Non-null `output_ptrs` are values the runtime writes the outputs into; null ones are set to
+    /// values allocated by the runtime, which the caller must release.
+    ///
+    /// A successful `Run()` returns the errors of the custom operators separately: the runtime
+    /// still allocates the outputs, so the caller must take ownership of them before reporting
+    /// those errors.
+    fn run_ort(
+        &self,
+        run_options: Option<&RunOptions>,
+        input_names: Vec<String>,
+        input_values: Vec<InputValue>,
+        output_names: Vec<String>,
+        output_ptrs: &mut [*mut sys::OrtValue],
+    ) -> Result<Result<()>> {
+        assert_eq!(output_names.len(), output_ptrs.len());
+
+        // Build arguments to Run()
+
+        let input_names_cstring: Vec<CString> = input_names
+            .into_iter()
+            .map(CString::new)
+            .collect::<std::result::Result<_, _>>()?;
+        let input_names_ptr: Vec<*const i8> = input_names_cstring
             .iter()
             .map(|n| n.as_ptr() as *const i8)
             .collect();
 
-        let mut output_tensor_extractors_ptrs: Vec<*mut sys::OrtValue> =
-            vec![std::ptr::null_mut(); self.outputs.len()];
+        let output_names_cstring: Vec<CString> = output_names
+            .into_iter()
+            .map(CString::new)
+            .collect::<std::result::Result<_, _>>()?;
+        let output_names_ptr: Vec<*const i8> = output_names_cstring
+            .iter()
+            .map(|n| n.as_ptr() as *const i8)
+            .collect();
 
         // The C API expects pointers for the arrays (pointers to C-arrays)
-        let input_ort_tensors: Vec<OrtTensor<TIn, D>> = input_arrays
+        let input_ort_tensors: Vec<Box<dyn OrtInput>> = input_values
             .into_iter()
-            .map(|input_array| {
-                OrtTensor::from_array(&self.memory_info, self.allocator_ptr, input_array)
-            })
-            .collect::<Result<Vec<OrtTensor<TIn, D>>>>()?;
+            .map(|input_value| input_value.into_ort_value(&self.memory_info, self.allocator_ptr))
+            .collect::<Result<Vec<Box<dyn OrtInput>>>>()?;
         let input_ort_values: Vec<*const sys::OrtValue> = input_ort_tensors
             .iter()
-            .map(|input_array_ort| input_array_ort.c_ptr as *const sys::OrtValue)
+            .map(|input_array_ort| input_array_ort.c_ptr())
             .collect();
 
-        let run_options_ptr: *const sys::OrtRunOptions = std::ptr::null();
-
-        let status = unsafe {
+        let mut run = |run_options_ptr: *const sys::OrtRunOptions| unsafe {
             g_ort().Run.unwrap()(
                 self.session_ptr,
                 run_options_ptr,
@@ -431,43 +1377,79 @@ impl<'a> Session<'a> {
                 input_ort_values.len() as u64, // C API expects a u64, not isize
                 output_names_ptr.as_ptr(),
                 output_names_ptr.len() as u64, // C API expects a u64, not isize
-                output_tensor_extractors_ptrs.as_mut_ptr(),
+                output_ptrs.as_mut_ptr(),
             )
         };
-        status_to_result(status).map_err(OrtError::Run)?;
+        let result = match run_options {
+            Some(run_options) => run_options.run(run),
+            None => status_to_result(run(std::ptr::null())).map_err(OrtError::Run),
+        };
+        // Always collect the errors of custom operators, so they are not reported by another call
+        let custom_op_result = custom_op::take_errors(&self.custom_op_errors);
+        result.map(|()| custom_op_result)
+    }
 
-        let memory_info_ref = &self.memory_info;
-        let outputs: Result<Vec<OrtOwnedTensor<TOut, ndarray::Dim<ndarray::IxDynImpl>>>> =
-            output_tensor_extractors_ptrs
-                .into_iter()
-                .map(|ptr| {
-                    let mut tensor_info_ptr: *mut sys::OrtTensorTypeAndShapeInfo =
-                        std::ptr::null_mut();
-                    let status = unsafe {
-                        g_ort().GetTensorTypeAndShape.unwrap()(ptr, &mut tensor_info_ptr as _)
-                    };
-                    status_to_result(status).map_err(OrtError::GetTensorTypeAndShape)?;
-                    let dims = unsafe { get_tensor_dimensions(tensor_info_ptr) };
-                    unsafe { g_ort().ReleaseTensorTypeAndShapeInfo.unwrap()(tensor_info_ptr) };
-                    let dims: Vec<_> = dims?.iter().map(|&n| n as usize).collect();
-
-                    let mut output_tensor_extractor =
-                        OrtOwnedTensorExtractor::new(memory_info_ref, ndarray::IxDyn(&dims));
-                    output_tensor_extractor.tensor_ptr = ptr;
-                    output_tensor_extractor.extract::<TOut>()
-                })
-                .collect();
+    /// Description of the memory of the inputs given to the runtime
+    ///
+    /// Configured with [`SessionBuilder::with_allocator()`](struct.SessionBuilder.html#method.with_allocator)
+    /// and [`SessionBuilder::with_memory_type()`](struct.SessionBuilder.html#method.with_memory_type).
+    pub fn memory_info(&self) -> &MemoryInfo {
+        &self.memory_info
+    }
 
-        // Reconvert to CString so drop impl is called and memory is freed
-        let _: Vec<CString> = input_names_ptr
-            .into_iter()
-            .map(|p| {
-                assert_ne!(p, std::ptr::null());
-                unsafe { CString::from_raw(p as *mut i8) }
-            })
-            .collect();
+    /// Read the model's metadata, like its producer, version and custom properties
+    pub fn metadata(&self) -> Result<ModelMetadata> {
+        ModelMetadata::new(self.session_ptr, self.allocator_ptr)
+    }
+
+    /// End profiling, returning the path of the trace written by the runtime
+    ///
+    /// Profiling must have been enabled with [`SessionBuilder::with_profiling()`](struct.SessionBuilder.html#method.with_profiling),
+    /// otherwise `OrtError::ProfilingNotEnabled` is returned. The trace can be parsed with
+    /// [`Profile::from_file()`](../profiling/struct.Profile.html#method.from_file) (requires the
+    /// `profiling` feature). Inference calls performed afterwards are not profiled.
+    pub fn end_profiling(&self) -> Result<PathBuf> {
+        let mut profile_file_ptr: *mut i8 = std::ptr::null_mut();
+        let status = unsafe {
+            g_ort().SessionEndProfiling.unwrap()(
+                self.session_ptr,
+                self.allocator_ptr,
+                &mut profile_file_ptr,
+            )
+        };
+        status_to_result(status).map_err(OrtError::Profiling)?;
+        assert_ne!(profile_file_ptr, std::ptr::null_mut());
+
+        let profile_file = char_p_to_string(profile_file_ptr);
+        let status = unsafe {
+            g_ort().AllocatorFree.unwrap()(self.allocator_ptr, profile_file_ptr as *mut c_void)
+        };
+        status_to_result(status).map_err(OrtError::Allocator)?;
 
-        outputs
+        // The runtime returns an empty path when profiling was not enabled
+        let profile_file = profile_file?;
+        if profile_file.is_empty() {
+            return Err(OrtError::ProfilingNotEnabled);
+        }
+        Ok(PathBuf::from(profile_file))
+    }
+
+    /// Start time of profiling, in nanoseconds
+    ///
+    /// The times of the trace's events are relative to it.
+    pub fn profiling_start_time_ns(&self) -> Result<u64> {
+        let mut start_time_ns = 0;
+        let status = unsafe {
+            g_ort().SessionGetProfilingStartTimeNs.unwrap()(self.session_ptr, &mut start_time_ns)
+        };
+        status_to_result(status).map_err(OrtError::Profiling)?;
+        Ok(start_time_ns)
+    }
+
+    /// Create an [`IoBinding`](../io_binding/struct.IoBinding.html) to bind the session's inputs
+    /// and outputs to values reused across inference calls
+    pub fn io_binding(&self) -> Result<IoBinding<'_>> {
+        IoBinding::new(self)
     }
 
     // pub fn tensor_from_array<'a, 'b, T, D>(&'a self, array: Array<T, D>) -> Tensor<'b, T, D>
@@ -477,13 +1459,180 @@ impl<'a> Session<'a> {
     //     Tensor::from_array(self, array)
     // }
 
-    fn validate_input_shapes<TIn, D>(&mut self, input_arrays: &[Array<TIn, D>]) -> Result<()>
-    where
-        TIn: TypeToTensorElementDataType + Debug + Clone,
-        D: ndarray::Dimension,
-    {
-        // ******************************************************************
-        // FIXME: Properly handle errors here
+    /// Validate the inputs given by name, the ones from `initializers_start` overriding
+    /// initializers
+    ///
+    /// Overridable initializers can also be given as inputs.
+    fn validate_input_names(
+        &self,
+        input_names: &[String],
+        input_arrays: &[InputTensor],
+        initializers_start: usize,
+    ) -> Result<()> {
+        for (index, name) in input_names.iter().enumerate() {
+            if input_names[..index].contains(name) {
+                error!("Input {:?} given more than once", name);
+                return Err(OrtError::DuplicateInputName { name: name.clone() });
+            }
+        }
+
+        let mut input_shapes = Vec::with_capacity(input_names.len());
+        for (index, (name, input_array)) in input_names.iter().zip(input_arrays).enumerate() {
+            let is_initializer = index >= initializers_start;
+            let input = self
+                .inputs
+                .iter()
+                .find(|input| !is_initializer && &input.name == name);
+            // Overridable initializers are given to the runtime as inputs
+            let (model_type, model_dimensions) = match input {
+                Some(input) => {
+                    validate_tensor(&input.name, &input.value_type)?;
+                    (input.input_type, &input.dimensions)
+                }
+                None => self
+                    .overridable_initializers
+                    .iter()
+                    .find(|initializer| &initializer.name == name)
+                    .map(|initializer| (initializer.initializer_type, &initializer.dimensions))
+                    .ok_or_else(|| {
+                        let name = name.clone();
+                        if is_initializer {
+                            error!("Unknown overridable initializer name {:?}", name);
+                            OrtError::UnknownInitializerName { name }
+                        } else {
+                            error!("Unknown input name {:?}", name);
+                            OrtError::UnknownInputName { name }
+                        }
+                    })?,
+            };
+            validate_data_type(name, input_array, model_type)?;
+
+            // Verify shape of the input (except dynamic dimensions)
+            let input_shape = input_array.shape();
+            let different_shape = input_shape.len() != model_dimensions.len()
+                || input_shape
+                    .iter()
+                    .zip(model_dimensions)
+                    .any(|(l, r)| !r.matches(*l));
+            if different_shape {
+                error!(
+                    "Different input shapes for {:?}: {:?} vs {:?}",
+                    name, input_shape, model_dimensions
+                );
+                return Err(OrtError::NonMatchingDimensions(
+                    NonMatchingDimensionsError::InputShape {
+                        name: name.clone(),
+                        inference_input: input_shape.to_vec(),
+                        model_input: model_dimensions.clone(),
+                    },
+                ));
+            }
+            input_shapes.push((name.as_str(), model_dimensions.as_slice(), input_shape));
+        }
+        validate_symbolic_dimensions(input_shapes)?;
+
+        if let Some(missing) = self
+            .inputs
+            .iter()
+            .find(|input| !input_names.contains(&input.name))
+        {
+            error!("Missing input {:?}", missing.name);
+            return Err(OrtError::MissingInput {
+                name: missing.name.clone(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_output_names(&self, output_names: &[String]) -> Result<()> {
+        match output_names
+            .iter()
+            .find(|name| !self.outputs.iter().any(|output| &output.name == *name))
+        {
+            Some(name) => {
+                error!("Unknown output name {:?}", name);
+                Err(OrtError::UnknownOutputName { name: name.clone() })
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn validate_output_buffers(&self, output_buffers: &[OutputBuffer]) -> Result<()> {
+        if output_buffers.len() != self.outputs.len() {
+            error!(
+                "Non-matching number of outputs: {} (buffers) vs {} (model)",
+                output_buffers.len(),
+                self.outputs.len()
+            );
+            return Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::OutputsCount {
+                    output_buffer_count: output_buffers.len(),
+                    model_output_count: self.outputs.len(),
+                },
+            ));
+        }
+
+        for (output_buffer, output) in output_buffers.iter().zip(self.outputs.iter()) {
+            validate_output_buffer(output, output_buffer)?;
+        }
+
+        Ok(())
+    }
+
+    fn validate_input_values(&self, input_values: &[InputValue]) -> Result<()> {
+        if input_values.len() != self.inputs.len() {
+            error!(
+                "Non-matching number of inputs: {} (inference) vs {} (model)",
+                input_values.len(),
+                self.inputs.len()
+            );
+            return Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::InputsCount {
+                    inference_input_count: input_values.len(),
+                    model_input_count: self.inputs.len(),
+                    inference_input: input_values
+                        .iter()
+                        .map(|input_value| match input_value {
+                            InputValue::Tensor(tensor) => tensor.shape().to_vec(),
+                            _ => Vec::new(),
+                        })
+                        .collect(),
+                    model_input: self
+                        .inputs
+                        .iter()
+                        .map(|input| input.dimensions.clone())
+                        .collect(),
+                },
+            ));
+        }
+
+        for (input_value, input) in input_values.iter().zip(&self.inputs) {
+            if !input_value.matches(&input.value_type) {
+                error!(
+                    "Non-matching value for input {:?}: {:?} vs {:?}",
+                    input.name, input_value, input.value_type
+                );
+                return Err(OrtError::NonMatchingValueType {
+                    name: input.name.clone(),
+                    model_type: input.value_type.clone(),
+                });
+            }
+        }
+
+        validate_symbolic_dimensions(self.inputs.iter().zip(input_values).filter_map(
+            |(input, input_value)| match input_value {
+                InputValue::Tensor(tensor) => Some((
+                    input.name.as_str(),
+                    input.dimensions.as_slice(),
+                    tensor.shape(),
+                )),
+                _ => None,
+            },
+        ))
+    }
+
+    pub(crate) fn validate_input_shapes(&self, input_arrays: &[InputTensor]) -> Result<()> {
         // Make sure all dimensions match (except dynamic ones)
 
         // Verify length of inputs
@@ -495,8 +1644,8 @@ impl<'a> Session<'a> {
             );
             return Err(OrtError::NonMatchingDimensions(
                 NonMatchingDimensionsError::InputsCount {
-                    inference_input_count: 0,
-                    model_input_count: 0,
+                    inference_input_count: input_arrays.len(),
+                    model_input_count: self.inputs.len(),
                     inference_input: input_arrays
                         .iter()
                         .map(|input_array| input_array.shape().to_vec())
@@ -510,54 +1659,200 @@ impl<'a> Session<'a> {
             ));
         }
 
-        // Verify length of each individual inputs
-        let inputs_different_length = input_arrays
-            .iter()
-            .zip(self.inputs.iter())
-            .any(|(l, r)| l.shape().len() != r.dimensions.len());
-        if inputs_different_length {
-            error!(
-                "Different input lengths: {:?} vs {:?}",
-                self.inputs, input_arrays
-            );
-            panic!(
-                "Different input lengths: {:?} vs {:?}",
-                self.inputs, input_arrays
-            );
+        for input in &self.inputs {
+            validate_tensor(&input.name, &input.value_type)?;
         }
 
-        // Verify shape of each individual inputs
-        let inputs_different_shape = input_arrays.iter().zip(self.inputs.iter()).any(|(l, r)| {
+        // Verify the rank and shape of each individual inputs
+        let different_shape = input_arrays.iter().zip(self.inputs.iter()).find(|(l, r)| {
             let l_shape = l.shape();
             let r_shape = r.dimensions.as_slice();
-            l_shape.iter().zip(r_shape.iter()).any(|(l2, r2)| match r2 {
-                Some(r3) => *r3 as usize != *l2,
-                None => false, // None means dynamic size; in that case shape always match
-            })
+            l_shape.len() != r_shape.len()
+                || l_shape
+                    .iter()
+                    .zip(r_shape.iter())
+                    .any(|(l2, r2)| !r2.matches(*l2))
         });
-        if inputs_different_shape {
+        if let Some((input_array, input)) = different_shape {
             error!(
-                "Different input lengths: {:?} vs {:?}",
-                self.inputs, input_arrays
-            );
-            panic!(
-                "Different input lengths: {:?} vs {:?}",
-                self.inputs, input_arrays
+                "Different input shapes for {:?}: {:?} vs {:?}",
+                input.name,
+                input_array.shape(),
+                input.dimensions
             );
+            return Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::InputShape {
+                    name: input.name.clone(),
+                    inference_input: input_array.shape().to_vec(),
+                    model_input: input.dimensions.clone(),
+                },
+            ));
+        }
+
+        validate_symbolic_dimensions(self.inputs.iter().zip(input_arrays).map(
+            |(input, input_array)| {
+                (
+                    input.name.as_str(),
+                    input.dimensions.as_slice(),
+                    input_array.shape(),
+                )
+            },
+        ))?;
+
+        for (input_array, input) in input_arrays.iter().zip(self.inputs.iter()) {
+            validate_input_data_type(input, input_array)?;
         }
 
         Ok(())
     }
 }
 
-unsafe fn get_tensor_dimensions(
+/// Check that an input or output is a tensor, for the operations which don't support sequences and maps
+pub(crate) fn validate_tensor(name: &str, value_type: &ValueType) -> Result<()> {
+    match value_type {
+        ValueType::Tensor { .. } => Ok(()),
+        _ => {
+            error!(
+                "Input or output {:?} is not a tensor but a {:?}",
+                name, value_type
+            );
+            Err(OrtError::NonTensorValue {
+                name: name.to_string(),
+                value_type: value_type.clone(),
+            })
+        }
+    }
+}
+
+/// Check that the inputs sharing a symbolic dimension have the same size for it
+///
+/// Each input is given as its name, its dimensions in the model and its shape in the inference call.
+fn validate_symbolic_dimensions<'i, I>(inputs: I) -> Result<()>
+where
+    I: IntoIterator<Item = (&'i str, &'i [Dimension], &'i [usize])>,
+{
+    let mut sizes: HashMap<&str, (&str, usize)> = HashMap::new();
+    for (name, dimensions, shape) in inputs {
+        for (dimension, &size) in dimensions.iter().zip(shape) {
+            let dimension = match dimension.name() {
+                Some(dimension) => dimension,
+                None => continue,
+            };
+            let &mut (previous_name, previous_size) =
+                sizes.entry(dimension).or_insert((name, size));
+            if previous_size != size {
+                error!(
+                    "Non-matching sizes for symbolic dimension {:?}: {} for input {:?} vs {} for input {:?}",
+                    dimension, size, name, previous_size, previous_name
+                );
+                return Err(OrtError::NonMatchingDimensions(
+                    NonMatchingDimensionsError::SymbolicDimension {
+                        dimension: dimension.to_string(),
+                        name: name.to_string(),
+                        size,
+                        previous_name: previous_name.to_string(),
+                        previous_size,
+                    },
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_input_data_type(input: &Input, input_array: &InputTensor) -> Result<()> {
+    validate_data_type(&input.name, input_array, input.input_type)
+}
+
+/// Check that an output buffer can receive a tensor output: its element type must match and its
+/// shape must match the output's fixed dimensions
+pub(crate) fn validate_output_buffer(output: &Output, output_buffer: &OutputBuffer) -> Result<()> {
+    validate_tensor(&output.name, &output.value_type)?;
+    if output_buffer.data_type() != output.output_type {
+        error!(
+            "Output buffer {:?} has type {:?} but model output has type {:?}",
+            output.name,
+            output_buffer.data_type(),
+            output.output_type
+        );
+        return Err(OrtError::NonMatchingDataType {
+            name: output.name.clone(),
+            inference_type: output_buffer.data_type(),
+            model_type: output.output_type,
+        });
+    }
+
+    let buffer_shape = output_buffer.shape();
+    let shape_matches = buffer_shape.len() == output.dimensions.len()
+        && buffer_shape.iter().zip(output.dimensions.iter()).all(
+            // Dynamic sizes are checked once the output is computed
+            |(buffer_dim, output_dim)| output_dim.matches(*buffer_dim),
+        );
+    if !shape_matches {
+        error!(
+            "Output buffer {:?} has shape {:?} but model output has dimensions {:?}",
+            output.name, buffer_shape, output.dimensions
+        );
+        return Err(OrtError::NonMatchingDimensions(
+            NonMatchingDimensionsError::OutputShape {
+                name: output.name.clone(),
+                output_buffer: buffer_shape.to_vec(),
+                model_output: output.dimensions.clone(),
+            },
+        ));
+    }
+    Ok(())
+}
+
+fn validate_data_type(
+    name: &str,
+    input_array: &InputTensor,
+    model_type: TensorElementDataType,
+) -> Result<()> {
+    if input_array.data_type() != model_type {
+        error!(
+            "Different input types for {:?}: {:?} vs {:?}",
+            name,
+            input_array.data_type(),
+            model_type
+        );
+        return Err(OrtError::NonMatchingDataType {
+            name: name.to_string(),
+            inference_type: input_array.data_type(),
+            model_type,
+        });
+    }
+    Ok(())
+}
+
+/// Convert a path to a null terminated string of the characters used by the runtime for paths
+#[cfg(target_family = "windows")]
+fn path_to_ort_string(path: &Path) -> Vec<u16> {
+    std::ffi::OsStr::new(path)
+        .encode_wide()
+        .chain(std::iter::once(0)) // Make sure we have a null terminated string
+        .collect()
+}
+
+/// Convert a path to a null terminated string of the characters used by the runtime for paths
+#[cfg(not(target_family = "windows"))]
+fn path_to_ort_string(path: &Path) -> Vec<std::os::raw::c_char> {
+    std::ffi::OsStr::new(path)
+        .as_bytes()
+        .iter()
+        .chain(std::iter::once(&b'\0')) // Make sure we have a null terminated string
+        .map(|b| *b as std::os::raw::c_char)
+        .collect()
+}
+
+pub(crate) unsafe fn get_tensor_dimensions(
     tensor_info_ptr: *const sys::OrtTensorTypeAndShapeInfo,
 ) -> Result<Vec<i64>> {
     let mut num_dims = 0;
     let status = g_ort().GetDimensionsCount.unwrap()(tensor_info_ptr, &mut num_dims);
     status_to_result(status).map_err(OrtError::GetDimensionsCount)?;
-    assert_ne!(num_dims, 0);
 
+    // Scalars have no dimensions
     let mut node_dims: Vec<i64> = vec![0; num_dims as usize];
     let status = g_ort().GetDimensions.unwrap()(
         tensor_info_ptr,
@@ -568,6 +1863,54 @@ unsafe fn get_tensor_dimensions(
     Ok(node_dims)
 }
 
+/// Names of the symbolic dimensions of a tensor, empty for the other dimensions
+pub(crate) unsafe fn get_symbolic_dimensions(
+    tensor_info_ptr: *const sys::OrtTensorTypeAndShapeInfo,
+    num_dims: usize,
+) -> Result<Vec<String>> {
+    // The names are owned by the tensor information
+    let mut dim_params: Vec<*const i8> = vec![std::ptr::null(); num_dims];
+    let status = g_ort().GetSymbolicDimensions.unwrap()(
+        tensor_info_ptr,
+        dim_params.as_mut_ptr(),
+        num_dims as u64,
+    );
+    status_to_result(status).map_err(OrtError::GetSymbolicDimensions)?;
+    dim_params
+        .into_iter()
+        .map(|dim_param| {
+            if dim_param.is_null() {
+                Ok(String::new())
+            } else {
+                char_p_to_string(dim_param)
+            }
+        })
+        .collect()
+}
+
+pub(crate) unsafe fn get_tensor_element_type(
+    tensor_info_ptr: *const sys::OrtTensorTypeAndShapeInfo,
+) -> Result<TensorElementDataType> {
+    let mut type_sys = sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED;
+    let status = g_ort().GetTensorElementType.unwrap()(tensor_info_ptr, &mut type_sys);
+    status_to_result(status).map_err(OrtError::TensorElementType)?;
+    TensorElementDataType::try_from(type_sys)
+}
+
+/// Element type and shape of a tensor value
+pub(crate) unsafe fn get_tensor_type_and_shape(
+    value_ptr: *const sys::OrtValue,
+) -> Result<(TensorElementDataType, Vec<usize>)> {
+    let mut tensor_info_ptr: *mut sys::OrtTensorTypeAndShapeInfo = std::ptr::null_mut();
+    let status = g_ort().GetTensorTypeAndShape.unwrap()(value_ptr, &mut tensor_info_ptr);
+    status_to_result(status).map_err(OrtError::GetTensorTypeAndShape)?;
+    let dims = get_tensor_dimensions(tensor_info_ptr);
+    let data_type = get_tensor_element_type(tensor_info_ptr);
+    g_ort().ReleaseTensorTypeAndShapeInfo.unwrap()(tensor_info_ptr);
+    let dims = dims?.iter().map(|&n| n as usize).collect();
+    Ok((data_type?, dims))
+}
+
 /// This module contains dangerous functions working on raw pointers.
 /// Those functions are only to be used from inside the
 /// `SessionBuilder::with_model_from_file()` method.
@@ -584,6 +1927,21 @@ mod dangerous {
         extract_io_count(f, session_ptr)
     }
 
+    pub(super) fn extract_overridable_initializers_count(
+        session_ptr: *mut sys::OrtSession,
+    ) -> Result<u64> {
+        // Unlike inputs and outputs, models usually have no overridable initializers
+        let mut num_initializers: u64 = 0;
+        let status = unsafe {
+            g_ort().SessionGetOverridableInitializerCount.unwrap()(
+                session_ptr,
+                &mut num_initializers,
+            )
+        };
+        status_to_result(status).map_err(OrtError::InOutCount)?;
+        Ok(num_initializers)
+    }
+
     fn extract_io_count(
         f: unsafe extern "C" fn(*const sys::OrtSession, *mut u64) -> *mut sys::OrtStatus,
         session_ptr: *mut sys::OrtSession,
@@ -614,6 +1972,15 @@ mod dangerous {
         extract_io_name(f, session_ptr, allocator_ptr, i)
     }
 
+    fn extract_overridable_initializer_name(
+        session_ptr: *mut sys::OrtSession,
+        allocator_ptr: *mut sys::OrtAllocator,
+        i: u64,
+    ) -> Result<String> {
+        let f = g_ort().SessionGetOverridableInitializerName.unwrap();
+        extract_io_name(f, session_ptr, allocator_ptr, i)
+    }
+
     fn extract_io_name(
         f: unsafe extern "C" fn(
             *const sys::OrtSession,
@@ -644,11 +2011,13 @@ mod dangerous {
     ) -> Result<Input> {
         let input_name = extract_input_name(session_ptr, allocator_ptr, i)?;
         let f = g_ort().SessionGetInputTypeInfo.unwrap();
-        let (input_type, dimensions) = extract_io(f, session_ptr, i)?;
+        let value_type = extract_io(f, session_ptr, i)?;
+        let (input_type, dimensions) = value_type.element_type_and_dimensions();
         Ok(Input {
             name: input_name,
             input_type,
             dimensions,
+            value_type,
         })
     }
 
@@ -659,11 +2028,30 @@ mod dangerous {
     ) -> Result<Output> {
         let output_name = extract_output_name(session_ptr, allocator_ptr, i)?;
         let f = g_ort().SessionGetOutputTypeInfo.unwrap();
-        let (output_type, dimensions) = extract_io(f, session_ptr, i)?;
+        let value_type = extract_io(f, session_ptr, i)?;
+        let (output_type, dimensions) = value_type.element_type_and_dimensions();
         Ok(Output {
             name: output_name,
             output_type,
             dimensions,
+            value_type,
+        })
+    }
+
+    pub(super) fn extract_overridable_initializer(
+        session_ptr: *mut sys::OrtSession,
+        allocator_ptr: *mut sys::OrtAllocator,
+        i: u64,
+    ) -> Result<OverridableInitializer> {
+        let initializer_name = extract_overridable_initializer_name(session_ptr, allocator_ptr, i)?;
+        let f = g_ort().SessionGetOverridableInitializerTypeInfo.unwrap();
+        let value_type = extract_io(f, session_ptr, i)?;
+        let (initializer_type, dimensions) = value_type.element_type_and_dimensions();
+        Ok(OverridableInitializer {
+            name: initializer_name,
+            initializer_type,
+            dimensions,
+            value_type,
         })
     }
 
@@ -675,47 +2063,102 @@ mod dangerous {
         ) -> *mut sys::OrtStatus,
         session_ptr: *mut sys::OrtSession,
         i: u64,
-    ) -> Result<(TensorElementDataType, Vec<Option<u32>>)> {
+    ) -> Result<ValueType> {
         let mut typeinfo_ptr: *mut sys::OrtTypeInfo = std::ptr::null_mut();
 
         let status = unsafe { f(session_ptr, i as u64, &mut typeinfo_ptr) };
         status_to_result(status).map_err(OrtError::GetTypeInfo)?;
         assert_ne!(typeinfo_ptr, std::ptr::null_mut());
 
-        let mut tensor_info_ptr: *const sys::OrtTensorTypeAndShapeInfo = std::ptr::null_mut();
-        let status = unsafe {
-            g_ort().CastTypeInfoToTensorInfo.unwrap()(typeinfo_ptr, &mut tensor_info_ptr)
-        };
-        status_to_result(status).map_err(OrtError::CastTypeInfoToTensorInfo)?;
-        assert_ne!(tensor_info_ptr, std::ptr::null_mut());
+        let value_type = unsafe { extract_value_type(typeinfo_ptr) };
+        unsafe { g_ort().ReleaseTypeInfo.unwrap()(typeinfo_ptr) };
+        value_type
+    }
 
-        let mut type_sys = sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED;
-        let status =
-            unsafe { g_ort().GetTensorElementType.unwrap()(tensor_info_ptr, &mut type_sys) };
-        status_to_result(status).map_err(OrtError::TensorElementType)?;
-        assert_ne!(
-            type_sys,
-            sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED
-        );
-        // This transmute should be safe since its value is read from GetTensorElementType which we must trust.
-        let io_type: TensorElementDataType = unsafe { std::mem::transmute(type_sys) };
+    /// Read the type information of a tensor, or of a sequence or map and their elements
+    unsafe fn extract_value_type(typeinfo_ptr: *const sys::OrtTypeInfo) -> Result<ValueType> {
+        let mut onnx_type = sys::ONNXType::ONNX_TYPE_UNKNOWN;
+        let status = g_ort().GetOnnxTypeFromTypeInfo.unwrap()(typeinfo_ptr, &mut onnx_type);
+        status_to_result(status).map_err(OrtError::GetTypeInfo)?;
 
-        // info!("{} : type={}", i, type_);
+        match onnx_type {
+            sys::ONNXType::ONNX_TYPE_TENSOR => extract_tensor_type(typeinfo_ptr),
+            sys::ONNXType::ONNX_TYPE_SEQUENCE => {
+                // Owned by the type information
+                let mut sequence_info_ptr: *const sys::OrtSequenceTypeInfo = std::ptr::null();
+                let status = g_ort().CastTypeInfoToSequenceTypeInfo.unwrap()(
+                    typeinfo_ptr,
+                    &mut sequence_info_ptr,
+                );
+                status_to_result(status).map_err(OrtError::CastTypeInfoToSequenceTypeInfo)?;
+                assert_ne!(sequence_info_ptr, std::ptr::null());
+
+                let mut element_typeinfo_ptr: *mut sys::OrtTypeInfo = std::ptr::null_mut();
+                let status = g_ort().GetSequenceElementType.unwrap()(
+                    sequence_info_ptr,
+                    &mut element_typeinfo_ptr,
+                );
+                status_to_result(status).map_err(OrtError::GetTypeInfo)?;
+                assert_ne!(element_typeinfo_ptr, std::ptr::null_mut());
+                let element_type = extract_value_type(element_typeinfo_ptr);
+                g_ort().ReleaseTypeInfo.unwrap()(element_typeinfo_ptr);
+
+                Ok(ValueType::Sequence(Box::new(element_type?)))
+            }
+            sys::ONNXType::ONNX_TYPE_MAP => {
+                // Owned by the type information
+                let mut map_info_ptr: *const sys::OrtMapTypeInfo = std::ptr::null();
+                let status =
+                    g_ort().CastTypeInfoToMapTypeInfo.unwrap()(typeinfo_ptr, &mut map_info_ptr);
+                status_to_result(status).map_err(OrtError::CastTypeInfoToMapTypeInfo)?;
+                assert_ne!(map_info_ptr, std::ptr::null());
+
+                let mut key_type_sys =
+                    sys::ONNXTensorElementDataType::ONNX_TENSOR_ELEMENT_DATA_TYPE_UNDEFINED;
+                let status = g_ort().GetMapKeyType.unwrap()(map_info_ptr, &mut key_type_sys);
+                status_to_result(status).map_err(OrtError::GetTypeInfo)?;
+                let key_type = TensorElementDataType::try_from(key_type_sys)?;
+
+                let mut value_typeinfo_ptr: *mut sys::OrtTypeInfo = std::ptr::null_mut();
+                let status =
+                    g_ort().GetMapValueType.unwrap()(map_info_ptr, &mut value_typeinfo_ptr);
+                status_to_result(status).map_err(OrtError::GetTypeInfo)?;
+                assert_ne!(value_typeinfo_ptr, std::ptr::null_mut());
+                let value_type = extract_value_type(value_typeinfo_ptr);
+                g_ort().ReleaseTypeInfo.unwrap()(value_typeinfo_ptr);
+
+                Ok(ValueType::Map {
+                    key_type,
+                    value_type: Box::new(value_type?),
+                })
+            }
+            onnx_type => Err(OrtError::UnsupportedValueType(onnx_type.into())),
+        }
+    }
 
-        let node_dims = unsafe { get_tensor_dimensions(tensor_info_ptr)? };
+    unsafe fn extract_tensor_type(typeinfo_ptr: *const sys::OrtTypeInfo) -> Result<ValueType> {
+        // Owned by the type information
+        let mut tensor_info_ptr: *const sys::OrtTensorTypeAndShapeInfo = std::ptr::null_mut();
+        let status = g_ort().CastTypeInfoToTensorInfo.unwrap()(typeinfo_ptr, &mut tensor_info_ptr);
+        status_to_result(status).map_err(OrtError::CastTypeInfoToTensorInfo)?;
+        assert_ne!(tensor_info_ptr, std::ptr::null_mut());
 
-        // for j in 0..num_dims {
-        //     info!("{} : dim {}={}", i, j, node_dims[j as usize]);
-        // }
+        let element_type = get_tensor_element_type(tensor_info_ptr)?;
 
-        unsafe { g_ort().ReleaseTypeInfo.unwrap()(typeinfo_ptr) };
+        let node_dims = get_tensor_dimensions(tensor_info_ptr)?;
+        let symbolic_dims = get_symbolic_dimensions(tensor_info_ptr, node_dims.len())?;
 
-        Ok((
-            io_type,
-            node_dims
+        Ok(ValueType::Tensor {
+            element_type,
+            dimensions: node_dims
                 .into_iter()
-                .map(|d| if d == -1 { None } else { Some(d as u32) })
+                .zip(symbolic_dims)
+                .map(|(d, name)| match d {
+                    -1 if name.is_empty() => Dimension::Unknown,
+                    -1 => Dimension::Symbolic(name),
+                    d => Dimension::Fixed(d as u32),
+                })
                 .collect(),
-        ))
+        })
     }
 }
@@ -0,0 +1,281 @@
+//! Module containing a pool of sessions over the same model
+
+use std::{
+    ops::Deref,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Condvar, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tracing::debug;
+
+use crate::{
+    environment::Environment,
+    error::Result,
+    session::{Session, SessionBuilder},
+    tensor::{DynOrtTensor, InputTensor},
+};
+
+/// Configuration applied to the builder of each session of a pool
+type Configure = Box<dyn Fn(SessionBuilder<'static>) -> Result<SessionBuilder<'static>>>;
+
+/// Pool of sessions over the same model
+///
+/// On CPU, many sessions using a single intra-op thread each often give a better tail latency
+/// than a single session using as many threads. A `SessionPool` holds a fixed number of sessions,
+/// all created from the same [`Environment`](../environment/struct.Environment.html) with the same
+/// configuration, and hands them out either:
+///
+/// * with checkout/return semantics: [`checkout()`](#method.checkout) waits for an idle session and
+///   returns a [`PooledSession`](struct.PooledSession.html) guard, which gives the session back to
+///   the pool when dropped;
+/// * in round-robin with [`round_robin()`](#method.round_robin), without waiting nor exclusivity.
+///
+/// Pool-level statistics are available through [`stats()`](#method.stats).
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::error::Error;
+/// # use onnxruntime::{environment::Environment, session_pool::SessionPool, LoggingLevel};
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+/// let pool = SessionPool::builder(&environment)
+///     .with_size(4)
+///     .with_session_configuration(|builder| builder.with_number_threads(1))
+///     .with_model_from_file("squeezenet.onnx")?;
+///
+/// let array = ndarray::Array::<f32, _>::zeros((1, 3, 224, 224));
+/// let session = pool.checkout();
+/// let outputs = session.run(vec![array])?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SessionPool {
+    sessions: Vec<Session<'static>>,
+    /// Indices of the sessions not checked out
+    available: Mutex<Vec<usize>>,
+    returned: Condvar,
+    /// Index of the next session returned by `round_robin()`
+    next: AtomicUsize,
+    waits: Mutex<WaitStats>,
+}
+
+#[derive(Debug, Default)]
+struct WaitStats {
+    checkouts: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+impl SessionPool {
+    /// Create a new [`SessionPoolBuilder`](struct.SessionPoolBuilder.html) creating its sessions
+    /// from `env`
+    ///
+    /// The sessions hold a clone of the environment, so the pool does not borrow it.
+    pub fn builder(env: &Environment) -> SessionPoolBuilder {
+        SessionPoolBuilder {
+            env: env.clone(),
+            size: 1,
+            configure: Box::new(|builder| builder.with_number_threads(1)),
+        }
+    }
+
+    /// Number of sessions in the pool
+    pub fn size(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Check out an idle session, waiting for one to be returned if they are all checked out
+    pub fn checkout(&self) -> PooledSession<'_> {
+        let start = Instant::now();
+        let mut available = self.available.lock().unwrap();
+        while available.is_empty() {
+            available = self.returned.wait(available).unwrap();
+        }
+        let index = available.pop().unwrap();
+        std::mem::drop(available);
+
+        self.record_wait(start.elapsed());
+        PooledSession { pool: self, index }
+    }
+
+    /// Check out an idle session, or return `None` if they are all checked out
+    pub fn try_checkout(&self) -> Option<PooledSession<'_>> {
+        let index = self.available.lock().unwrap().pop()?;
+        self.record_wait(Duration::from_secs(0));
+        Some(PooledSession { pool: self, index })
+    }
+
+    /// Return the next session of the pool, in round-robin order
+    ///
+    /// Sessions support concurrent inference calls, so the returned session may be in use by
+    /// other callers at the same time. It is not checked out and not accounted in
+    /// [`SessionPoolStats::busy`](struct.SessionPoolStats.html#structfield.busy).
+    pub fn round_robin(&self) -> &Session<'static> {
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.sessions.len();
+        &self.sessions[index]
+    }
+
+    /// Run the inference on a checked out session, returning it to the pool afterwards
+    ///
+    /// See [`Session::run()`](../session/struct.Session.html#method.run) for the inputs and outputs.
+    pub fn run<'i, I>(
+        &self,
+        input_arrays: Vec<I>,
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>>
+    where
+        I: Into<InputTensor<'i>>,
+    {
+        self.checkout().run(input_arrays)
+    }
+
+    /// Return the statistics of the pool
+    pub fn stats(&self) -> SessionPoolStats {
+        let busy = self.sessions.len() - self.available.lock().unwrap().len();
+        let waits = self.waits.lock().unwrap();
+        SessionPoolStats {
+            size: self.sessions.len(),
+            busy,
+            checkouts: waits.checkouts,
+            total_wait: waits.total_wait,
+            max_wait: waits.max_wait,
+        }
+    }
+
+    fn record_wait(&self, wait: Duration) {
+        let mut waits = self.waits.lock().unwrap();
+        waits.checkouts += 1;
+        waits.total_wait += wait;
+        waits.max_wait = waits.max_wait.max(wait);
+    }
+}
+
+/// Statistics of a [`SessionPool`](struct.SessionPool.html)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionPoolStats {
+    /// Number of sessions in the pool
+    pub size: usize,
+    /// Number of sessions currently checked out
+    pub busy: usize,
+    /// Number of checkouts since the pool was created
+    pub checkouts: u64,
+    /// Total time spent by checkouts waiting for an idle session
+    pub total_wait: Duration,
+    /// Longest time a checkout waited for an idle session
+    pub max_wait: Duration,
+}
+
+impl SessionPoolStats {
+    /// Average time a checkout waited for an idle session
+    pub fn mean_wait(&self) -> Duration {
+        if self.checkouts == 0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(self.total_wait.as_secs_f64() / self.checkouts as f64)
+        }
+    }
+}
+
+/// Session checked out of a [`SessionPool`](struct.SessionPool.html)
+///
+/// Derefs to the [`Session`](../session/struct.Session.html); the session is returned to the pool
+/// when the guard is dropped.
+#[derive(Debug)]
+pub struct PooledSession<'p> {
+    pool: &'p SessionPool,
+    index: usize,
+}
+
+impl<'p> Deref for PooledSession<'p> {
+    type Target = Session<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pool.sessions[self.index]
+    }
+}
+
+impl<'p> Drop for PooledSession<'p> {
+    fn drop(&mut self) {
+        self.pool.available.lock().unwrap().push(self.index);
+        self.pool.returned.notify_one();
+    }
+}
+
+/// Struct used to build a [`SessionPool`](struct.SessionPool.html)
+///
+/// Created with [`SessionPool::builder()`](struct.SessionPool.html#method.builder).
+///
+/// **NOTE**: If the same configuration method (for example [`with_size()`](#method.with_size))
+/// is called multiple times, the last value will have precedence.
+pub struct SessionPoolBuilder {
+    env: Environment,
+    size: usize,
+    configure: Configure,
+}
+
+impl SessionPoolBuilder {
+    /// Configure the number of sessions in the pool
+    ///
+    /// Defaults to 1.
+    pub fn with_size(mut self, size: usize) -> SessionPoolBuilder {
+        self.size = size.max(1);
+        self
+    }
+
+    /// Configure each session of the pool, using the [`SessionBuilder`](../session/struct.SessionBuilder.html) methods
+    ///
+    /// `configure` is called once per session. Defaults to a single intra-op thread per session
+    /// ([`with_number_threads(1)`](../session/struct.SessionBuilder.html#method.with_number_threads)).
+    pub fn with_session_configuration<F>(mut self, configure: F) -> SessionPoolBuilder
+    where
+        F: Fn(SessionBuilder<'static>) -> Result<SessionBuilder<'static>> + 'static,
+    {
+        self.configure = Box::new(configure);
+        self
+    }
+
+    /// Load an ONNX graph from a file in each session and commit the pool
+    pub fn with_model_from_file<P>(self, model_filepath: P) -> Result<SessionPool>
+    where
+        P: AsRef<Path>,
+    {
+        // Sessions built from an owned environment only accept a `'static` path
+        let model_filepath = model_filepath.as_ref().to_path_buf();
+        self.build(|builder| builder.with_model_from_file(model_filepath.clone()))
+    }
+
+    /// Load an ONNX graph from memory in each session and commit the pool
+    pub fn with_model_from_memory<B>(self, model_bytes: B) -> Result<SessionPool>
+    where
+        B: AsRef<[u8]>,
+    {
+        let model_bytes = model_bytes.as_ref();
+        self.build(|builder| builder.with_model_from_memory(model_bytes))
+    }
+
+    fn build<F>(self, load_model: F) -> Result<SessionPool>
+    where
+        F: Fn(SessionBuilder<'static>) -> Result<Session<'static>>,
+    {
+        debug!(size = self.size, "Creating a session pool.");
+        let sessions = (0..self.size)
+            .map(|_| {
+                let builder = (self.configure)(self.env.new_owned_session_builder()?)?;
+                load_model(builder)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(SessionPool {
+            available: Mutex::new((0..sessions.len()).rev().collect()),
+            sessions,
+            returned: Condvar::new(),
+            next: AtomicUsize::new(0),
+            waits: Mutex::new(WaitStats::default()),
+        })
+    }
+}
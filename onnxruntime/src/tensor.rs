@@ -6,26 +6,38 @@
 //! is an _owned_ tensor that is backed by [`ndarray`](https://crates.io/crates/ndarray).
 //! This kind of tensor is used to pass input data for the inference.
 //!
-//! The second one, [`OrtOwnedTensor`](struct.OrtOwnedTensor.html), is used
+//! The second one, [`DynOrtTensor`](struct.DynOrtTensor.html), is used
 //! internally to pass to the ONNX Runtime inference execution to place
-//! its output values. It is built using a [`OrtOwnedTensorExtractor`](struct.OrtOwnedTensorExtractor.html)
-//! following the builder pattern.
+//! its output values. Its element type is only known at runtime; it is converted
+//! to an [`OrtOwnedTensor`](struct.OrtOwnedTensor.html) of a concrete Rust type using
+//! [`DynOrtTensor::try_extract()`](struct.DynOrtTensor.html#method.try_extract).
 //!
 //! Once "extracted" from the runtime environment, this tensor will contain an
 //! [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html)
-//! containing _a view_ of the data. When going out of scope, this tensor will free the required
+//! containing _a view_ of the data. When going out of scope, the `DynOrtTensor` will free the required
 //! memory on the C side.
 //!
 //! **NOTE**: Tensors are not meant to be built directly. When performing inference,
 //! the [`Session::run()`](../session/struct.Session.html#method.run) method takes
-//! an `ndarray::Array` as input (taking ownership of it) and will convert it internally
-//! to a [`Tensor`](struct.Tensor.html). After inference, a [`OrtOwnedTensor`](struct.OrtOwnedTensor.html)
-//! will be returned by the method which can be derefed into its internal
+//! an `ndarray::Array` as input (taking ownership of it) or an `ndarray::ArrayView` (borrowing
+//! the data, without copy) and will convert it internally to a [`Tensor`](struct.Tensor.html).
+//! Inputs of different element types can be mixed in the same call by converting them to an
+//! [`InputTensor`](enum.InputTensor.html) first.
+//! After inference, a vector of [`DynOrtTensor`](struct.DynOrtTensor.html) will be returned
+//! by the method, which can be extracted into [`OrtOwnedTensor`](struct.OrtOwnedTensor.html)s
+//! and derefed into their internal
 //! [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html).
+//!
+//! Outputs can also be written directly into caller-owned arrays by wrapping a mutable view in
+//! an [`OutputBuffer`](enum.OutputBuffer.html).
 
+pub mod input_tensor;
 pub mod ndarray_tensor;
 pub mod ort_owned_tensor;
 pub mod ort_tensor;
+pub mod output_buffer;
 
-pub use ort_owned_tensor::OrtOwnedTensor;
+pub use input_tensor::InputTensor;
+pub use ort_owned_tensor::{DynOrtTensor, OrtOwnedTensor};
 pub use ort_tensor::OrtTensor;
+pub use output_buffer::OutputBuffer;
@@ -0,0 +1,434 @@
+//! Module containing a type-erased input tensor, allowing inputs of different types in the same inference call
+
+use std::fmt::Debug;
+
+use ndarray::{Array, ArrayView, ArrayViewMut, CowArray, IxDyn};
+
+use onnxruntime_sys as sys;
+
+use tracing::debug;
+
+use crate::{
+    g_ort,
+    memory::MemoryInfo,
+    tensor::{output_buffer::create_tensor_with_data, OrtTensor},
+    OrtError, Result, TensorElementDataType, TypeToTensorElementDataType,
+};
+
+/// Input tensor whose element type and number of dimensions are only known at runtime
+///
+/// ONNX models can have inputs of different types (for example BERT-like models taking `i64`
+/// token ids and `f32` masks). Since [`Session::run()`](../../session/struct.Session.html#method.run)
+/// takes a single `Vec<_>` of inputs, each input is converted into an `InputTensor` which records
+/// its [`TensorElementDataType`](../../enum.TensorElementDataType.html).
+///
+/// An `InputTensor` is usually built from an [`ndarray::Array`](https://docs.rs/ndarray/latest/ndarray/type.Array.html)
+/// of any dimension using `From`/`Into`, which moves the array:
+///
+/// ```
+/// # use onnxruntime::{tensor::InputTensor, TensorElementDataType};
+/// let input_ids = ndarray::arr2(&[[101_i64, 2023, 102]]);
+/// let attention_mask = ndarray::arr2(&[[1.0_f32, 1.0, 1.0]]);
+///
+/// let inputs: Vec<InputTensor> = vec![input_ids.into(), attention_mask.into()];
+/// assert_eq!(inputs[0].data_type(), TensorElementDataType::Int64);
+/// assert_eq!(inputs[1].data_type(), TensorElementDataType::Float);
+/// ```
+///
+/// It can also borrow the caller's data, from an [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html),
+/// an [`ndarray::ArrayViewMut`](https://docs.rs/ndarray/latest/ndarray/type.ArrayViewMut.html)
+/// or a slice and a shape with [`from_slice()`](#method.from_slice). Numeric data in standard
+/// layout is then used by the runtime in place, without any copy:
+///
+/// ```
+/// # use onnxruntime::tensor::InputTensor;
+/// let points = vec![0.0_f32; 3 * 1024];
+///
+/// let input = InputTensor::from_slice(&points, &[1, 1024, 3]).unwrap();
+/// assert_eq!(input.shape(), &[1, 1024, 3]);
+/// ```
+///
+/// Views that are not in standard (row major) layout, like transposed arrays, are copied.
+#[derive(Debug, Clone)]
+pub enum InputTensor<'a> {
+    /// 32-bit floating point tensor
+    Float(CowArray<'a, f32, IxDyn>),
+    /// Unsigned 8-bit int tensor
+    Uint8(CowArray<'a, u8, IxDyn>),
+    /// Signed 8-bit int tensor
+    Int8(CowArray<'a, i8, IxDyn>),
+    /// Unsigned 16-bit int tensor
+    Uint16(CowArray<'a, u16, IxDyn>),
+    /// Signed 16-bit int tensor
+    Int16(CowArray<'a, i16, IxDyn>),
+    /// Signed 32-bit int tensor
+    Int32(CowArray<'a, i32, IxDyn>),
+    /// Signed 64-bit int tensor
+    Int64(CowArray<'a, i64, IxDyn>),
+    /// String tensor
+    String(CowArray<'a, String, IxDyn>),
+    /// 64-bit floating point tensor
+    Double(CowArray<'a, f64, IxDyn>),
+    /// Unsigned 32-bit int tensor
+    Uint32(CowArray<'a, u32, IxDyn>),
+    /// Unsigned 64-bit int tensor
+    Uint64(CowArray<'a, u64, IxDyn>),
+}
+
+/// Apply the same expression to the array contained in any variant of an `InputTensor`
+macro_rules! map_input_tensor {
+    ($input_tensor:expr, $array:ident => $e:expr) => {
+        match $input_tensor {
+            InputTensor::Float($array) => $e,
+            InputTensor::Uint8($array) => $e,
+            InputTensor::Int8($array) => $e,
+            InputTensor::Uint16($array) => $e,
+            InputTensor::Int16($array) => $e,
+            InputTensor::Int32($array) => $e,
+            InputTensor::Int64($array) => $e,
+            InputTensor::String($array) => $e,
+            InputTensor::Double($array) => $e,
+            InputTensor::Uint32($array) => $e,
+            InputTensor::Uint64($array) => $e,
+        }
+    };
+}
+
+impl<'a> InputTensor<'a> {
+    /// Borrow `data` as a tensor of the given shape, without copy
+    ///
+    /// Returns an [`OrtError::InvalidShape`](../../error/enum.OrtError.html#variant.InvalidShape)
+    /// error if the number of elements of `shape` does not match the length of `data`.
+    pub fn from_slice<T>(data: &'a [T], shape: &[usize]) -> Result<InputTensor<'a>>
+    where
+        ArrayView<'a, T, IxDyn>: Into<InputTensor<'a>>,
+    {
+        let array = ArrayView::from_shape(shape, data).map_err(OrtError::InvalidShape)?;
+        Ok(array.into())
+    }
+
+    /// Type of the tensor's elements
+    pub fn data_type(&self) -> TensorElementDataType {
+        match self {
+            InputTensor::Float(_) => TensorElementDataType::Float,
+            InputTensor::Uint8(_) => TensorElementDataType::Uint8,
+            InputTensor::Int8(_) => TensorElementDataType::Int8,
+            InputTensor::Uint16(_) => TensorElementDataType::Uint16,
+            InputTensor::Int16(_) => TensorElementDataType::Int16,
+            InputTensor::Int32(_) => TensorElementDataType::Int32,
+            InputTensor::Int64(_) => TensorElementDataType::Int64,
+            InputTensor::String(_) => TensorElementDataType::String,
+            InputTensor::Double(_) => TensorElementDataType::Double,
+            InputTensor::Uint32(_) => TensorElementDataType::Uint32,
+            InputTensor::Uint64(_) => TensorElementDataType::Uint64,
+        }
+    }
+
+    /// Shape of the tensor
+    pub fn shape(&self) -> &[usize] {
+        map_input_tensor!(self, array => array.shape())
+    }
+
+    /// Provide the data to the runtime without copy, returning a value usable as a session initializer
+    ///
+    /// Data not in standard layout is copied first. The runtime does not take ownership of the
+    /// data of initializers, so string tensors are not supported.
+    pub(crate) fn into_initializer(
+        self,
+        memory_info: &MemoryInfo,
+    ) -> Result<InitializerTensor<'a>> {
+        let data_type = self.data_type();
+        if data_type == TensorElementDataType::String {
+            return Err(OrtError::StringTensorNotSupported);
+        }
+
+        let mut data = self;
+        map_input_tensor!(&mut data, array => {
+            if !array.is_standard_layout() {
+                let standard_layout = array.as_standard_layout().into_owned();
+                *array = standard_layout.into();
+            }
+        });
+        let c_ptr = map_input_tensor!(&data, array => {
+            let slice = array.as_slice().ok_or(OrtError::NonContiguousArray)?;
+            unsafe {
+                create_tensor_with_data(
+                    memory_info,
+                    data_type,
+                    array.shape(),
+                    slice.as_ptr() as *mut std::ffi::c_void,
+                    std::mem::size_of_val(slice),
+                )?
+            }
+        });
+
+        Ok(InitializerTensor {
+            c_ptr,
+            _data: Box::new(data),
+        })
+    }
+
+    /// Concatenate tensors along their first axis, copying their data
+    ///
+    /// All the tensors must have the same element type, and the same shape except for the first
+    /// dimension.
+    pub(crate) fn concatenate(tensors: Vec<InputTensor<'a>>) -> Result<InputTensor<'static>> {
+        macro_rules! concatenate_variant {
+            ($variant:ident) => {{
+                let arrays: Vec<_> = tensors
+                    .into_iter()
+                    .map(|tensor| match tensor {
+                        InputTensor::$variant(array) => array,
+                        tensor => panic!(
+                            "Concatenating tensors of types {:?} and {:?}",
+                            TensorElementDataType::$variant,
+                            tensor.data_type()
+                        ),
+                    })
+                    .collect();
+                InputTensor::$variant(concatenate_arrays(&arrays)?.into())
+            }};
+        }
+
+        Ok(match tensors[0].data_type() {
+            TensorElementDataType::Float => concatenate_variant!(Float),
+            TensorElementDataType::Uint8 => concatenate_variant!(Uint8),
+            TensorElementDataType::Int8 => concatenate_variant!(Int8),
+            TensorElementDataType::Uint16 => concatenate_variant!(Uint16),
+            TensorElementDataType::Int16 => concatenate_variant!(Int16),
+            TensorElementDataType::Int32 => concatenate_variant!(Int32),
+            TensorElementDataType::Int64 => concatenate_variant!(Int64),
+            TensorElementDataType::String => concatenate_variant!(String),
+            TensorElementDataType::Double => concatenate_variant!(Double),
+            TensorElementDataType::Uint32 => concatenate_variant!(Uint32),
+            TensorElementDataType::Uint64 => concatenate_variant!(Uint64),
+        })
+    }
+
+    /// Provide the data to the runtime, returning a tensor usable as an inference input
+    ///
+    /// Only string tensors and numeric tensors not in standard layout are copied.
+    pub(crate) fn into_ort_tensor<'t>(
+        self,
+        memory_info: &'t MemoryInfo,
+        allocator_ptr: *mut sys::OrtAllocator,
+    ) -> Result<Box<dyn OrtInput + 't>>
+    where
+        'a: 't, // 'a outlives 't
+    {
+        map_input_tensor!(self, array => {
+            let tensor = OrtTensor::from_array(memory_info, allocator_ptr, shorten_lifetime(array))?;
+            Ok(Box::new(tensor) as Box<dyn OrtInput + 't>)
+        })
+    }
+}
+
+/// Concatenate arrays along their first axis
+///
+/// `ndarray`'s `stack()` requires `Copy` elements, which `String`s are not.
+fn concatenate_arrays<T>(arrays: &[CowArray<T, IxDyn>]) -> Result<Array<T, IxDyn>>
+where
+    T: Clone,
+{
+    let mut shape = arrays[0].shape().to_vec();
+    shape[0] = arrays.iter().map(|array| array.shape()[0]).sum();
+    let data: Vec<T> = arrays
+        .iter()
+        .flat_map(|array| array.iter().cloned())
+        .collect();
+    Array::from_shape_vec(shape, data).map_err(OrtError::InvalidShape)
+}
+
+/// Shorten the lifetime of the data borrowed by an array
+///
+/// `ndarray`'s arrays are not covariant in the lifetime of their data, so a `CowArray<'a, _, _>`
+/// can't be used as a `CowArray<'t, _, _>` even though `'a` outlives `'t`.
+fn shorten_lifetime<'a, 't, T, D>(array: CowArray<'a, T, D>) -> CowArray<'t, T, D>
+where
+    'a: 't, // 'a outlives 't
+    T: Clone,
+    D: ndarray::Dimension,
+{
+    if array.is_view() {
+        // The view points to data borrowed for 'a, not to `array` itself
+        let view: ArrayView<'t, T, D> = unsafe { array.raw_view().deref_into_view() };
+        view.into()
+    } else {
+        // Moves the data, without copy
+        array.into_owned().into()
+    }
+}
+
+/// Runtime value pointing to the data of an [`InputTensor`](enum.InputTensor.html), used as a
+/// session initializer
+///
+/// The data is kept alive (or borrowed) for as long as the value exists. It is stored as a trait
+/// object, which unlike `InputTensor` is covariant in `'a`, so that a `Session` holding
+/// initializers stays covariant in its lifetime.
+#[derive(Debug)]
+pub(crate) struct InitializerTensor<'a> {
+    pub(crate) c_ptr: *mut sys::OrtValue,
+    _data: Box<dyn Debug + Send + Sync + 'a>,
+}
+
+impl<'a> Drop for InitializerTensor<'a> {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping InitializerTensor.");
+        unsafe { g_ort().ReleaseValue.unwrap()(self.c_ptr) }
+
+        self.c_ptr = std::ptr::null_mut();
+    }
+}
+
+/// A tensor stored in the runtime's memory, used as an inference input
+///
+/// This trait erases the element type and dimension of an [`OrtTensor`](../ort_tensor/struct.OrtTensor.html)
+/// so that inputs of different types can be passed to the same inference call.
+pub(crate) trait OrtInput: Debug {
+    /// Pointer to the runtime's value
+    fn c_ptr(&self) -> *const sys::OrtValue;
+}
+
+impl<'t, T, D> OrtInput for OrtTensor<'t, T, D>
+where
+    T: TypeToTensorElementDataType + Debug + Clone,
+    D: ndarray::Dimension,
+{
+    fn c_ptr(&self) -> *const sys::OrtValue {
+        self.c_ptr
+    }
+}
+
+macro_rules! impl_from_array {
+    ($type_:ty, $variant:ident) => {
+        impl<'a, D> From<Array<$type_, D>> for InputTensor<'a>
+        where
+            D: ndarray::Dimension,
+        {
+            fn from(array: Array<$type_, D>) -> Self {
+                InputTensor::$variant(array.into_dyn().into())
+            }
+        }
+
+        impl<'a, D> From<ArrayView<'a, $type_, D>> for InputTensor<'a>
+        where
+            D: ndarray::Dimension,
+        {
+            fn from(array: ArrayView<'a, $type_, D>) -> Self {
+                InputTensor::$variant(array.into_dyn().into())
+            }
+        }
+
+        impl<'a, D> From<ArrayViewMut<'a, $type_, D>> for InputTensor<'a>
+        where
+            D: ndarray::Dimension,
+        {
+            fn from(array: ArrayViewMut<'a, $type_, D>) -> Self {
+                // The exclusive borrow of the data is downgraded to a shared one for 'a
+                let array: ArrayView<'a, $type_, D> = unsafe { array.raw_view().deref_into_view() };
+                InputTensor::$variant(array.into_dyn().into())
+            }
+        }
+    };
+}
+
+impl_from_array!(f32, Float);
+impl_from_array!(u8, Uint8);
+impl_from_array!(i8, Int8);
+impl_from_array!(u16, Uint16);
+impl_from_array!(i16, Int16);
+impl_from_array!(i32, Int32);
+impl_from_array!(i64, Int64);
+impl_from_array!(String, String);
+impl_from_array!(f64, Double);
+impl_from_array!(u32, Uint32);
+impl_from_array!(u64, Uint64);
+
+impl<'a, 's, D> From<Array<&'s str, D>> for InputTensor<'a>
+where
+    D: ndarray::Dimension,
+{
+    fn from(array: Array<&'s str, D>) -> Self {
+        InputTensor::String(array.map(|s| s.to_string()).into_dyn().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllocatorType, MemType};
+    use ndarray::{arr1, arr2, arr3};
+    use test_env_log::test;
+
+    #[test]
+    fn input_tensor_from_arrays() {
+        let int64 = InputTensor::from(arr2(&[[1_i64, 2, 3]]));
+        assert_eq!(int64.data_type(), TensorElementDataType::Int64);
+        assert_eq!(int64.shape(), &[1, 3]);
+
+        let float = InputTensor::from(arr3(&[[[1.0_f32], [2.0]]]));
+        assert_eq!(float.data_type(), TensorElementDataType::Float);
+        assert_eq!(float.shape(), &[1, 2, 1]);
+
+        let string = InputTensor::from(arr1(&["foo", "bar"]));
+        assert_eq!(string.data_type(), TensorElementDataType::String);
+        assert_eq!(string.shape(), &[2]);
+    }
+
+    #[test]
+    fn input_tensor_from_views() {
+        let mut array = arr2(&[[1.0_f32, 2.0], [3.0, 4.0]]);
+
+        let view = InputTensor::from(array.view());
+        assert_eq!(view.data_type(), TensorElementDataType::Float);
+        assert_eq!(view.shape(), &[2, 2]);
+
+        let view_mut = InputTensor::from(array.view_mut());
+        assert_eq!(view_mut.shape(), &[2, 2]);
+
+        let data = [1_i32, 2, 3, 4, 5, 6];
+        let slice = InputTensor::from_slice(&data, &[3, 2]).unwrap();
+        assert_eq!(slice.data_type(), TensorElementDataType::Int32);
+        assert_eq!(slice.shape(), &[3, 2]);
+        assert!(matches!(
+            InputTensor::from_slice(&data, &[4, 2]),
+            Err(OrtError::InvalidShape(_))
+        ));
+    }
+
+    #[test]
+    fn input_tensor_concatenate() {
+        let first = arr2(&[[1.0_f32, 2.0]]);
+        let second = arr2(&[[3.0_f32, 4.0], [5.0, 6.0]]);
+        let tensors = vec![InputTensor::from(first.view()), second.t().into()];
+
+        match InputTensor::concatenate(tensors).unwrap() {
+            InputTensor::Float(array) => {
+                assert_eq!(
+                    array,
+                    arr2(&[[1.0_f32, 2.0], [3.0, 5.0], [4.0, 6.0]]).into_dyn()
+                )
+            }
+            tensor => panic!("Unexpected tensor {:?}", tensor),
+        }
+
+        let strings = vec![arr1(&["foo"]).into(), arr1(&["bar", "baz"]).into()];
+        let concatenated = InputTensor::concatenate(strings).unwrap();
+        assert_eq!(concatenated.data_type(), TensorElementDataType::String);
+        assert_eq!(concatenated.shape(), &[3]);
+    }
+
+    #[test]
+    fn input_tensor_into_ort_tensor() {
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        let borrowed = arr2(&[[1.0_f32, 1.0, 0.0]]);
+        let inputs: Vec<InputTensor> = vec![arr2(&[[1_i64, 2, 3]]).into(), borrowed.view().into()];
+        let ort_inputs = inputs
+            .into_iter()
+            .map(|input| input.into_ort_tensor(&memory_info, std::ptr::null_mut()))
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert!(ort_inputs.iter().all(|input| !input.c_ptr().is_null()));
+    }
+}
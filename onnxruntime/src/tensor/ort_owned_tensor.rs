@@ -1,6 +1,6 @@
 //! Module containing tensor with memory owned by the ONNX Runtime
 
-use std::{fmt::Debug, ops::Deref};
+use std::{fmt::Debug, marker::PhantomData, ops::Deref};
 
 use ndarray::{Array, ArrayView};
 use tracing::debug;
@@ -8,8 +8,11 @@ use tracing::debug;
 use onnxruntime_sys as sys;
 
 use crate::{
-    error::status_to_result, g_ort, memory::MemoryInfo, tensor::ndarray_tensor::NdArrayTensor,
-    OrtError, Result, TypeToTensorElementDataType,
+    error::{call_ort, status_to_result},
+    g_ort,
+    session::get_tensor_type_and_shape,
+    tensor::ndarray_tensor::NdArrayTensor,
+    OrtError, Result, TensorElementDataType, TypeToTensorElementDataType,
 };
 
 /// Tensor containing data owned by the ONNX Runtime C library, used to return values from inference.
@@ -17,73 +20,67 @@ use crate::{
 /// This tensor type is returned by the [`Session::run()`](../session/struct.Session.html#method.run) method.
 /// It is not meant to be created directly.
 ///
-/// The tensor hosts an [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html)
-/// of the data on the C side. This allows manipulation on the Rust side using `ndarray` without copying the data.
+/// The element type of the data is only known at runtime, and is available through
+/// [`data_type()`](#method.data_type). Use [`try_extract()`](#method.try_extract) to obtain
+/// an [`OrtOwnedTensor`](struct.OrtOwnedTensor.html) of a concrete Rust type, which fails if
+/// the requested type does not match the tensor's element type.
 ///
-/// `OrtOwnedTensor` implements the [`std::deref::Deref`](#impl-Deref) trait for ergonomic access to
-/// the underlying [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html).
+/// The runtime keeps the memory of the tensor alive on its own, so a tensor returned by
+/// [`Session::run()`](../session/struct.Session.html#method.run) is `'static`: it can outlive
+/// the session that produced it and be sent to another thread. The `'d` lifetime is only
+/// restricted when the tensor points to borrowed data, like the outputs of an
+/// [`IoBinding`](../io_binding/struct.IoBinding.html) bound to caller-owned buffers.
+///
+/// When going out of scope, this tensor will free the required memory on the C side.
 #[derive(Debug)]
-pub struct OrtOwnedTensor<'t, 'm, T, D>
+pub struct DynOrtTensor<'d, D>
 where
-    T: TypeToTensorElementDataType + Debug + Clone,
     D: ndarray::Dimension,
-    'm: 't, // 'm outlives 't
 {
-    pub(crate) tensor_ptr: *mut sys::OrtValue,
-    array_view: ArrayView<'t, T, D>,
-    memory_info: &'m MemoryInfo,
+    tensor_ptr: *mut sys::OrtValue,
+    shape: D,
+    data_type: TensorElementDataType,
+    _data: PhantomData<&'d ()>,
 }
 
-impl<'t, 'm, T, D> Deref for OrtOwnedTensor<'t, 'm, T, D>
-where
-    T: TypeToTensorElementDataType + Debug + Clone,
-    D: ndarray::Dimension,
-{
-    type Target = ArrayView<'t, T, D>;
+// The value is exclusively owned by the tensor, and the runtime's values are not tied to the
+// thread that created them.
+unsafe impl<'d, D> Send for DynOrtTensor<'d, D> where D: ndarray::Dimension {}
 
-    fn deref(&self) -> &Self::Target {
-        &self.array_view
-    }
-}
-
-impl<'t, 'm, T, D> OrtOwnedTensor<'t, 'm, T, D>
+impl<'d, D> DynOrtTensor<'d, D>
 where
-    T: TypeToTensorElementDataType + Debug + Clone,
     D: ndarray::Dimension,
 {
-    /// Apply a softmax on the specified axis
-    pub fn softmax(&self, axis: ndarray::Axis) -> Array<T, D>
-    where
-        D: ndarray::RemoveAxis,
-        T: ndarray::NdFloat + std::ops::SubAssign + std::ops::DivAssign,
-    {
-        self.array_view.softmax(axis)
+    pub(crate) fn new(
+        tensor_ptr: *mut sys::OrtValue,
+        shape: D,
+        data_type: TensorElementDataType,
+    ) -> DynOrtTensor<'d, D> {
+        DynOrtTensor {
+            tensor_ptr,
+            shape,
+            data_type,
+            _data: PhantomData,
+        }
     }
-}
 
-#[derive(Debug)]
-pub(crate) struct OrtOwnedTensorExtractor<'m, D>
-where
-    D: ndarray::Dimension,
-{
-    pub(crate) tensor_ptr: *mut sys::OrtValue,
-    memory_info: &'m MemoryInfo,
-    shape: D,
-}
+    /// Type of the tensor's elements, as reported by the runtime
+    pub fn data_type(&self) -> TensorElementDataType {
+        self.data_type
+    }
 
-impl<'m, D> OrtOwnedTensorExtractor<'m, D>
-where
-    D: ndarray::Dimension,
-{
-    pub(crate) fn new(memory_info: &'m MemoryInfo, shape: D) -> OrtOwnedTensorExtractor<'m, D> {
-        OrtOwnedTensorExtractor {
-            tensor_ptr: std::ptr::null_mut(),
-            memory_info,
-            shape,
-        }
+    /// Shape of the tensor
+    pub fn shape(&self) -> &[usize] {
+        self.shape.slice()
     }
 
-    pub(crate) fn extract<'t, T>(self) -> Result<OrtOwnedTensor<'t, 'm, T, D>>
+    /// Extract a view of the tensor's data as elements of type `T`
+    ///
+    /// Returns an [`OrtError::NonMatchingOutputDataType`](../../error/enum.OrtError.html#variant.NonMatchingOutputDataType)
+    /// error if `T` does not match the tensor's [`data_type()`](#method.data_type).
+    ///
+    /// **NOTE**: Extracting `String` tensors is not supported yet.
+    pub fn try_extract<'t, T>(&'t self) -> Result<OrtOwnedTensor<'t, T, D>>
     where
         T: TypeToTensorElementDataType + Debug + Clone,
     {
@@ -92,43 +89,229 @@ where
 
         assert_ne!(self.tensor_ptr, std::ptr::null_mut());
 
+        let requested_type = T::tensor_element_data_type();
+        if requested_type != self.data_type {
+            return Err(OrtError::NonMatchingOutputDataType {
+                requested_type,
+                output_type: self.data_type,
+            });
+        }
+        if self.data_type == TensorElementDataType::String {
+            // Strings are stored in the runtime as C strings; they can't be viewed as `String`s.
+            return Err(OrtError::StringTensorNotSupported);
+        }
+
         let mut is_tensor = 0;
         let status = unsafe { g_ort().IsTensor.unwrap()(self.tensor_ptr, &mut is_tensor) };
         status_to_result(status).map_err(OrtError::IsTensor)?;
         assert_eq!(is_tensor, 1);
 
-        // Get pointer to output tensor float values
-        let mut output_array_ptr: *mut T = std::ptr::null_mut();
-        let output_array_ptr_ptr: *mut *mut T = &mut output_array_ptr;
-        let output_array_ptr_ptr_void: *mut *mut std::ffi::c_void =
-            output_array_ptr_ptr as *mut *mut std::ffi::c_void;
-        let status = unsafe {
-            g_ort().GetTensorMutableData.unwrap()(self.tensor_ptr, output_array_ptr_ptr_void)
-        };
-        status_to_result(status).map_err(OrtError::IsTensor)?;
-        assert_ne!(output_array_ptr, std::ptr::null_mut());
+        // Get pointer to output tensor values
+        let output_array_ptr = self.data_ptr()? as *mut T;
+
+        let array_view = unsafe { ArrayView::from_shape_ptr(self.shape.clone(), output_array_ptr) };
+
+        Ok(OrtOwnedTensor { array_view })
+    }
+
+    /// Copy the elements of a `String` tensor, in standard layout order
+    pub(crate) fn extract_strings(&self) -> Result<Vec<String>> {
+        assert_eq!(self.data_type, TensorElementDataType::String);
+        let count = self.shape.size();
+
+        let mut total_length = 0;
+        unsafe {
+            call_ort(|ort| {
+                ort.GetStringTensorDataLength.unwrap()(self.tensor_ptr, &mut total_length)
+            })
+        }
+        .map_err(OrtError::GetStringTensorContent)?;
+
+        // The strings are concatenated, without null terminators
+        let mut content: Vec<u8> = vec![0; total_length as usize];
+        let mut offsets: Vec<u64> = vec![0; count];
+        unsafe {
+            call_ort(|ort| {
+                ort.GetStringTensorContent.unwrap()(
+                    self.tensor_ptr,
+                    content.as_mut_ptr() as *mut std::ffi::c_void,
+                    total_length,
+                    offsets.as_mut_ptr(),
+                    count as u64,
+                )
+            })
+        }
+        .map_err(OrtError::GetStringTensorContent)?;
+
+        let ends = offsets.iter().skip(1).copied().chain(Some(total_length));
+        offsets
+            .iter()
+            .zip(ends)
+            .map(|(&start, end)| {
+                String::from_utf8(content[start as usize..end as usize].to_vec())
+                    .map_err(OrtError::NonUtf8String)
+            })
+            .collect()
+    }
+
+    /// Pointer to the tensor's data, owned by the runtime
+    fn data_ptr(&self) -> Result<*mut std::ffi::c_void> {
+        let mut data_ptr: *mut std::ffi::c_void = std::ptr::null_mut();
+        let status =
+            unsafe { g_ort().GetTensorMutableData.unwrap()(self.tensor_ptr, &mut data_ptr) };
+        status_to_result(status).map_err(OrtError::GetTensorMutableData)?;
+        assert_ne!(data_ptr, std::ptr::null_mut());
+        Ok(data_ptr)
+    }
+}
+
+impl<'d> DynOrtTensor<'d, ndarray::IxDyn> {
+    /// Wrap a tensor value returned by the runtime, querying its shape and element type
+    pub(crate) fn from_ort_value(
+        tensor_ptr: *mut sys::OrtValue,
+    ) -> Result<DynOrtTensor<'d, ndarray::IxDyn>> {
+        let (data_type, dims) = unsafe { get_tensor_type_and_shape(tensor_ptr)? };
+
+        Ok(DynOrtTensor::new(
+            tensor_ptr,
+            ndarray::IxDyn(&dims),
+            data_type,
+        ))
+    }
 
-        let array_view = unsafe { ArrayView::from_shape_ptr(self.shape, output_array_ptr) };
+    /// Copy the tensor into new tensors, each containing the next `batch_sizes[i]` entries along
+    /// the first axis
+    ///
+    /// The sizes must add up to the size of the first dimension.
+    pub(crate) fn split_batch(
+        &self,
+        batch_sizes: &[usize],
+        allocator_ptr: *mut sys::OrtAllocator,
+    ) -> Result<Vec<DynOrtTensor<'static, ndarray::IxDyn>>> {
+        let shape = self.shape();
+        assert_eq!(shape[0], batch_sizes.iter().sum::<usize>());
+        let entry_byte_size =
+            element_byte_size(self.data_type)? * shape[1..].iter().product::<usize>();
+        let data_ptr = self.data_ptr()? as *const u8;
 
-        Ok(OrtOwnedTensor {
-            tensor_ptr: self.tensor_ptr,
-            array_view,
-            memory_info: self.memory_info,
-        })
+        let mut offset = 0;
+        batch_sizes
+            .iter()
+            .map(|&batch_size| {
+                let mut split_shape = shape.to_vec();
+                split_shape[0] = batch_size;
+                let split_dims: Vec<i64> = split_shape.iter().map(|&d| d as i64).collect();
+
+                let mut tensor_ptr: *mut sys::OrtValue = std::ptr::null_mut();
+                unsafe {
+                    call_ort(|ort| {
+                        ort.CreateTensorAsOrtValue.unwrap()(
+                            allocator_ptr,
+                            split_dims.as_ptr(),
+                            split_dims.len() as u64,
+                            self.data_type.into(),
+                            &mut tensor_ptr,
+                        )
+                    })
+                }
+                .map_err(OrtError::CreateTensor)?;
+                assert_ne!(tensor_ptr, std::ptr::null_mut());
+                let tensor =
+                    DynOrtTensor::new(tensor_ptr, ndarray::IxDyn(&split_shape), self.data_type);
+
+                let byte_size = batch_size * entry_byte_size;
+                if byte_size > 0 {
+                    unsafe {
+                        std::ptr::copy_nonoverlapping(
+                            data_ptr.add(offset),
+                            tensor.data_ptr()? as *mut u8,
+                            byte_size,
+                        )
+                    };
+                }
+                offset += byte_size;
+
+                Ok(tensor)
+            })
+            .collect()
     }
 }
 
-impl<'t, 'm, T, D> Drop for OrtOwnedTensor<'t, 'm, T, D>
+/// Size in bytes of a tensor element of type `data_type`
+fn element_byte_size(data_type: TensorElementDataType) -> Result<usize> {
+    use std::mem::size_of;
+    use TensorElementDataType::*;
+    Ok(match data_type {
+        Float => size_of::<f32>(),
+        Uint8 => size_of::<u8>(),
+        Int8 => size_of::<i8>(),
+        Uint16 => size_of::<u16>(),
+        Int16 => size_of::<i16>(),
+        Int32 => size_of::<i32>(),
+        Int64 => size_of::<i64>(),
+        // Strings are stored in the runtime as C strings, not in a contiguous buffer
+        String => return Err(OrtError::StringTensorNotSupported),
+        Double => size_of::<f64>(),
+        Uint32 => size_of::<u32>(),
+        Uint64 => size_of::<u64>(),
+    })
+}
+
+impl<'d, D> Drop for DynOrtTensor<'d, D>
 where
-    T: TypeToTensorElementDataType + Debug + Clone,
     D: ndarray::Dimension,
-    'm: 't, // 'm outlives 't
 {
     #[tracing::instrument]
     fn drop(&mut self) {
-        debug!("Dropping OrtOwnedTensor.");
+        debug!("Dropping DynOrtTensor.");
         unsafe { g_ort().ReleaseValue.unwrap()(self.tensor_ptr) }
 
         self.tensor_ptr = std::ptr::null_mut();
     }
 }
+
+/// View of the data of a [`DynOrtTensor`](struct.DynOrtTensor.html), with a concrete element type.
+///
+/// This tensor type is obtained with [`DynOrtTensor::try_extract()`](struct.DynOrtTensor.html#method.try_extract).
+/// It is not meant to be created directly.
+///
+/// The tensor hosts an [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html)
+/// of the data on the C side. This allows manipulation on the Rust side using `ndarray` without copying the data.
+///
+/// `OrtOwnedTensor` implements the [`std::deref::Deref`](#impl-Deref) trait for ergonomic access to
+/// the underlying [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html).
+#[derive(Debug)]
+pub struct OrtOwnedTensor<'t, T, D>
+where
+    T: TypeToTensorElementDataType + Debug + Clone,
+    D: ndarray::Dimension,
+{
+    array_view: ArrayView<'t, T, D>,
+}
+
+impl<'t, T, D> Deref for OrtOwnedTensor<'t, T, D>
+where
+    T: TypeToTensorElementDataType + Debug + Clone,
+    D: ndarray::Dimension,
+{
+    type Target = ArrayView<'t, T, D>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.array_view
+    }
+}
+
+impl<'t, T, D> OrtOwnedTensor<'t, T, D>
+where
+    T: TypeToTensorElementDataType + Debug + Clone,
+    D: ndarray::Dimension,
+{
+    /// Apply a softmax on the specified axis
+    pub fn softmax(&self, axis: ndarray::Axis) -> Array<T, D>
+    where
+        D: ndarray::RemoveAxis,
+        T: ndarray::NdFloat + std::ops::SubAssign + std::ops::DivAssign,
+    {
+        self.array_view.softmax(axis)
+    }
+}
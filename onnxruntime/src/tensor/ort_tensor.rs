@@ -2,7 +2,7 @@
 
 use std::{ffi, fmt::Debug, ops::Deref};
 
-use ndarray::Array;
+use ndarray::{Array, CowArray};
 use tracing::{debug, error};
 
 use onnxruntime_sys as sys;
@@ -13,10 +13,13 @@ use crate::{
     TypeToTensorElementDataType,
 };
 
-/// Owned tensor, backed by an [`ndarray::Array`](https://docs.rs/ndarray/latest/ndarray/type.Array.html)
+/// Tensor backed by an [`ndarray::CowArray`](https://docs.rs/ndarray/latest/ndarray/type.CowArray.html)
 ///
-/// This tensor bounds the ONNX Runtime to `ndarray`; it is used to copy an
-/// [`ndarray::Array`](https://docs.rs/ndarray/latest/ndarray/type.Array.html) to the runtime's memory.
+/// This tensor bounds the ONNX Runtime to `ndarray`; it is used to provide an
+/// [`ndarray::Array`](https://docs.rs/ndarray/latest/ndarray/type.Array.html) or an
+/// [`ndarray::ArrayView`](https://docs.rs/ndarray/latest/ndarray/type.ArrayView.html) to the runtime.
+/// Numeric data in standard layout is used by the runtime in place, without copy; the data is
+/// borrowed (or owned) for the lifetime `'t` of the tensor.
 ///
 /// **NOTE**: The type is not meant to be used directly, use an [`ndarray::Array`](https://docs.rs/ndarray/latest/ndarray/type.Array.html)
 /// instead.
@@ -27,7 +30,7 @@ where
     D: ndarray::Dimension,
 {
     pub(crate) c_ptr: *mut sys::OrtValue,
-    array: Array<T, D>,
+    array: CowArray<'t, T, D>,
     memory_info: &'t MemoryInfo,
 }
 
@@ -36,14 +39,22 @@ where
     T: TypeToTensorElementDataType + Debug + Clone,
     D: ndarray::Dimension,
 {
-    pub(crate) fn from_array<'m>(
+    pub(crate) fn from_array<'m, A>(
         memory_info: &'m MemoryInfo,
         allocator_ptr: *mut sys::OrtAllocator,
-        mut array: Array<T, D>,
+        array: A,
     ) -> Result<OrtTensor<'t, T, D>>
     where
         'm: 't, // 'm outlives 't
+        A: Into<CowArray<'t, T, D>>,
     {
+        let mut array: CowArray<'t, T, D> = array.into();
+        if !array.is_standard_layout() {
+            // The runtime expects the data contiguous and in row major order
+            let standard_layout_array = array.as_standard_layout().into_owned();
+            array = standard_layout_array.into();
+        }
+
         // where onnxruntime will write the tensor data to
         let mut tensor_ptr: *mut sys::OrtValue = std::ptr::null_mut();
         let tensor_ptr_ptr: *mut *mut sys::OrtValue = &mut tensor_ptr;
@@ -64,9 +75,10 @@ where
             | TensorElementDataType::Uint32
             | TensorElementDataType::Uint64 => {
                 // primitive data is already suitably laid out in memory; provide it to
-                // onnxruntime as is
+                // onnxruntime as is. The runtime does not write to its inputs, so the data
+                // can be borrowed immutably.
                 let tensor_values_ptr: *mut std::ffi::c_void =
-                    array.as_mut_ptr() as *mut std::ffi::c_void;
+                    array.as_ptr() as *mut std::ffi::c_void;
                 assert_ne!(tensor_values_ptr, std::ptr::null_mut());
 
                 unsafe {
@@ -149,7 +161,7 @@ where
     T: TypeToTensorElementDataType + Debug + Clone,
     D: ndarray::Dimension,
 {
-    type Target = Array<T, D>;
+    type Target = CowArray<'t, T, D>;
 
     fn deref(&self) -> &Self::Target {
         &self.array
@@ -194,7 +206,7 @@ where
 mod tests {
     use super::*;
     use crate::{AllocatorType, MemType};
-    use ndarray::{arr0, arr1, arr2, arr3};
+    use ndarray::{arr0, arr1, arr2, arr3, s};
     use std::ptr;
     use test_env_log::test;
 
@@ -259,6 +271,31 @@ mod tests {
         assert_eq!(tensor.shape(), &[2, 2, 3]);
     }
 
+    #[test]
+    fn orttensor_from_array_view_borrows_data() {
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        let array = arr2(&[[1.0_f32, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let tensor = OrtTensor::from_array(&memory_info, ptr::null_mut(), array.view()).unwrap();
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert!(tensor.is_view());
+        assert_eq!(tensor.as_ptr(), array.as_ptr());
+    }
+
+    #[test]
+    fn orttensor_from_array_view_non_standard_layout() {
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        let array = arr2(&[[1_i64, 2, 3], [4, 5, 6]]);
+        let tensor = OrtTensor::from_array(&memory_info, ptr::null_mut(), array.t()).unwrap();
+        assert_eq!(tensor.shape(), &[3, 2]);
+        assert!(tensor.is_owned());
+        assert!(tensor.is_standard_layout());
+        assert_eq!(*tensor, arr2(&[[1_i64, 4], [2, 5], [3, 6]]));
+
+        let tensor =
+            OrtTensor::from_array(&memory_info, ptr::null_mut(), array.slice(s![.., 1..])).unwrap();
+        assert_eq!(tensor.as_slice(), Some(&[2_i64, 3, 5, 6][..]));
+    }
+
     fn ort_default_allocator() -> *mut sys::OrtAllocator {
         let mut allocator_ptr: *mut sys::OrtAllocator = std::ptr::null_mut();
         unsafe {
@@ -0,0 +1,242 @@
+//! Module containing caller-owned buffers the runtime can write output values into
+
+use ndarray::{ArrayViewMut, ArrayViewMutD};
+use tracing::debug;
+
+use onnxruntime_sys as sys;
+
+use crate::{
+    error::call_ort, g_ort, memory::MemoryInfo, tensor::DynOrtTensor, OrtError, Result,
+    TensorElementDataType,
+};
+
+/// Mutable view of a caller-owned array, used by the runtime to write an output value
+///
+/// Writing outputs directly into pre-allocated buffers avoids an allocation and a copy for each
+/// inference call. An `OutputBuffer` is built from an
+/// [`ndarray::ArrayViewMut`](https://docs.rs/ndarray/latest/ndarray/type.ArrayViewMut.html)
+/// of any dimension using `From`/`Into`:
+///
+/// ```
+/// # use onnxruntime::{tensor::OutputBuffer, TensorElementDataType};
+/// let mut probabilities = ndarray::Array2::<f32>::zeros((1, 1000));
+///
+/// let buffer: OutputBuffer = probabilities.view_mut().into();
+/// assert_eq!(buffer.data_type(), TensorElementDataType::Float);
+/// assert_eq!(buffer.shape(), &[1, 1000]);
+/// ```
+///
+/// The view must be contiguous and in standard (row major) layout. Since the runtime writes to
+/// the buffer directly, its shape must match the shape of the output exactly.
+///
+/// **NOTE**: String outputs can't be written to caller-owned buffers.
+#[derive(Debug)]
+pub enum OutputBuffer<'a> {
+    /// 32-bit floating point buffer
+    Float(ArrayViewMutD<'a, f32>),
+    /// Unsigned 8-bit int buffer
+    Uint8(ArrayViewMutD<'a, u8>),
+    /// Signed 8-bit int buffer
+    Int8(ArrayViewMutD<'a, i8>),
+    /// Unsigned 16-bit int buffer
+    Uint16(ArrayViewMutD<'a, u16>),
+    /// Signed 16-bit int buffer
+    Int16(ArrayViewMutD<'a, i16>),
+    /// Signed 32-bit int buffer
+    Int32(ArrayViewMutD<'a, i32>),
+    /// Signed 64-bit int buffer
+    Int64(ArrayViewMutD<'a, i64>),
+    /// 64-bit floating point buffer
+    Double(ArrayViewMutD<'a, f64>),
+    /// Unsigned 32-bit int buffer
+    Uint32(ArrayViewMutD<'a, u32>),
+    /// Unsigned 64-bit int buffer
+    Uint64(ArrayViewMutD<'a, u64>),
+}
+
+/// Apply the same expression to the view contained in any variant of an `OutputBuffer`
+macro_rules! map_output_buffer {
+    ($output_buffer:expr, $array:ident => $e:expr) => {
+        match $output_buffer {
+            OutputBuffer::Float($array) => $e,
+            OutputBuffer::Uint8($array) => $e,
+            OutputBuffer::Int8($array) => $e,
+            OutputBuffer::Uint16($array) => $e,
+            OutputBuffer::Int16($array) => $e,
+            OutputBuffer::Int32($array) => $e,
+            OutputBuffer::Int64($array) => $e,
+            OutputBuffer::Double($array) => $e,
+            OutputBuffer::Uint32($array) => $e,
+            OutputBuffer::Uint64($array) => $e,
+        }
+    };
+}
+
+impl<'a> OutputBuffer<'a> {
+    /// Type of the buffer's elements
+    pub fn data_type(&self) -> TensorElementDataType {
+        match self {
+            OutputBuffer::Float(_) => TensorElementDataType::Float,
+            OutputBuffer::Uint8(_) => TensorElementDataType::Uint8,
+            OutputBuffer::Int8(_) => TensorElementDataType::Int8,
+            OutputBuffer::Uint16(_) => TensorElementDataType::Uint16,
+            OutputBuffer::Int16(_) => TensorElementDataType::Int16,
+            OutputBuffer::Int32(_) => TensorElementDataType::Int32,
+            OutputBuffer::Int64(_) => TensorElementDataType::Int64,
+            OutputBuffer::Double(_) => TensorElementDataType::Double,
+            OutputBuffer::Uint32(_) => TensorElementDataType::Uint32,
+            OutputBuffer::Uint64(_) => TensorElementDataType::Uint64,
+        }
+    }
+
+    /// Shape of the buffer
+    pub fn shape(&self) -> &[usize] {
+        map_output_buffer!(self, array => array.shape())
+    }
+
+    /// Copy the data of an output computed by the runtime into the buffer
+    ///
+    /// The output must have the same element type and shape as the buffer.
+    pub(crate) fn copy_from(&mut self, output: &DynOrtTensor<ndarray::IxDyn>) -> Result<()> {
+        map_output_buffer!(self, array => {
+            let output = output.try_extract()?;
+            array.assign(&*output);
+        });
+        Ok(())
+    }
+
+    /// Wrap the buffer in a runtime value, without copying it
+    pub(crate) fn into_ort_tensor(
+        mut self,
+        memory_info: &MemoryInfo,
+    ) -> Result<OrtBufferTensor<'a>> {
+        let data_type = self.data_type();
+        let c_ptr = map_output_buffer!(&mut self, array => {
+            let shape = array.shape().to_vec();
+            let data = array.as_slice_mut().ok_or(OrtError::NonContiguousArray)?;
+            unsafe {
+                create_tensor_with_data(
+                    memory_info,
+                    data_type,
+                    &shape,
+                    data.as_mut_ptr() as *mut std::ffi::c_void,
+                    std::mem::size_of_val(data),
+                )?
+            }
+        });
+
+        Ok(OrtBufferTensor {
+            c_ptr,
+            _buffer: self,
+        })
+    }
+}
+
+/// Create a runtime value pointing to `data`, contiguous elements of type `data_type` in
+/// standard layout
+///
+/// # Safety
+///
+/// `data` must point to `data_byte_size` bytes, and the returned value must not outlive them.
+pub(crate) unsafe fn create_tensor_with_data(
+    memory_info: &MemoryInfo,
+    data_type: TensorElementDataType,
+    shape: &[usize],
+    data: *mut std::ffi::c_void,
+    data_byte_size: usize,
+) -> Result<*mut sys::OrtValue> {
+    let shape: Vec<i64> = shape.iter().map(|d: &usize| *d as i64).collect();
+
+    let mut tensor_ptr: *mut sys::OrtValue = std::ptr::null_mut();
+    call_ort(|ort| {
+        ort.CreateTensorWithDataAsOrtValue.unwrap()(
+            memory_info.ptr,
+            data,
+            data_byte_size as u64,
+            shape.as_ptr(),
+            shape.len() as u64,
+            data_type.into(),
+            &mut tensor_ptr,
+        )
+    })
+    .map_err(OrtError::CreateTensorWithData)?;
+    assert_ne!(tensor_ptr, std::ptr::null_mut());
+
+    Ok(tensor_ptr)
+}
+
+/// Runtime value pointing to the data of an [`OutputBuffer`](enum.OutputBuffer.html)
+///
+/// The buffer is mutably borrowed for as long as the value exists.
+#[derive(Debug)]
+pub(crate) struct OrtBufferTensor<'a> {
+    pub(crate) c_ptr: *mut sys::OrtValue,
+    _buffer: OutputBuffer<'a>,
+}
+
+impl<'a> Drop for OrtBufferTensor<'a> {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        // We need to let the C part free
+        debug!("Dropping OrtBufferTensor.");
+        unsafe { g_ort().ReleaseValue.unwrap()(self.c_ptr) }
+
+        self.c_ptr = std::ptr::null_mut();
+    }
+}
+
+macro_rules! impl_from_array_view_mut {
+    ($type_:ty, $variant:ident) => {
+        impl<'a, D> From<ArrayViewMut<'a, $type_, D>> for OutputBuffer<'a>
+        where
+            D: ndarray::Dimension,
+        {
+            fn from(array: ArrayViewMut<'a, $type_, D>) -> Self {
+                OutputBuffer::$variant(array.into_dyn())
+            }
+        }
+    };
+}
+
+impl_from_array_view_mut!(f32, Float);
+impl_from_array_view_mut!(u8, Uint8);
+impl_from_array_view_mut!(i8, Int8);
+impl_from_array_view_mut!(u16, Uint16);
+impl_from_array_view_mut!(i16, Int16);
+impl_from_array_view_mut!(i32, Int32);
+impl_from_array_view_mut!(i64, Int64);
+impl_from_array_view_mut!(f64, Double);
+impl_from_array_view_mut!(u32, Uint32);
+impl_from_array_view_mut!(u64, Uint64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AllocatorType, MemType};
+    use ndarray::{s, Array2};
+    use test_env_log::test;
+
+    #[test]
+    fn output_buffer_into_ort_tensor() {
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        let mut array = Array2::<i64>::zeros((2, 3));
+
+        let buffer = OutputBuffer::from(array.view_mut());
+        assert_eq!(buffer.data_type(), TensorElementDataType::Int64);
+        assert_eq!(buffer.shape(), &[2, 3]);
+        let tensor = buffer.into_ort_tensor(&memory_info).unwrap();
+        assert!(!tensor.c_ptr.is_null());
+    }
+
+    #[test]
+    fn output_buffer_non_contiguous() {
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        let mut array = Array2::<f32>::zeros((4, 4));
+
+        let buffer = OutputBuffer::from(array.slice_mut(s![.., 1..3]));
+        assert!(matches!(
+            buffer.into_ort_tensor(&memory_info),
+            Err(OrtError::NonContiguousArray)
+        ));
+    }
+}
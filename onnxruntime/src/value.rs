@@ -0,0 +1,477 @@
+//! Module containing values which are not only tensors: sequences and maps.
+//!
+//! Besides tensors, ONNX models can take and return sequences (of tensors or of maps) and maps
+//! (from integer or string keys to tensor elements). For example, scikit-learn classifiers
+//! converted with `ZipMap` return their probabilities as a sequence of maps, one map from class
+//! label to probability per sample.
+//!
+//! Such models are run with [`Session::run_values()`](../session/struct.Session.html#method.run_values),
+//! taking [`InputValue`](enum.InputValue.html)s and returning [`DynOrtValue`](struct.DynOrtValue.html)s.
+//! The type of the model's inputs and outputs is described by their
+//! [`ValueType`](../session/enum.ValueType.html).
+
+use std::{collections::HashMap, fmt::Debug, hash::Hash};
+
+use ndarray::{Array, IxDyn};
+use tracing::debug;
+
+use onnxruntime_sys as sys;
+
+use crate::{
+    error::{call_ort, OrtError, Result},
+    g_ort,
+    memory::MemoryInfo,
+    session::ValueType,
+    tensor::{input_tensor::OrtInput, DynOrtTensor, InputTensor},
+    TensorElementDataType,
+};
+
+/// Kind of an ONNX value, as reported by the runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    /// Value of unknown kind
+    Unknown,
+    /// Tensor
+    Tensor,
+    /// Sequence of values
+    Sequence,
+    /// Map from keys to values
+    Map,
+    /// Opaque value, only known to some operators
+    Opaque,
+    /// Sparse tensor
+    SparseTensor,
+}
+
+impl From<sys::ONNXType> for ValueKind {
+    fn from(onnx_type: sys::ONNXType) -> Self {
+        match onnx_type {
+            sys::ONNXType::ONNX_TYPE_UNKNOWN => ValueKind::Unknown,
+            sys::ONNXType::ONNX_TYPE_TENSOR => ValueKind::Tensor,
+            sys::ONNXType::ONNX_TYPE_SEQUENCE => ValueKind::Sequence,
+            sys::ONNXType::ONNX_TYPE_MAP => ValueKind::Map,
+            sys::ONNXType::ONNX_TYPE_OPAQUE => ValueKind::Opaque,
+            sys::ONNXType::ONNX_TYPE_SPARSETENSOR => ValueKind::SparseTensor,
+        }
+    }
+}
+
+/// Input value of an inference call made with [`Session::run_values()`](../session/struct.Session.html#method.run_values)
+///
+/// Anything convertible into an [`InputTensor`](../tensor/enum.InputTensor.html), like an
+/// `ndarray::Array`, converts into an `InputValue::Tensor`.
+///
+/// # Example
+///
+/// ```
+/// # use onnxruntime::value::InputValue;
+/// let sequence = InputValue::Sequence(vec![
+///     ndarray::arr1(&[1.0_f32, 2.0]).into(),
+///     ndarray::arr1(&[3.0_f32]).into(),
+/// ]);
+/// let map = InputValue::Map {
+///     keys: ndarray::arr1(&[1_i64, 2]).into(),
+///     values: ndarray::arr1(&[0.25_f32, 0.75]).into(),
+/// };
+/// ```
+#[derive(Debug)]
+pub enum InputValue<'a> {
+    /// Tensor
+    Tensor(InputTensor<'a>),
+    /// Sequence of values, all of the same type
+    Sequence(Vec<InputValue<'a>>),
+    /// Map, given as one-dimensional tensors of its keys and of the matching values
+    ///
+    /// Keys must be `i64`s or `String`s.
+    Map {
+        /// Keys of the map
+        keys: InputTensor<'a>,
+        /// Values of the map, in the order of the keys
+        values: InputTensor<'a>,
+    },
+}
+
+impl<'a, T> From<T> for InputValue<'a>
+where
+    T: Into<InputTensor<'a>>,
+{
+    fn from(tensor: T) -> Self {
+        InputValue::Tensor(tensor.into())
+    }
+}
+
+impl<'a> InputValue<'a> {
+    /// Check if the value matches the type of a model's input
+    pub(crate) fn matches(&self, value_type: &ValueType) -> bool {
+        match (self, value_type) {
+            (
+                InputValue::Tensor(tensor),
+                ValueType::Tensor {
+                    element_type,
+                    dimensions,
+                },
+            ) => {
+                tensor.data_type() == *element_type
+                    && tensor.shape().len() == dimensions.len()
+                    && tensor
+                        .shape()
+                        .iter()
+                        .zip(dimensions)
+                        .all(|(size, dimension)| dimension.matches(*size))
+            }
+            (InputValue::Sequence(elements), ValueType::Sequence(element_type)) => {
+                elements.iter().all(|element| element.matches(element_type))
+            }
+            (
+                InputValue::Map { keys, values },
+                ValueType::Map {
+                    key_type,
+                    value_type,
+                },
+            ) => {
+                // The values of a map are given as a single tensor, not one tensor per value
+                keys.data_type() == *key_type
+                    && matches!(**value_type, ValueType::Tensor { element_type, .. } if values.data_type() == element_type)
+                    && keys.shape().len() == 1
+                    && keys.shape() == values.shape()
+            }
+            _ => false,
+        }
+    }
+
+    /// Provide the data to the runtime, returning a value usable as an inference input
+    ///
+    /// Tensors are given as for [`InputTensor`](../tensor/enum.InputTensor.html); the runtime
+    /// copies the elements of sequences and maps.
+    pub(crate) fn into_ort_value<'t>(
+        self,
+        memory_info: &'t MemoryInfo,
+        allocator_ptr: *mut sys::OrtAllocator,
+    ) -> Result<Box<dyn OrtInput + 't>>
+    where
+        'a: 't, // 'a outlives 't
+    {
+        let (elements, onnx_type) = match self {
+            InputValue::Tensor(tensor) => {
+                return tensor.into_ort_tensor(memory_info, allocator_ptr)
+            }
+            InputValue::Sequence(elements) => (
+                elements
+                    .into_iter()
+                    .map(|element| element.into_ort_value(memory_info, allocator_ptr))
+                    .collect::<Result<Vec<_>>>()?,
+                sys::ONNXType::ONNX_TYPE_SEQUENCE,
+            ),
+            InputValue::Map { keys, values } => (
+                vec![
+                    keys.into_ort_tensor(memory_info, allocator_ptr)?,
+                    values.into_ort_tensor(memory_info, allocator_ptr)?,
+                ],
+                sys::ONNXType::ONNX_TYPE_MAP,
+            ),
+        };
+
+        let element_ptrs: Vec<*const sys::OrtValue> =
+            elements.iter().map(|element| element.c_ptr()).collect();
+        let mut value_ptr: *mut sys::OrtValue = std::ptr::null_mut();
+        unsafe {
+            call_ort(|ort| {
+                ort.CreateValue.unwrap()(
+                    element_ptrs.as_ptr(),
+                    element_ptrs.len() as u64,
+                    onnx_type,
+                    &mut value_ptr,
+                )
+            })
+        }
+        .map_err(OrtError::CreateValue)?;
+        assert_ne!(value_ptr, std::ptr::null_mut());
+
+        Ok(Box::new(CompositeValue {
+            c_ptr: value_ptr,
+            _elements: elements,
+        }))
+    }
+}
+
+/// Sequence or map created by the runtime from the values of its elements
+#[derive(Debug)]
+struct CompositeValue<'t> {
+    c_ptr: *mut sys::OrtValue,
+    _elements: Vec<Box<dyn OrtInput + 't>>,
+}
+
+impl<'t> OrtInput for CompositeValue<'t> {
+    fn c_ptr(&self) -> *const sys::OrtValue {
+        self.c_ptr
+    }
+}
+
+impl<'t> Drop for CompositeValue<'t> {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping CompositeValue.");
+        unsafe { g_ort().ReleaseValue.unwrap()(self.c_ptr) }
+
+        self.c_ptr = std::ptr::null_mut();
+    }
+}
+
+/// Value owned by the ONNX Runtime C library, returned by [`Session::run_values()`](../session/struct.Session.html#method.run_values)
+///
+/// The value can be a tensor, a sequence or a map; its [`kind()`](#method.kind) is only known at
+/// runtime. Its content is copied out with the `try_` methods, which fail if the requested kind
+/// or element types do not match the value's.
+///
+/// When going out of scope, this value will free the required memory on the C side.
+#[derive(Debug)]
+pub struct DynOrtValue {
+    value_ptr: *mut sys::OrtValue,
+}
+
+// The value is exclusively owned, and the runtime's values are not tied to the thread that
+// created them.
+unsafe impl Send for DynOrtValue {}
+
+impl DynOrtValue {
+    pub(crate) fn new(value_ptr: *mut sys::OrtValue) -> DynOrtValue {
+        assert_ne!(value_ptr, std::ptr::null_mut());
+        DynOrtValue { value_ptr }
+    }
+
+    /// Kind of the value: tensor, sequence, map...
+    pub fn kind(&self) -> Result<ValueKind> {
+        let mut onnx_type = sys::ONNXType::ONNX_TYPE_UNKNOWN;
+        unsafe { call_ort(|ort| ort.GetValueType.unwrap()(self.value_ptr, &mut onnx_type)) }
+            .map_err(OrtError::GetValue)?;
+        Ok(onnx_type.into())
+    }
+
+    /// Convert the value into a tensor, without copy
+    pub fn try_into_tensor(self) -> Result<DynOrtTensor<'static, IxDyn>> {
+        self.expect_kind(ValueKind::Tensor)?;
+        self.into_tensor()
+    }
+
+    /// Convert a value known to be a tensor, possibly pointing to data borrowed for `'d`
+    ///
+    /// The value is released if it can't be converted.
+    pub(crate) fn into_tensor<'d>(self) -> Result<DynOrtTensor<'d, IxDyn>> {
+        let tensor = DynOrtTensor::from_ort_value(self.value_ptr)?;
+        // The tensor takes over the ownership of the value
+        std::mem::forget(self);
+        Ok(tensor)
+    }
+
+    /// Copy the tensors of a sequence
+    pub fn try_extract_sequence<T>(&self) -> Result<Vec<Array<T, IxDyn>>>
+    where
+        T: ValueElement,
+    {
+        self.expect_kind(ValueKind::Sequence)?;
+        (0..self.count()?)
+            .map(|index| T::extract_array(&self.element(index)?.try_into_tensor()?))
+            .collect()
+    }
+
+    /// Copy the entries of a map
+    pub fn try_extract_map<K, V>(&self) -> Result<HashMap<K, V>>
+    where
+        K: ValueElement + Eq + Hash,
+        V: ValueElement,
+    {
+        self.expect_kind(ValueKind::Map)?;
+        let keys = K::extract_array(&self.element(0)?.try_into_tensor()?)?;
+        let values = V::extract_array(&self.element(1)?.try_into_tensor()?)?;
+        // Both arrays are copied from the runtime's tensors, in standard layout
+        Ok(keys
+            .into_raw_vec()
+            .into_iter()
+            .zip(values.into_raw_vec())
+            .collect())
+    }
+
+    /// Copy the entries of the maps of a sequence, like the probabilities returned by a
+    /// scikit-learn classifier
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::error::Error;
+    /// # use onnxruntime::{environment::Environment, LoggingLevel};
+    /// # fn main() -> Result<(), Box<dyn Error>> {
+    /// # let environment = Environment::builder().with_log_level(LoggingLevel::Verbose).build()?;
+    /// # let session = environment.new_session_builder()?.with_model_from_file("classifier.onnx")?;
+    /// let samples = ndarray::Array::<f32, _>::zeros((4, 3));
+    /// let outputs = session.run_values(vec![samples])?;
+    /// let probabilities = outputs[1].try_extract_sequence_of_maps::<i64, f32>()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_extract_sequence_of_maps<K, V>(&self) -> Result<Vec<HashMap<K, V>>>
+    where
+        K: ValueElement + Eq + Hash,
+        V: ValueElement,
+    {
+        self.expect_kind(ValueKind::Sequence)?;
+        (0..self.count()?)
+            .map(|index| self.element(index)?.try_extract_map())
+            .collect()
+    }
+
+    fn expect_kind(&self, requested: ValueKind) -> Result<()> {
+        let kind = self.kind()?;
+        if kind != requested {
+            return Err(OrtError::NonMatchingValueKind { requested, kind });
+        }
+        Ok(())
+    }
+
+    /// Number of elements of a sequence or map
+    ///
+    /// Maps always have two elements, their keys and their values.
+    fn count(&self) -> Result<usize> {
+        let mut count = 0;
+        unsafe { call_ort(|ort| ort.GetValueCount.unwrap()(self.value_ptr, &mut count)) }
+            .map_err(OrtError::GetValue)?;
+        Ok(count as usize)
+    }
+
+    /// Element of a sequence, or the keys (at index 0) or values (at index 1) of a map
+    fn element(&self, index: usize) -> Result<DynOrtValue> {
+        let mut allocator_ptr: *mut sys::OrtAllocator = std::ptr::null_mut();
+        unsafe {
+            // this default non-arena allocator doesn't need to be deallocated
+            call_ort(|ort| ort.GetAllocatorWithDefaultOptions.unwrap()(&mut allocator_ptr))
+        }
+        .map_err(OrtError::Allocator)?;
+
+        let mut element_ptr: *mut sys::OrtValue = std::ptr::null_mut();
+        unsafe {
+            call_ort(|ort| {
+                ort.GetValue.unwrap()(
+                    self.value_ptr,
+                    index as i32,
+                    allocator_ptr,
+                    &mut element_ptr,
+                )
+            })
+        }
+        .map_err(OrtError::GetValue)?;
+        Ok(DynOrtValue::new(element_ptr))
+    }
+}
+
+impl Drop for DynOrtValue {
+    #[tracing::instrument]
+    fn drop(&mut self) {
+        debug!("Dropping DynOrtValue.");
+        unsafe { g_ort().ReleaseValue.unwrap()(self.value_ptr) }
+
+        self.value_ptr = std::ptr::null_mut();
+    }
+}
+
+/// Element types which can be copied out of the tensors of a [`DynOrtValue`](struct.DynOrtValue.html):
+/// numeric types and `String`
+pub trait ValueElement: Sized {
+    /// Copy the elements of a tensor
+    ///
+    /// Returns an [`OrtError::NonMatchingOutputDataType`](../error/enum.OrtError.html#variant.NonMatchingOutputDataType)
+    /// error if the type does not match the tensor's element type.
+    fn extract_array(tensor: &DynOrtTensor<IxDyn>) -> Result<Array<Self, IxDyn>>;
+}
+
+macro_rules! impl_value_element {
+    ($type_:ty) => {
+        impl ValueElement for $type_ {
+            fn extract_array(tensor: &DynOrtTensor<IxDyn>) -> Result<Array<Self, IxDyn>> {
+                Ok(tensor.try_extract::<$type_>()?.to_owned())
+            }
+        }
+    };
+}
+
+impl_value_element!(f32);
+impl_value_element!(u8);
+impl_value_element!(i8);
+impl_value_element!(u16);
+impl_value_element!(i16);
+impl_value_element!(i32);
+impl_value_element!(i64);
+impl_value_element!(f64);
+impl_value_element!(u32);
+impl_value_element!(u64);
+
+impl ValueElement for String {
+    fn extract_array(tensor: &DynOrtTensor<IxDyn>) -> Result<Array<Self, IxDyn>> {
+        if tensor.data_type() != TensorElementDataType::String {
+            return Err(OrtError::NonMatchingOutputDataType {
+                requested_type: TensorElementDataType::String,
+                output_type: tensor.data_type(),
+            });
+        }
+        let strings = tensor.extract_strings()?;
+        Array::from_shape_vec(tensor.shape(), strings).map_err(OrtError::InvalidShape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::Dimension;
+    use test_env_log::test;
+
+    fn float_tensor_type(dimensions: Vec<Dimension>) -> ValueType {
+        ValueType::Tensor {
+            element_type: TensorElementDataType::Float,
+            dimensions,
+        }
+    }
+
+    #[test]
+    fn input_value_matches_tensor() {
+        let value_type = float_tensor_type(vec![
+            Dimension::Symbolic("batch".to_string()),
+            Dimension::Fixed(2),
+        ]);
+        let value: InputValue = ndarray::arr2(&[[1.0_f32, 2.0]]).into();
+        assert!(value.matches(&value_type));
+
+        let value: InputValue = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]).into();
+        assert!(!value.matches(&value_type));
+        let value: InputValue = ndarray::arr2(&[[1_i64, 2]]).into();
+        assert!(!value.matches(&value_type));
+        let value = InputValue::Sequence(vec![ndarray::arr2(&[[1.0_f32, 2.0]]).into()]);
+        assert!(!value.matches(&value_type));
+    }
+
+    #[test]
+    fn input_value_matches_sequence_and_map() {
+        let value_type = ValueType::Sequence(Box::new(ValueType::Map {
+            key_type: TensorElementDataType::Int64,
+            value_type: Box::new(float_tensor_type(vec![])),
+        }));
+        let map = |keys: &[i64], values: &[f32]| InputValue::Map {
+            keys: ndarray::arr1(keys).into(),
+            values: ndarray::arr1(values).into(),
+        };
+
+        assert!(InputValue::Sequence(vec![]).matches(&value_type));
+        assert!(
+            InputValue::Sequence(vec![map(&[1, 2], &[0.5, 0.5]), map(&[3], &[1.0])])
+                .matches(&value_type)
+        );
+
+        // Different number of keys and values
+        assert!(!InputValue::Sequence(vec![map(&[1, 2], &[1.0])]).matches(&value_type));
+        // Map instead of a sequence
+        assert!(!map(&[1], &[1.0]).matches(&value_type));
+        // String keys instead of integers
+        let value = InputValue::Sequence(vec![InputValue::Map {
+            keys: ndarray::arr1(&["a"]).into(),
+            values: ndarray::arr1(&[1.0_f32]).into(),
+        }]);
+        assert!(!value.matches(&value_type));
+    }
+}
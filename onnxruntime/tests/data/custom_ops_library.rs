@@ -0,0 +1,241 @@
+//! Minimal custom operators library, compiled as a `cdylib` by the integration tests
+//!
+//! It registers the `Negate` operator (`y = -x`, on `float` tensors) in the
+//! `ai.onnxruntime-rs.library` domain, used by `custom_ops_library.onnx`. Compiling it with
+//! `--cfg missing_entry_point` leaves out the `RegisterCustomOps` function looked up by the runtime.
+//!
+//! The library is built without the `onnxruntime-sys` crate: the functions of the runtime's
+//! `OrtApi` are found by their position in the structure of the C API (version 6, ORT 1.6).
+
+#![allow(non_snake_case)]
+#![cfg_attr(missing_entry_point, allow(dead_code))]
+
+use std::{
+    ffi::c_void,
+    os::raw::{c_char, c_int},
+    ptr::null_mut,
+};
+
+type Status = *mut c_void;
+
+/// Version of the C API requested from the runtime
+const ORT_API_VERSION: u32 = 6;
+
+/// `ONNX_TENSOR_ELEMENT_DATA_TYPE_FLOAT`
+const ELEMENT_TYPE_FLOAT: c_int = 1;
+
+/// Positions of the used functions in `OrtApi`
+const CREATE_CUSTOM_OP_DOMAIN: usize = 26;
+const CUSTOM_OP_DOMAIN_ADD: usize = 27;
+const ADD_CUSTOM_OP_DOMAIN: usize = 28;
+const GET_TENSOR_MUTABLE_DATA: usize = 51;
+const GET_DIMENSIONS_COUNT: usize = 61;
+const GET_DIMENSIONS: usize = 62;
+const GET_TENSOR_SHAPE_ELEMENT_COUNT: usize = 64;
+const GET_TENSOR_TYPE_AND_SHAPE: usize = 65;
+const KERNEL_CONTEXT_GET_INPUT: usize = 89;
+const KERNEL_CONTEXT_GET_OUTPUT: usize = 90;
+const RELEASE_STATUS: usize = 92;
+const RELEASE_TENSOR_TYPE_AND_SHAPE_INFO: usize = 98;
+
+#[repr(C)]
+pub struct OrtApiBase {
+    GetApi: unsafe extern "C" fn(version: u32) -> *const c_void,
+    GetVersionString: unsafe extern "C" fn() -> *const c_char,
+}
+
+#[repr(C)]
+pub struct OrtCustomOp {
+    version: u32,
+    CreateKernel:
+        unsafe extern "C" fn(*const OrtCustomOp, *const c_void, *const c_void) -> *mut c_void,
+    GetName: unsafe extern "C" fn(*const OrtCustomOp) -> *const c_char,
+    GetExecutionProviderType: unsafe extern "C" fn(*const OrtCustomOp) -> *const c_char,
+    GetInputType: unsafe extern "C" fn(*const OrtCustomOp, usize) -> c_int,
+    GetInputTypeCount: unsafe extern "C" fn(*const OrtCustomOp) -> usize,
+    GetOutputType: unsafe extern "C" fn(*const OrtCustomOp, usize) -> c_int,
+    GetOutputTypeCount: unsafe extern "C" fn(*const OrtCustomOp) -> usize,
+    KernelCompute: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    KernelDestroy: unsafe extern "C" fn(*mut c_void),
+}
+
+static NEGATE: OrtCustomOp = OrtCustomOp {
+    version: ORT_API_VERSION,
+    CreateKernel: create_kernel,
+    GetName: get_name,
+    GetExecutionProviderType: get_execution_provider_type,
+    GetInputType: get_type,
+    GetInputTypeCount: get_type_count,
+    GetOutputType: get_type,
+    GetOutputTypeCount: get_type_count,
+    KernelCompute: kernel_compute,
+    KernelDestroy: kernel_destroy,
+};
+
+/// Function of the runtime's `OrtApi` at `index`
+unsafe fn api_function<F: Copy>(api: *const c_void, index: usize) -> F {
+    *(api as *const F).add(index)
+}
+
+/// Release `status`, returning if it reported a success
+unsafe fn succeeded(api: *const c_void, status: Status) -> bool {
+    if status.is_null() {
+        return true;
+    }
+    api_function::<unsafe extern "C" fn(Status)>(api, RELEASE_STATUS)(status);
+    false
+}
+
+/// Entry point called by the runtime with the session options and its API base, returning a
+/// null status on success
+#[cfg(not(missing_entry_point))]
+#[no_mangle]
+pub unsafe extern "C" fn RegisterCustomOps(
+    options: *mut c_void,
+    api_base: *const OrtApiBase,
+) -> Status {
+    let api = ((*api_base).GetApi)(ORT_API_VERSION);
+
+    // The domain is never released: it must outlive every session using it
+    let mut domain: *mut c_void = null_mut();
+    let status = api_function::<unsafe extern "C" fn(*const c_char, *mut *mut c_void) -> Status>(
+        api,
+        CREATE_CUSTOM_OP_DOMAIN,
+    )(
+        b"ai.onnxruntime-rs.library\0".as_ptr() as *const c_char,
+        &mut domain,
+    );
+    if !status.is_null() {
+        return status;
+    }
+    let status = api_function::<unsafe extern "C" fn(*mut c_void, *const OrtCustomOp) -> Status>(
+        api,
+        CUSTOM_OP_DOMAIN_ADD,
+    )(domain, &NEGATE);
+    if !status.is_null() {
+        return status;
+    }
+    api_function::<unsafe extern "C" fn(*mut c_void, *mut c_void) -> Status>(
+        api,
+        ADD_CUSTOM_OP_DOMAIN,
+    )(options, domain)
+}
+
+/// Keeps the library non-empty when the entry point is left out
+#[cfg(missing_entry_point)]
+#[no_mangle]
+pub extern "C" fn NotRegisterCustomOps() -> *mut c_void {
+    null_mut()
+}
+
+/// The kernel only needs the runtime's API
+unsafe extern "C" fn create_kernel(
+    _op: *const OrtCustomOp,
+    api: *const c_void,
+    _info: *const c_void,
+) -> *mut c_void {
+    api as *mut c_void
+}
+
+unsafe extern "C" fn get_name(_op: *const OrtCustomOp) -> *const c_char {
+    b"Negate\0".as_ptr() as *const c_char
+}
+
+unsafe extern "C" fn get_execution_provider_type(_op: *const OrtCustomOp) -> *const c_char {
+    null_mut()
+}
+
+unsafe extern "C" fn get_type(_op: *const OrtCustomOp, _index: usize) -> c_int {
+    ELEMENT_TYPE_FLOAT
+}
+
+unsafe extern "C" fn get_type_count(_op: *const OrtCustomOp) -> usize {
+    1
+}
+
+unsafe extern "C" fn kernel_compute(op_kernel: *mut c_void, context: *mut c_void) {
+    let api = op_kernel as *const c_void;
+
+    let mut input: *const c_void = null_mut();
+    if !succeeded(
+        api,
+        api_function::<unsafe extern "C" fn(*mut c_void, usize, *mut *const c_void) -> Status>(
+            api,
+            KERNEL_CONTEXT_GET_INPUT,
+        )(context, 0, &mut input),
+    ) {
+        return;
+    }
+
+    let mut info: *mut c_void = null_mut();
+    if !succeeded(
+        api,
+        api_function::<unsafe extern "C" fn(*const c_void, *mut *mut c_void) -> Status>(
+            api,
+            GET_TENSOR_TYPE_AND_SHAPE,
+        )(input, &mut info),
+    ) {
+        return;
+    }
+    let mut dimensions_count = 0;
+    let mut element_count = 0;
+    let mut dimensions = Vec::new();
+    let shape_read = succeeded(
+        api,
+        api_function::<unsafe extern "C" fn(*const c_void, *mut usize) -> Status>(
+            api,
+            GET_DIMENSIONS_COUNT,
+        )(info, &mut dimensions_count),
+    ) && {
+        dimensions.resize(dimensions_count, 0_i64);
+        succeeded(
+            api,
+            api_function::<unsafe extern "C" fn(*const c_void, *mut i64, usize) -> Status>(
+                api,
+                GET_DIMENSIONS,
+            )(info, dimensions.as_mut_ptr(), dimensions_count),
+        )
+    } && succeeded(
+        api,
+        api_function::<unsafe extern "C" fn(*const c_void, *mut usize) -> Status>(
+            api,
+            GET_TENSOR_SHAPE_ELEMENT_COUNT,
+        )(info, &mut element_count),
+    );
+    api_function::<unsafe extern "C" fn(*mut c_void)>(api, RELEASE_TENSOR_TYPE_AND_SHAPE_INFO)(
+        info,
+    );
+    if !shape_read {
+        return;
+    }
+
+    let mut output: *mut c_void = null_mut();
+    let mut input_data: *mut f32 = null_mut();
+    let mut output_data: *mut f32 = null_mut();
+    let get_data = api_function::<unsafe extern "C" fn(*const c_void, *mut *mut f32) -> Status>(
+        api,
+        GET_TENSOR_MUTABLE_DATA,
+    );
+    if !succeeded(
+        api,
+        api_function::<
+            unsafe extern "C" fn(*mut c_void, usize, *const i64, usize, *mut *mut c_void) -> Status,
+        >(api, KERNEL_CONTEXT_GET_OUTPUT)(
+            context,
+            0,
+            dimensions.as_ptr(),
+            dimensions_count,
+            &mut output,
+        ),
+    ) || !succeeded(api, get_data(input, &mut input_data))
+        || !succeeded(api, get_data(output, &mut output_data))
+    {
+        return;
+    }
+
+    for i in 0..element_count {
+        *output_data.add(i) = -*input_data.add(i);
+    }
+}
+
+unsafe extern "C" fn kernel_destroy(_op_kernel: *mut c_void) {}
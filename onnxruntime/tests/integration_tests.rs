@@ -1,7 +1,9 @@
 use std::{
+    collections::HashMap,
     fs,
     io::{self, BufRead, BufReader},
-    path::Path,
+    path::{Path, PathBuf},
+    sync::Arc,
     time::Duration,
 };
 
@@ -13,11 +15,43 @@ mod download {
     use test_env_log::test;
 
     use onnxruntime::{
+        batcher::Batcher,
+        custom_op::{
+            CustomOp, CustomOpDomain, CustomOpError, CustomOpKernel, KernelContext, KernelInfo,
+        },
         download::vision::{DomainBasedImageClassification, ImageClassification},
         environment::Environment,
-        GraphOptimizationLevel, LoggingLevel,
+        error::NonMatchingDimensionsError,
+        execution_provider::{
+            CudaProviderOptions, ExecutionProvider, OpenVinoProviderOptions, ProviderFallback,
+        },
+        memory::MemoryInfo,
+        run_options::RunOptions,
+        session::{Dimension, Session, SessionConfigEntry, ValueType},
+        session_pool::SessionPool,
+        tensor::{InputTensor, OutputBuffer},
+        value::{InputValue, ValueKind},
+        AllocatorType, ExecutionMode, GraphOptimizationLevel, LoggingLevel, MemType, OrtError,
+        TensorElementDataType,
     };
 
+    /// Environment shared by the tests, logging the runtime's warnings
+    fn test_environment() -> Environment {
+        Environment::builder()
+            .with_name("integration_test")
+            .with_log_level(LoggingLevel::Warning)
+            .build()
+            .unwrap()
+    }
+
+    /// Path of a model stored in `tests/data`
+    fn model_path(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("data")
+            .join(name)
+    }
+
     #[test]
     fn squeezenet_mushroom() {
         const IMAGE_TO_LOAD: &str = "mushroom.png";
@@ -28,7 +62,7 @@ mod download {
             .build()
             .unwrap();
 
-        let mut session = environment
+        let session = environment
             .new_session_builder()
             .unwrap()
             .with_optimization_level(GraphOptimizationLevel::Basic)
@@ -90,16 +124,17 @@ mod download {
         }
 
         // Batch of 1
-        let input_tensor_values = vec![array];
+        let input_tensor_values = vec![array.view()];
 
         // Perform the inference
-        let outputs: Vec<
-            onnxruntime::tensor::OrtOwnedTensor<f32, ndarray::Dim<ndarray::IxDynImpl>>,
-        > = session.run(input_tensor_values).unwrap();
+        let outputs: Vec<onnxruntime::tensor::DynOrtTensor<ndarray::Dim<ndarray::IxDynImpl>>> =
+            session.run(input_tensor_values).unwrap();
+        let output: onnxruntime::tensor::OrtOwnedTensor<f32, _> = outputs[0].try_extract().unwrap();
+        let expected_output = output.to_owned();
 
         // Downloaded model does not have a softmax as final layer; call softmax on second axis
         // and iterate on resulting probabilities, creating an index to later access labels.
-        let mut probabilities: Vec<(usize, f32)> = outputs[0]
+        let mut probabilities: Vec<(usize, f32)> = output
             .softmax(ndarray::Axis(1))
             .into_iter()
             .copied()
@@ -120,6 +155,15 @@ mod download {
             IMAGE_TO_LOAD
         );
 
+        // The output has a fixed shape: the runtime writes it directly into a caller-owned buffer
+        drop(output);
+        drop(outputs);
+        let mut output_buffer = ndarray::Array2::<f32>::zeros((1, 1000));
+        session
+            .run_into(vec![array.view()], vec![output_buffer.view_mut()])
+            .unwrap();
+        assert_eq!(output_buffer.into_dyn(), expected_output);
+
         // for i in 0..5 {
         //     println!(
         //         "class={} ({}); probability={}",
@@ -138,7 +182,7 @@ mod download {
             .build()
             .unwrap();
 
-        let mut session = environment
+        let session = environment
             .new_session_builder()
             .unwrap()
             .with_optimization_level(GraphOptimizationLevel::Basic)
@@ -184,11 +228,11 @@ mod download {
         let input_tensor_values = vec![array];
 
         // Perform the inference
-        let outputs: Vec<
-            onnxruntime::tensor::OrtOwnedTensor<f32, ndarray::Dim<ndarray::IxDynImpl>>,
-        > = session.run(input_tensor_values).unwrap();
+        let outputs: Vec<onnxruntime::tensor::DynOrtTensor<ndarray::Dim<ndarray::IxDynImpl>>> =
+            session.run(input_tensor_values).unwrap();
+        let output: onnxruntime::tensor::OrtOwnedTensor<f32, _> = outputs[0].try_extract().unwrap();
 
-        let mut probabilities: Vec<(usize, f32)> = outputs[0]
+        let mut probabilities: Vec<(usize, f32)> = output
             .softmax(ndarray::Axis(1))
             .into_iter()
             .copied()
@@ -236,7 +280,7 @@ mod download {
             .build()
             .unwrap();
 
-        let mut session = environment
+        let session = environment
             .new_session_builder()
             .unwrap()
             .with_optimization_level(GraphOptimizationLevel::Basic)
@@ -282,16 +326,1404 @@ mod download {
         let input_tensor_values = vec![array];
 
         // Perform the inference
-        let outputs: Vec<
-            onnxruntime::tensor::OrtOwnedTensor<f32, ndarray::Dim<ndarray::IxDynImpl>>,
-        > = session.run(input_tensor_values).unwrap();
+        let outputs: Vec<onnxruntime::tensor::DynOrtTensor<ndarray::Dim<ndarray::IxDynImpl>>> =
+            session.run(input_tensor_values).unwrap();
 
         assert_eq!(outputs.len(), 1);
-        let output = &outputs[0];
+        let output: onnxruntime::tensor::OrtOwnedTensor<f32, _> = outputs[0].try_extract().unwrap();
 
         // The image should have doubled in size
         assert_eq!(output.shape(), [1, 448, 448, 3]);
     }
+
+    #[test]
+    fn upsample_concurrent_runs() {
+        let environment = test_environment();
+
+        let session = Arc::new(
+            environment
+                .new_owned_session_builder()
+                .unwrap()
+                .with_model_from_file(model_path("upsample.onnx"))
+                .expect("Could not open model from file"),
+        );
+
+        let threads: Vec<_> = (0..4)
+            .map(|thread_index| {
+                let session = Arc::clone(&session);
+                std::thread::spawn(move || {
+                    for run_index in 0..5 {
+                        let value = (thread_index * 10 + run_index) as f32;
+                        let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), value);
+                        let outputs = session.run(vec![array]).unwrap();
+                        let output = outputs[0].try_extract::<f32>().unwrap();
+                        assert_eq!(output.shape(), [1, 20, 40, 3]);
+                        assert!(output.iter().all(|&x| x == value));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn upsample_owned_session() {
+        struct AppState {
+            session: Session<'static>,
+        }
+
+        let environment = test_environment();
+        let state = AppState {
+            session: environment
+                .new_owned_session_builder()
+                .unwrap()
+                .with_model_from_file(model_path("upsample.onnx"))
+                .expect("Could not open model from file"),
+        };
+        // The session holds its own clone of the environment
+        std::mem::drop(environment);
+
+        let outputs = std::thread::spawn(move || {
+            let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 1.5);
+            state.session.run(vec![array]).unwrap()
+        })
+        .join()
+        .unwrap();
+
+        // The outputs outlive the session, which was dropped with the thread
+        let output = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(output.shape(), [1, 20, 40, 3]);
+        assert!(output.iter().all(|&x| x == 1.5));
+    }
+
+    #[test]
+    fn upsample_session_options() {
+        let environment = test_environment();
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_execution_mode(ExecutionMode::Parallel)
+            .unwrap()
+            .with_inter_op_number_threads(2)
+            .unwrap()
+            .with_memory_pattern(false)
+            .unwrap()
+            .with_cpu_memory_arena(false)
+            .unwrap()
+            .with_allocator(AllocatorType::Device)
+            .unwrap()
+            .with_memory_type(MemType::Default)
+            .unwrap()
+            .with_config_entry(SessionConfigEntry::DisablePrepacking(true))
+            .unwrap()
+            .with_config_entry(SessionConfigEntry::SetDenormalAsZero(true))
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        let memory_info = session.memory_info();
+        assert_eq!(memory_info.allocator_type().unwrap(), AllocatorType::Device);
+        assert_eq!(memory_info.memory_type().unwrap(), MemType::Default);
+
+        let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 1.5);
+        let outputs = session.run(vec![array]).unwrap();
+        let output = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(output.shape(), [1, 20, 40, 3]);
+        assert!(output.iter().all(|&x| x == 1.5));
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn upsample_profiling() {
+        use onnxruntime::profiling::Profile;
+
+        let environment = test_environment();
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_profiling(std::env::temp_dir().join("onnxruntime-rs-upsample"))
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        for _ in 0..3 {
+            let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 1.5);
+            session.run(vec![array]).unwrap();
+        }
+
+        let profile_file = session.end_profiling().unwrap();
+        let profile = Profile::from_file(&profile_file).unwrap();
+        fs::remove_file(&profile_file).unwrap();
+
+        assert!(profile
+            .events
+            .iter()
+            .any(|event| event.category == "Session" && event.name == "model_run"));
+        let nodes = profile.node_summaries();
+        assert!(!nodes.is_empty());
+        assert!(nodes.iter().all(|node| node.calls == 3));
+        let op_types = profile.op_type_summaries();
+        assert_eq!(
+            op_types.iter().map(|op_type| op_type.nodes).sum::<usize>(),
+            nodes.len()
+        );
+    }
+
+    #[test]
+    fn upsample_profiling_not_enabled() {
+        let environment = test_environment();
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        assert!(matches!(
+            session.end_profiling(),
+            Err(OrtError::ProfilingNotEnabled)
+        ));
+    }
+
+    #[test]
+    fn upsample_optimized_model() {
+        let environment = test_environment();
+        let model_dir = std::env::temp_dir().join("onnxruntime-rs-optimized-model");
+        fs::create_dir_all(&model_dir).unwrap();
+        let model_filepath = model_dir.join("upsample.onnx");
+        fs::copy(model_path("upsample.onnx"), &model_filepath).unwrap();
+
+        let optimized_model_filepath = environment
+            .optimize_model(&model_filepath, GraphOptimizationLevel::Basic)
+            .unwrap();
+        assert_eq!(
+            optimized_model_filepath,
+            model_dir.join("upsample.basic.onnx")
+        );
+
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_optimization_level(GraphOptimizationLevel::DisableAll)
+            .unwrap()
+            .with_model_from_file(&optimized_model_filepath)
+            .expect("Could not open optimized model from file");
+
+        let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 1.5);
+        let outputs = session.run(vec![array]).unwrap();
+        let output = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(output.shape(), [1, 20, 40, 3]);
+        assert!(output.iter().all(|&x| x == 1.5));
+
+        std::mem::drop(outputs);
+        std::mem::drop(session);
+        fs::remove_dir_all(&model_dir).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn upsample_async() {
+        use std::{
+            future::Future,
+            sync::{mpsc, Mutex},
+            task::{Context, Poll, Wake, Waker},
+        };
+
+        struct ChannelWaker(Mutex<mpsc::Sender<()>>);
+
+        impl Wake for ChannelWaker {
+            fn wake(self: Arc<Self>) {
+                let _ = self.0.lock().unwrap().send(());
+            }
+        }
+
+        let environment = test_environment();
+        let session = Arc::new(
+            environment
+                .new_owned_session_builder()
+                .unwrap()
+                .with_model_from_file(model_path("upsample.onnx"))
+                .expect("Could not open model from file"),
+        );
+
+        let (wake_tx, wake_rx) = mpsc::channel();
+        let waker = Waker::from(Arc::new(ChannelWaker(Mutex::new(wake_tx))));
+        let mut cx = Context::from_waker(&waker);
+
+        let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 2.5);
+        let mut future = Box::pin(session.run_async(vec![array]));
+        let outputs = loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(outputs) => break outputs.unwrap(),
+                Poll::Pending => wake_rx.recv().unwrap(),
+            }
+        };
+        let output = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(output.shape(), [1, 20, 40, 3]);
+        assert!(output.iter().all(|&x| x == 2.5));
+    }
+
+    #[test]
+    fn upsample_batcher() {
+        let environment = test_environment();
+        let session = environment
+            .new_owned_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+        let batcher = Arc::new(
+            Batcher::builder(Arc::new(session))
+                .with_max_batch_size(4)
+                .with_max_latency(Duration::from_millis(100))
+                .build()
+                .unwrap(),
+        );
+
+        // Requests of different sizes can't be batched together, but are still answered
+        let threads: Vec<_> = (0..6)
+            .map(|index| {
+                let batcher = Arc::clone(&batcher);
+                std::thread::spawn(move || {
+                    let (height, width) = if index % 2 == 0 { (10, 20) } else { (5, 5) };
+                    let array =
+                        ndarray::Array::<f32, _>::from_elem((1, height, width, 3), index as f32);
+                    let outputs = batcher.run(vec![array]).unwrap();
+                    let output = outputs[0].try_extract::<f32>().unwrap();
+                    assert_eq!(output.shape(), [1, height * 2, width * 2, 3]);
+                    assert!(output.iter().all(|&x| x == index as f32));
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        let two_samples = ndarray::Array::<f32, _>::zeros((2, 10, 20, 3));
+        let outputs = batcher.run(vec![two_samples]).unwrap();
+        assert_eq!(outputs[0].shape(), [2, 20, 40, 3]);
+    }
+
+    #[test]
+    fn upsample_session_pool() {
+        let environment = test_environment();
+        let pool = Arc::new(
+            SessionPool::builder(&environment)
+                .with_size(2)
+                .with_session_configuration(|builder| {
+                    builder
+                        .with_number_threads(1)?
+                        .with_optimization_level(GraphOptimizationLevel::Basic)
+                })
+                .with_model_from_file(model_path("upsample.onnx"))
+                .expect("Could not open model from file"),
+        );
+        assert_eq!(pool.size(), 2);
+        assert!(!std::ptr::eq(pool.round_robin(), pool.round_robin()));
+
+        let first = pool.checkout();
+        let second = pool.try_checkout().unwrap();
+        assert!(pool.try_checkout().is_none());
+        assert_eq!(pool.stats().busy, 2);
+
+        let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 1.0);
+        let outputs = first.run(vec![array]).unwrap();
+        assert_eq!(outputs[0].shape(), [1, 20, 40, 3]);
+
+        // Wait for a session to be returned
+        let waiting = {
+            let pool = Arc::clone(&pool);
+            std::thread::spawn(move || {
+                let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 2.0);
+                pool.run(vec![array]).unwrap()
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        std::mem::drop(first);
+        let outputs = waiting.join().unwrap();
+        assert!(outputs[0]
+            .try_extract::<f32>()
+            .unwrap()
+            .iter()
+            .all(|&x| x == 2.0));
+        std::mem::drop(second);
+
+        let stats = pool.stats();
+        assert_eq!(stats.size, 2);
+        assert_eq!(stats.busy, 0);
+        assert_eq!(stats.checkouts, 3);
+        assert!(stats.max_wait >= Duration::from_millis(50));
+        assert!(stats.mean_wait() <= stats.max_wait);
+    }
+
+    #[test]
+    fn scale_metadata() {
+        let environment = test_environment();
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("scale.onnx"))
+            .expect("Could not open model from file");
+
+        let metadata = session.metadata().unwrap();
+        assert_eq!(metadata.producer_name, "onnxruntime-rs");
+        assert_eq!(metadata.graph_name, "scale");
+        assert_eq!(metadata.domain, "ai.onnxruntime-rs.test");
+        assert_eq!(metadata.description, "Test model scaling its input");
+        assert_eq!(metadata.version, 3);
+        assert_eq!(metadata.custom.len(), 2);
+        assert_eq!(metadata.custom["author"], "onnxruntime-rs");
+        assert_eq!(metadata.custom["license"], "MIT OR Apache-2.0");
+
+        // A model without metadata properties
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("add.onnx"))
+            .expect("Could not open model from file");
+
+        let metadata = session.metadata().unwrap();
+        assert_eq!(metadata.producer_name, "onnxruntime-rs");
+        assert_eq!(metadata.graph_name, "add");
+        assert_eq!(metadata.domain, "");
+        assert_eq!(metadata.description, "");
+        assert_eq!(metadata.version, 0);
+        assert!(metadata.custom.is_empty());
+    }
+
+    #[test]
+    fn scale_overridable_initializers() {
+        let environment = test_environment();
+        // Multiplies its input `x` by the initializer `scale`, stored as 2.0
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("scale.onnx"))
+            .expect("Could not open model from file");
+
+        assert_eq!(session.inputs.len(), 1);
+        assert_eq!(session.overridable_initializers.len(), 1);
+        let initializer = &session.overridable_initializers[0];
+        assert_eq!(initializer.name, "scale");
+        assert_eq!(initializer.initializer_type, TensorElementDataType::Float);
+        assert!(initializer.dimensions.is_empty());
+
+        let x = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        let outputs = session.run(vec![x.clone()]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*y, ndarray::arr2(&[[2.0_f32, 4.0, 6.0]]).into_dyn().view());
+
+        let outputs = session
+            .run_with_initializers(vec![x.view()], vec![("scale", ndarray::arr0(0.5_f32))])
+            .unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*y, ndarray::arr2(&[[0.5_f32, 1.0, 1.5]]).into_dyn().view());
+
+        let mut inputs: HashMap<&str, InputTensor> = HashMap::new();
+        inputs.insert("x", x.into());
+        inputs.insert("scale", ndarray::arr0(3.0_f32).into());
+        let outputs = session.run_with(inputs, &["y"]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*y, ndarray::arr2(&[[3.0_f32, 6.0, 9.0]]).into_dyn().view());
+
+        assert!(matches!(
+            session.run_with_initializers(
+                vec![ndarray::arr2(&[[1.0_f32, 2.0, 3.0]])],
+                vec![("bias", ndarray::arr0(1.0_f32))]
+            ),
+            Err(OrtError::UnknownInitializerName { name }) if name == "bias"
+        ));
+    }
+
+    #[test]
+    fn add_symbolic_dimensions() {
+        let environment = test_environment();
+        // Adds its inputs `a` and `b`, both of shape `[batch, seq_len]`
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("add.onnx"))
+            .expect("Could not open model from file");
+
+        for input in &session.inputs {
+            assert_eq!(
+                input.dimensions,
+                vec![
+                    Dimension::Symbolic("batch".to_string()),
+                    Dimension::Symbolic("seq_len".to_string())
+                ]
+            );
+            assert_eq!(input.dimensions().collect::<Vec<_>>(), vec![None, None]);
+        }
+
+        let a = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        let b = ndarray::arr2(&[[4.0_f32, 5.0, 6.0]]);
+        let outputs = session.run(vec![a.view(), b.view()]).unwrap();
+        let c = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*c, ndarray::arr2(&[[5.0_f32, 7.0, 9.0]]).into_dyn().view());
+
+        let b = ndarray::arr2(&[[4.0_f32, 5.0]]);
+        let result = session.run(vec![a.view(), b.view()]);
+        match result {
+            Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::SymbolicDimension {
+                    dimension,
+                    name,
+                    size,
+                    previous_name,
+                    previous_size,
+                },
+            )) => {
+                assert_eq!(dimension, "seq_len");
+                assert_eq!((name.as_str(), size), ("b", 2));
+                assert_eq!((previous_name.as_str(), previous_size), ("a", 3));
+            }
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        let mut inputs: HashMap<&str, InputTensor> = HashMap::new();
+        inputs.insert("a", a.into());
+        inputs.insert("b", ndarray::arr2(&[[4.0_f32], [5.0]]).into());
+        assert!(matches!(
+            session.run_with(inputs, &["c"]),
+            Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::SymbolicDimension { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn scale_free_dimension_override() {
+        let environment = test_environment();
+        // The input `x` has a shape of `[batch, 3]`
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_free_dimension_override_by_name("batch", 2)
+            .unwrap()
+            .with_model_from_file(model_path("scale.onnx"))
+            .expect("Could not open model from file");
+
+        assert_eq!(
+            session.inputs[0].dimensions,
+            vec![Dimension::Fixed(2), Dimension::Fixed(3)]
+        );
+
+        let x = ndarray::arr2(&[[1.0_f32, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let outputs = session.run(vec![x]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(y.shape(), &[2, 3]);
+
+        // The overridden dimension is no longer free
+        let x = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        match session.run(vec![x]) {
+            Err(OrtError::NonMatchingDimensions(NonMatchingDimensionsError::InputShape {
+                name,
+                inference_input,
+                model_input,
+            })) => {
+                assert_eq!(name, "x");
+                assert_eq!(inference_input, vec![1, 3]);
+                assert_eq!(model_input, vec![Dimension::Fixed(2), Dimension::Fixed(3)]);
+            }
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        }
+
+        let x = ndarray::arr1(&[1.0_f32, 2.0, 3.0]);
+        assert!(matches!(
+            session.run(vec![x]),
+            Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::InputShape { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn scale_with_initializer() {
+        let environment = test_environment();
+        let model_bytes = std::fs::read(model_path("scale.onnx")).unwrap();
+
+        // The initializer's data is borrowed by the session
+        let scale = ndarray::arr0(4.0_f32);
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_initializer("scale", scale.view())
+            .unwrap()
+            .with_model_from_memory(&model_bytes)
+            .expect("Could not open model from memory");
+
+        let x = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        let outputs = session.run(vec![x]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*y, ndarray::arr2(&[[4.0_f32, 8.0, 12.0]]).into_dyn().view());
+
+        std::mem::drop(session);
+
+        // An owned initializer is kept alive by the session
+        let session = environment
+            .new_owned_session_builder()
+            .unwrap()
+            .with_initializer("scale", ndarray::arr0(-1.0_f32))
+            .unwrap()
+            .with_model_from_memory(&model_bytes)
+            .expect("Could not open model from memory");
+        std::mem::drop(environment);
+
+        let x = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        let outputs = session.run(vec![x]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(
+            *y,
+            ndarray::arr2(&[[-1.0_f32, -2.0, -3.0]]).into_dyn().view()
+        );
+    }
+
+    #[test]
+    fn scale_stripped_with_initializer() {
+        let environment = test_environment();
+        // The data of the model's `scale` initializer is stored in a file which is not provided
+        let model_bytes = std::fs::read(model_path("scale_stripped.onnx")).unwrap();
+
+        let scale = ndarray::arr0(3.0_f32);
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_initializer("scale", scale.view())
+            .unwrap()
+            .with_model_from_memory(&model_bytes)
+            .expect("Could not open model from memory");
+        assert!(session.overridable_initializers.is_empty());
+
+        let x = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        let outputs = session.run(vec![x]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*y, ndarray::arr2(&[[3.0_f32, 6.0, 9.0]]).into_dyn().view());
+    }
+
+    #[test]
+    fn classifier_sequence_of_maps() {
+        let environment = test_environment();
+        // Returns the label of its inputs `x` of shape `[batch, 2]` and their probabilities per
+        // label, as a sequence of maps
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("classifier.onnx"))
+            .expect("Could not open model from file");
+
+        assert_eq!(
+            session.outputs[1].value_type,
+            ValueType::Sequence(Box::new(ValueType::Map {
+                key_type: TensorElementDataType::Int64,
+                value_type: Box::new(ValueType::Tensor {
+                    element_type: TensorElementDataType::Float,
+                    dimensions: vec![],
+                }),
+            }))
+        );
+        assert_eq!(session.outputs[1].output_type, TensorElementDataType::Float);
+        assert!(session.outputs[1].dimensions.is_empty());
+
+        let x = ndarray::arr2(&[[1.0_f32, 1.0], [0.0, 2.0_f32.ln()]]);
+        assert!(matches!(
+            session.run(vec![x.view()]),
+            Err(OrtError::NonTensorValue { name, .. }) if name == "probabilities"
+        ));
+        // Only tensor outputs can be bound
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        assert!(matches!(
+            session
+                .io_binding()
+                .unwrap()
+                .bind_output_to_device("probabilities", &memory_info),
+            Err(OrtError::NonTensorValue { name, .. }) if name == "probabilities"
+        ));
+
+        let mut outputs = session.run_values(vec![x.view()]).unwrap();
+        assert_eq!(outputs[0].kind().unwrap(), ValueKind::Tensor);
+        assert_eq!(outputs[1].kind().unwrap(), ValueKind::Sequence);
+        assert!(matches!(
+            outputs[1].try_extract_map::<i64, f32>(),
+            Err(OrtError::NonMatchingValueKind { .. })
+        ));
+
+        let probabilities = outputs[1]
+            .try_extract_sequence_of_maps::<i64, f32>()
+            .unwrap();
+        assert_eq!(probabilities.len(), 2);
+        assert!((probabilities[0][&10] - 0.5).abs() < 1e-6);
+        assert!((probabilities[0][&20] - 0.5).abs() < 1e-6);
+        assert!((probabilities[1][&10] - 1.0 / 3.0).abs() < 1e-6);
+        assert!((probabilities[1][&20] - 2.0 / 3.0).abs() < 1e-6);
+
+        let label = outputs.remove(0).try_into_tensor().unwrap();
+        assert_eq!(label.try_extract::<i64>().unwrap().shape(), &[2]);
+    }
+
+    #[test]
+    fn sequence_and_map_inputs() {
+        let environment = test_environment();
+        // Appends `tensor` to the sequence `tensors` and vectorizes the map `features` over the
+        // keys 1, 2 and 3
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("sequence.onnx"))
+            .expect("Could not open model from file");
+
+        assert!(matches!(
+            &session.inputs[0].value_type,
+            ValueType::Sequence(element) if matches!(**element, ValueType::Tensor { .. })
+        ));
+        assert!(matches!(
+            session.inputs[2].value_type,
+            ValueType::Map {
+                key_type: TensorElementDataType::Int64,
+                ..
+            }
+        ));
+
+        let inputs = vec![
+            InputValue::Sequence(vec![
+                ndarray::arr1(&[1.0_f32, 2.0]).into(),
+                ndarray::arr1(&[3.0_f32]).into(),
+            ]),
+            ndarray::arr1(&[4.0_f32, 5.0, 6.0]).into(),
+            InputValue::Map {
+                keys: ndarray::arr1(&[3_i64, 1]).into(),
+                values: ndarray::arr1(&[0.5_f32, 2.0]).into(),
+            },
+        ];
+        let mut outputs = session.run_values(inputs).unwrap();
+
+        let extended = outputs[0].try_extract_sequence::<f32>().unwrap();
+        assert_eq!(
+            extended,
+            vec![
+                ndarray::arr1(&[1.0_f32, 2.0]).into_dyn(),
+                ndarray::arr1(&[3.0_f32]).into_dyn(),
+                ndarray::arr1(&[4.0_f32, 5.0, 6.0]).into_dyn(),
+            ]
+        );
+
+        assert!(matches!(
+            outputs[1].try_extract_sequence::<f32>(),
+            Err(OrtError::NonMatchingValueKind {
+                requested: ValueKind::Sequence,
+                kind: ValueKind::Tensor,
+            })
+        ));
+        let vector = outputs.pop().unwrap().try_into_tensor().unwrap();
+        assert_eq!(
+            *vector.try_extract::<f32>().unwrap(),
+            ndarray::arr2(&[[2.0_f32, 0.0, 0.5]]).into_dyn().view()
+        );
+
+        // A map given where a sequence is expected
+        let inputs = vec![
+            InputValue::Map {
+                keys: ndarray::arr1(&[1_i64]).into(),
+                values: ndarray::arr1(&[1.0_f32]).into(),
+            },
+            ndarray::arr1(&[4.0_f32]).into(),
+            InputValue::Map {
+                keys: ndarray::arr1(&[1_i64]).into(),
+                values: ndarray::arr1(&[1.0_f32]).into(),
+            },
+        ];
+        assert!(matches!(
+            session.run_values(inputs),
+            Err(OrtError::NonMatchingValueType { name, .. }) if name == "tensors"
+        ));
+    }
+
+    #[test]
+    fn custom_op() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex,
+        };
+
+        /// Computes `alpha * x + beta`
+        struct Affine {
+            labels: Arc<Mutex<Vec<String>>>,
+        }
+
+        struct AffineKernel {
+            alpha: f32,
+            beta: f32,
+        }
+
+        impl CustomOp for Affine {
+            type Kernel = AffineKernel;
+
+            fn name(&self) -> &str {
+                "Affine"
+            }
+
+            fn input_types(&self) -> Vec<TensorElementDataType> {
+                vec![TensorElementDataType::Float]
+            }
+
+            fn output_types(&self) -> Vec<TensorElementDataType> {
+                vec![TensorElementDataType::Float]
+            }
+
+            fn create_kernel(&self, info: &KernelInfo) -> Result<AffineKernel, CustomOpError> {
+                self.labels
+                    .lock()
+                    .unwrap()
+                    .push(info.attribute_string("label")?);
+                assert!(info.attribute_f32("missing").is_err());
+                Ok(AffineKernel {
+                    alpha: info.attribute_f32("alpha")?,
+                    beta: info.attribute_i64("beta")? as f32,
+                })
+            }
+        }
+
+        impl CustomOpKernel for AffineKernel {
+            fn compute(&self, context: &mut KernelContext) -> Result<(), CustomOpError> {
+                assert_eq!(context.input_count()?, 1);
+                assert_eq!(context.output_count()?, 1);
+                let x = context.input::<f32>(0)?;
+                assert!(context.input::<i64>(0).is_err());
+                let mut y = context.output::<f32>(0, x.shape())?;
+                y.assign(&x.mapv(|x| self.alpha * x + self.beta));
+                Ok(())
+            }
+        }
+
+        /// Panics when computed
+        struct Panicking {
+            computed: Arc<AtomicBool>,
+        }
+
+        impl CustomOp for Panicking {
+            type Kernel = PanickingKernel;
+
+            fn name(&self) -> &str {
+                "Affine"
+            }
+
+            fn input_types(&self) -> Vec<TensorElementDataType> {
+                vec![TensorElementDataType::Float]
+            }
+
+            fn output_types(&self) -> Vec<TensorElementDataType> {
+                vec![TensorElementDataType::Float]
+            }
+
+            fn create_kernel(&self, _: &KernelInfo) -> Result<PanickingKernel, CustomOpError> {
+                Ok(PanickingKernel(Arc::clone(&self.computed)))
+            }
+        }
+
+        struct PanickingKernel(Arc<AtomicBool>);
+
+        impl CustomOpKernel for PanickingKernel {
+            fn compute(&self, _: &mut KernelContext) -> Result<(), CustomOpError> {
+                self.0.store(true, Ordering::SeqCst);
+                panic!("Custom operator panic");
+            }
+        }
+
+        /// Fails to create its kernels
+        struct Failing;
+
+        impl CustomOp for Failing {
+            type Kernel = PanickingKernel;
+
+            fn name(&self) -> &str {
+                "Affine"
+            }
+
+            fn input_types(&self) -> Vec<TensorElementDataType> {
+                vec![TensorElementDataType::Float]
+            }
+
+            fn output_types(&self) -> Vec<TensorElementDataType> {
+                vec![TensorElementDataType::Float]
+            }
+
+            fn create_kernel(&self, _: &KernelInfo) -> Result<PanickingKernel, CustomOpError> {
+                Err("missing weights".into())
+            }
+        }
+
+        let environment = test_environment();
+        let model_path = model_path("custom_op.onnx");
+
+        // The model's `Affine` node has the attributes `alpha = 2.0`, `beta = 1` and
+        // `label = "affine"`
+        assert!(environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(&model_path)
+            .is_err());
+
+        let labels = Arc::new(Mutex::new(Vec::new()));
+        let domain = CustomOpDomain::new("ai.onnxruntime-rs.test")
+            .unwrap()
+            .with_op(Affine {
+                labels: Arc::clone(&labels),
+            })
+            .unwrap();
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_custom_op_domain(domain)
+            .unwrap()
+            .with_model_from_file(&model_path)
+            .expect("Could not open model from file");
+        assert_eq!(*labels.lock().unwrap(), vec!["affine".to_string()]);
+
+        let x = ndarray::arr1(&[1.0_f32, 2.0, 3.0]);
+        let outputs = session.run(vec![x.view()]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*y, ndarray::arr1(&[3.0_f32, 5.0, 7.0]).into_dyn().view());
+
+        let computed = Arc::new(AtomicBool::new(false));
+        let domain = CustomOpDomain::new("ai.onnxruntime-rs.test")
+            .unwrap()
+            .with_op(Panicking {
+                computed: Arc::clone(&computed),
+            })
+            .unwrap();
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_custom_op_domain(domain)
+            .unwrap()
+            .with_model_from_file(&model_path)
+            .expect("Could not open model from file");
+        // The panic is caught instead of unwinding into the runtime, and reported by the call
+        match session.run(vec![x.view()]) {
+            Err(OrtError::CustomOp(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert!(errors[0].contains("panicked: Custom operator panic"));
+            }
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        }
+        assert!(computed.load(Ordering::SeqCst));
+        assert!(session.run(vec![x.view()]).is_err());
+
+        // The outputs allocated by the runtime are released before the error is reported
+        let mut inputs: HashMap<&str, InputTensor> = HashMap::new();
+        inputs.insert("x", x.view().into());
+        assert!(matches!(
+            session.run_with(inputs, &["y"]),
+            Err(OrtError::CustomOp(errors)) if errors.len() == 1
+        ));
+        assert!(matches!(
+            session.run_values(vec![InputValue::Tensor(x.view().into())]),
+            Err(OrtError::CustomOp(errors)) if errors.len() == 1
+        ));
+
+        let domain = CustomOpDomain::new("ai.onnxruntime-rs.test")
+            .unwrap()
+            .with_op(Failing)
+            .unwrap();
+        let result = environment
+            .new_session_builder()
+            .unwrap()
+            .with_custom_op_domain(domain)
+            .unwrap()
+            .with_model_from_file(&model_path);
+        match result {
+            Err(OrtError::CustomOp(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert!(errors[0].contains("missing weights"));
+            }
+            result => panic!("Unexpected result: {:?}", result.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn custom_ops_library() {
+        use std::process::Command;
+
+        /// Compile `tests/data/custom_ops_library.rs` into the shared library `name`
+        fn build_library(out_dir: &Path, name: &str, cfgs: &[&str]) -> PathBuf {
+            let library_path = out_dir.join(format!(
+                "{}{}{}",
+                std::env::consts::DLL_PREFIX,
+                name,
+                std::env::consts::DLL_SUFFIX
+            ));
+            let mut command =
+                Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()));
+            command
+                .args(["--crate-type", "cdylib", "--edition", "2018", "-o"])
+                .arg(&library_path)
+                .arg(
+                    Path::new(env!("CARGO_MANIFEST_DIR"))
+                        .join("tests")
+                        .join("data")
+                        .join("custom_ops_library.rs"),
+                );
+            for cfg in cfgs {
+                command.args(["--cfg", cfg]);
+            }
+            let status = command.status().expect("Could not run rustc");
+            assert!(status.success(), "Could not compile the custom ops library");
+            library_path
+        }
+
+        let out_dir = std::env::temp_dir().join(format!(
+            "onnxruntime-rs-custom-ops-library-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&out_dir).unwrap();
+        let library_path = build_library(&out_dir, "custom_ops", &[]);
+        let no_entry_point_path = build_library(
+            &out_dir,
+            "custom_ops_no_entry_point",
+            &["missing_entry_point"],
+        );
+
+        let environment = test_environment();
+
+        let missing_path = out_dir.join("missing_library.so");
+        match environment
+            .new_session_builder()
+            .unwrap()
+            .with_custom_ops_library(&missing_path)
+        {
+            Err(OrtError::FileDoesNotExists { filename }) => assert_eq!(filename, missing_path),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        // A bare name is looked up by the system's library loader
+        let missing_name = format!(
+            "{}onnxruntime_rs_missing_custom_ops{}",
+            std::env::consts::DLL_PREFIX,
+            std::env::consts::DLL_SUFFIX
+        );
+        match environment
+            .new_session_builder()
+            .unwrap()
+            .with_custom_ops_library(&missing_name)
+        {
+            Err(OrtError::FileDoesNotExists { filename }) => {
+                assert_eq!(filename, Path::new(&missing_name))
+            }
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        match environment
+            .new_session_builder()
+            .unwrap()
+            .with_custom_ops_library(&no_entry_point_path)
+        {
+            Err(OrtError::CustomOpsLibrary { path, .. }) => assert_eq!(path, no_entry_point_path),
+            other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+        }
+
+        // The model's `Negate` node is implemented by the library
+        let model_path = model_path("custom_ops_library.onnx");
+        assert!(environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(&model_path)
+            .is_err());
+
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_custom_ops_library(&library_path)
+            .unwrap()
+            .with_model_from_file(&model_path)
+            .expect("Could not open model from file");
+        let x = ndarray::arr1(&[1.0_f32, -2.0, 3.0]);
+        let outputs = session.run(vec![x]).unwrap();
+        let y = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*y, ndarray::arr1(&[-1.0_f32, 2.0, -3.0]).into_dyn().view());
+
+        // The library is unloaded with the session
+        drop(outputs);
+        drop(session);
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+
+    #[test]
+    fn execution_providers_fallback() {
+        let environment = test_environment();
+        let model_path = model_path("add.onnx");
+        let available = onnxruntime::available_providers().unwrap();
+        assert!(available.iter().any(|name| name == "CPUExecutionProvider"));
+
+        let cuda = ExecutionProvider::Cuda(CudaProviderOptions::default());
+        let open_vino = ExecutionProvider::OpenVino(OpenVinoProviderOptions::default());
+
+        // Whatever the host, the session can be built and falls back to the CPU
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_execution_providers(
+                vec![cuda.clone(), open_vino.clone()],
+                ProviderFallback::Skip,
+            )
+            .unwrap()
+            .with_model_from_file(&model_path)
+            .expect("Could not open model from file");
+        assert_eq!(
+            session.execution_providers.last(),
+            Some(&ExecutionProvider::Cpu)
+        );
+        assert!(session
+            .execution_providers
+            .iter()
+            .all(|provider| available.iter().any(|name| name == provider.name())));
+
+        let a = ndarray::arr2(&[[1.0_f32, 2.0, 3.0]]);
+        let b = ndarray::arr2(&[[4.0_f32, 5.0, 6.0]]);
+        let outputs = session.run(vec![a.view(), b.view()]).unwrap();
+        let c = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*c, ndarray::arr2(&[[5.0_f32, 7.0, 9.0]]).into_dyn().view());
+
+        // Without GPU support in the runtime library, requiring CUDA fails
+        let result = environment
+            .new_session_builder()
+            .unwrap()
+            .with_execution_providers(vec![cuda.clone()], ProviderFallback::Error);
+        if !cuda.is_available().unwrap() {
+            match result {
+                Err(OrtError::ExecutionProviderNotAvailable { provider, .. }) => {
+                    assert_eq!(provider, "CUDAExecutionProvider")
+                }
+                other => panic!("Unexpected result: {:?}", other.map(|_| ())),
+            }
+        }
+
+        // The CPU provider is always available
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_execution_providers(vec![ExecutionProvider::Cpu], ProviderFallback::Error)
+            .unwrap()
+            .with_model_from_file(&model_path)
+            .expect("Could not open model from file");
+        assert_eq!(session.execution_providers, vec![ExecutionProvider::Cpu]);
+    }
+
+    #[test]
+    fn upsample_into_output_buffers() {
+        let environment = test_environment();
+
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 1.0);
+
+        // The output has dynamic dimensions; it is copied in the buffer once its shape is known
+        let mut output = ndarray::Array::<f32, _>::zeros((1, 20, 40, 3));
+        session
+            .run_into(vec![array.view()], vec![output.view_mut()])
+            .unwrap();
+        assert!(output.iter().all(|&x| x == 1.0));
+
+        let mut output = ndarray::Array::<f32, _>::zeros((1, 10, 10, 3));
+        match session.run_into(vec![array.view()], vec![output.view_mut()]) {
+            Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::DynamicOutputShape {
+                    output_buffer,
+                    inference_output,
+                    ..
+                },
+            )) => {
+                assert_eq!(output_buffer, [1, 10, 10, 3]);
+                assert_eq!(inference_output, [1, 20, 40, 3]);
+            }
+            result => panic!("Unexpected result: {:?}", result),
+        }
+
+        let mut output = ndarray::Array::<f32, _>::zeros((20, 40, 3));
+        assert!(matches!(
+            session.run_into(vec![array.view()], vec![output.view_mut()]),
+            Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::OutputShape { .. }
+            ))
+        ));
+
+        let mut output = ndarray::Array::<i64, _>::zeros((1, 20, 40, 3));
+        assert!(matches!(
+            session.run_into(vec![array.view()], vec![output.view_mut()]),
+            Err(OrtError::NonMatchingDataType { .. })
+        ));
+
+        let no_buffers: Vec<OutputBuffer> = Vec::new();
+        assert!(matches!(
+            session.run_into(vec![array.view()], no_buffers),
+            Err(OrtError::NonMatchingDimensions(
+                NonMatchingDimensionsError::OutputsCount { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn upsample_with_borrowed_inputs() {
+        let environment = test_environment();
+
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 1.0);
+        {
+            let outputs = session.run(vec![array.view()]).unwrap();
+            assert_eq!(outputs[0].shape(), [1, 20, 40, 3]);
+        }
+
+        // Non standard layout views are copied
+        let transposed = ndarray::Array::<f32, _>::from_elem((3, 20, 10, 1), 1.0);
+        {
+            let outputs = session
+                .run(vec![transposed.view().reversed_axes()])
+                .unwrap();
+            assert_eq!(outputs[0].shape(), [1, 20, 40, 3]);
+        }
+
+        let data = vec![2.0_f32; 10 * 20 * 3];
+        let input = InputTensor::from_slice(&data, &[1, 10, 20, 3]).unwrap();
+        let outputs = session.run(vec![input]).unwrap();
+        let output = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(output.shape(), [1, 20, 40, 3]);
+        assert!(output.iter().all(|&x| x == 2.0));
+    }
+
+    #[test]
+    fn upsample_with_io_binding() {
+        let environment = test_environment();
+
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        let mut output = ndarray::Array::<f32, _>::zeros((1, 20, 40, 3));
+        let mut wrong_shape = ndarray::Array::<f32, _>::zeros((20, 40, 3));
+        {
+            let mut io_binding = session.io_binding().unwrap();
+            io_binding
+                .bind_output("Identity:0", output.view_mut())
+                .unwrap();
+
+            // Inputs stay bound across runs until bound again
+            let array = ndarray::Array::<f32, _>::from_elem((1, 10, 20, 3), 2.0);
+            io_binding
+                .bind_input("up_sampling2d_input:0", array)
+                .unwrap();
+            io_binding.run().unwrap();
+            io_binding
+                .run_with_options(&RunOptions::new().unwrap())
+                .unwrap();
+
+            assert_eq!(io_binding.output_names().unwrap(), ["Identity:0"]);
+            {
+                let outputs = io_binding.outputs().unwrap();
+                assert_eq!(outputs[0].shape(), [1, 20, 40, 3]);
+            }
+
+            let array = ndarray::Array::<i64, _>::zeros((1, 10, 20, 3));
+            assert!(matches!(
+                io_binding.bind_input("up_sampling2d_input:0", array),
+                Err(OrtError::NonMatchingDataType { .. })
+            ));
+            let array = ndarray::Array::<f32, _>::zeros((1, 10, 20, 3));
+            assert!(matches!(
+                io_binding.bind_input("unknown", array),
+                Err(OrtError::UnknownInputName { .. })
+            ));
+            assert!(matches!(
+                io_binding.bind_output("Identity:0", wrong_shape.view_mut()),
+                Err(OrtError::NonMatchingDimensions(
+                    NonMatchingDimensionsError::OutputShape { .. }
+                ))
+            ));
+        }
+        // The runtime wrote the output in our buffer
+        assert!(output.iter().all(|&x| x == 2.0));
+
+        // Outputs bound to a memory location are allocated by the runtime
+        let memory_info = MemoryInfo::new(AllocatorType::Arena, MemType::Default).unwrap();
+        let mut io_binding = session.io_binding().unwrap();
+        io_binding
+            .bind_output_to_device("Identity:0", &memory_info)
+            .unwrap();
+        let array = ndarray::Array::<f32, _>::from_elem((1, 5, 5, 3), 3.0);
+        io_binding
+            .bind_input("up_sampling2d_input:0", array)
+            .unwrap();
+        io_binding.run().unwrap();
+
+        let outputs = io_binding.outputs().unwrap();
+        let output = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(output.shape(), [1, 10, 10, 3]);
+        assert!(output.iter().all(|&x| x == 3.0));
+    }
+
+    #[test]
+    fn upsample_with_run_options() {
+        let environment = test_environment();
+
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        let run_options = RunOptions::new()
+            .unwrap()
+            .with_run_tag("upsample_with_run_options")
+            .unwrap()
+            .with_log_level(LoggingLevel::Verbose)
+            .unwrap();
+
+        {
+            let array = ndarray::Array::<f32, _>::zeros((1, 10, 20, 3));
+            let outputs = session.run_with_options(&run_options, vec![array]).unwrap();
+            assert_eq!(outputs[0].shape(), [1, 20, 40, 3]);
+        }
+
+        // A run using cancelled options fails
+        let cancel_handle = run_options.cancel_handle();
+        std::thread::spawn(move || cancel_handle.cancel().unwrap())
+            .join()
+            .unwrap();
+        {
+            let array = ndarray::Array::<f32, _>::zeros((1, 10, 20, 3));
+            let result = session.run_with_options(&run_options, vec![array]);
+            assert!(matches!(result, Err(OrtError::RunCancelled)));
+        }
+
+        run_options.unset_terminate().unwrap();
+        let array = ndarray::Array::<f32, _>::zeros((1, 10, 20, 3));
+        let outputs = session.run_with_options(&run_options, vec![array]).unwrap();
+        assert_eq!(outputs[0].shape(), [1, 20, 40, 3]);
+    }
+
+    #[test]
+    fn upsample_with_names() {
+        let environment = test_environment();
+
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("upsample.onnx"))
+            .expect("Could not open model from file");
+
+        assert_eq!(session.inputs[0].name, "up_sampling2d_input:0");
+        assert_eq!(session.outputs[0].name, "Identity:0");
+
+        {
+            let mut inputs = HashMap::new();
+            inputs.insert(
+                "up_sampling2d_input:0",
+                ndarray::Array::<f32, _>::zeros((1, 10, 20, 3)),
+            );
+            let outputs = session.run_with(inputs, &["Identity:0"]).unwrap();
+
+            assert_eq!(outputs.len(), 1);
+            assert_eq!(outputs[0].data_type(), TensorElementDataType::Float);
+            assert_eq!(outputs[0].shape(), [1, 20, 40, 3]);
+            assert_eq!(
+                outputs[0].try_extract::<f32>().unwrap().shape(),
+                [1, 20, 40, 3]
+            );
+
+            // Requesting the wrong element type fails instead of reinterpreting the data
+            assert!(matches!(
+                outputs[0].try_extract::<i64>(),
+                Err(OrtError::NonMatchingOutputDataType {
+                    requested_type: TensorElementDataType::Int64,
+                    output_type: TensorElementDataType::Float,
+                })
+            ));
+        }
+
+        // Unknown input and output names are rejected before running the model
+        let mut inputs = HashMap::new();
+        inputs.insert("unknown", ndarray::Array::<f32, _>::zeros((1, 10, 20, 3)));
+        {
+            let result = session.run_with(inputs, &["Identity:0"]);
+            assert!(matches!(
+                result,
+                Err(OrtError::UnknownInputName { name }) if name == "unknown"
+            ));
+        }
+
+        {
+            let mut inputs = HashMap::new();
+            inputs.insert(
+                "up_sampling2d_input:0",
+                ndarray::Array::<f32, _>::zeros((1, 10, 20, 3)),
+            );
+            let result = session.run_with(inputs, &["unknown"]);
+            assert!(matches!(
+                result,
+                Err(OrtError::UnknownOutputName { name }) if name == "unknown"
+            ));
+        }
+
+        // Element type of the inputs is checked against the model
+        let mut inputs = HashMap::new();
+        inputs.insert(
+            "up_sampling2d_input:0",
+            InputTensor::from(ndarray::Array::<i64, _>::zeros((1, 10, 20, 3))),
+        );
+        let result = session.run_with(inputs, &["Identity:0"]);
+        assert!(matches!(
+            result,
+            Err(OrtError::NonMatchingDataType {
+                inference_type: TensorElementDataType::Int64,
+                model_type: TensorElementDataType::Float,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn mixed_input_types() {
+        let environment = test_environment();
+        // Computes `sum = x + y` and `y_doubled = 2 * y`, with `x` of type `f32` and `y` of type
+        // `i64`
+        let session = environment
+            .new_session_builder()
+            .unwrap()
+            .with_model_from_file(model_path("mixed.onnx"))
+            .expect("Could not open model from file");
+
+        let x = ndarray::arr1(&[0.5_f32, 1.5, 2.5]);
+        let y = ndarray::arr1(&[1_i64, 2, 3]);
+        let outputs = session
+            .run(vec![
+                InputTensor::from(x.view()),
+                InputTensor::from(y.view()),
+            ])
+            .unwrap();
+        assert_eq!(outputs[0].data_type(), TensorElementDataType::Float);
+        let sum = outputs[0].try_extract::<f32>().unwrap();
+        assert_eq!(*sum, ndarray::arr1(&[1.5_f32, 3.5, 5.5]).into_dyn().view());
+        assert_eq!(outputs[1].data_type(), TensorElementDataType::Int64);
+        let y_doubled = outputs[1].try_extract::<i64>().unwrap();
+        assert_eq!(*y_doubled, ndarray::arr1(&[2_i64, 4, 6]).into_dyn().view());
+
+        // Same through named inputs, in any order
+        let mut inputs = HashMap::new();
+        inputs.insert("y", InputTensor::from(y));
+        inputs.insert("x", InputTensor::from(x));
+        let outputs = session.run_with(inputs, &["y_doubled"]).unwrap();
+        let y_doubled = outputs[0].try_extract::<i64>().unwrap();
+        assert_eq!(*y_doubled, ndarray::arr1(&[2_i64, 4, 6]).into_dyn().view());
+    }
 }
 
 fn get_imagenet_labels() -> Result<Vec<String>, io::Error> {